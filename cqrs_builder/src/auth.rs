@@ -0,0 +1,260 @@
+//! 根据使用的api推断需要的权限
+//!
+//! 见[酷q文档](https://docs.cqp.im/dev/v9/app.json/auth/)
+
+use std::collections::BTreeSet;
+
+use crate::scan::Sources;
+
+/// 权限id，说明，需要该权限的api函数
+pub(crate) const AUTHS: &[(usize, &str, &[&str])] = &[
+    (20, "取Cookies", &["get_cookies", "get_cookies_v2", "get_csrf_token"]),
+    (30, "接收语音", &["get_record_v2"]),
+    (101, "发送群消息", &["send_group_msg"]),
+    (103, "发送讨论组消息", &["send_discuss_msg"]),
+    (106, "发送私聊消息", &["send_private_msg"]),
+    (110, "发送赞", &["send_like_v2"]),
+    (120, "置群员移除", &["set_group_kick"]),
+    (121, "置群员禁言", &["set_group_ban"]),
+    (122, "置群管理员", &["set_group_admin"]),
+    (123, "置全群禁言", &["set_group_whole_ban"]),
+    (124, "置匿名群员禁言", &["set_group_anonymous_ban"]),
+    (125, "置群匿名设置", &["set_group_anonymous"]),
+    (126, "置群成员名片", &["set_group_card"]),
+    (127, "置群退出", &["set_group_leave"]),
+    (128, "置群成员专属头衔", &["set_group_special_title"]),
+    (130, "取群成员信息", &["get_group_member_info_v2"]),
+    (131, "取陌生人信息", &["get_stranger_info"]),
+    (132, "取群信息", &["get_group_info"]),
+    (140, "置讨论组退出", &["set_discuss_leave"]),
+    (150, "置好友添加请求", &["set_friend_add_request"]),
+    (151, "置群添加请求", &["set_group_add_request_v2"]),
+    (160, "取群成员列表", &["get_group_member_list"]),
+    (161, "取群列表", &["get_group_list"]),
+    (162, "取好友列表", &["get_friend_list"]),
+    (180, "撤回消息", &["delete_msg"]),
+];
+
+const SEND: &[&str] = &["SendMessage", "prelude", "message"];
+const MESSAGE_EVENTS: &[&str] = &[
+    "PrivateMessageEvent",
+    "GroupMessageEvent",
+    "DiscussMessageEvent",
+    "GroupUploadEvent",
+];
+
+/// sdk中调用了api的方法，权限，方法所在的类型
+///
+/// 只按方法名匹配，`send`、`delete`这类常见的方法名还需要源码中用到了所在的类型（或者从sdk导入了该类型），
+/// 为空时不检查。
+const METHODS: &[(&str, &[usize], &[&str])] = &[
+    ("send_message", &[101, 103, 106], SEND),
+    ("send", &[101, 103, 106], SEND),
+    ("send_rps", &[101, 103, 106], SEND),
+    ("reply_file", &[101], MESSAGE_EVENTS),
+    ("delete", &[180], &[
+        "Message",
+        "PrivateMessageEvent",
+        "GroupMessageEvent",
+        "DiscussMessageEvent",
+    ]),
+    ("leave", &[140], &["Discuss", "DiscussMessageEvent"]),
+    ("revoke", &[121, 123], &["GroupBanEvent"]),
+    ("ban", &[124], &["Anonymous", "GroupMessageEvent"]),
+    ("set_ban", &[121], &[]),
+    ("set_kick", &[120], &[]),
+    ("set_whole_ban", &[123], &[]),
+    ("set_can_anonymous", &[125], &[]),
+    ("get_member", &[130], &[]),
+    ("update_member", &[130], &[]),
+    ("get_members", &[160], &[]),
+    ("update", &[131, 132], &["Group", "User"]),
+];
+
+/// 事件类型，权限
+type EventAuths = &'static [(&'static str, usize)];
+
+/// 事件的方法，事件类型和它需要的权限
+///
+/// `reply`只会发送到事件的来源，只申请用到的事件类型对应的权限。
+const EVENT_METHODS: &[(&[&str], EventAuths)] = &[
+    (&["reply", "reply_at"], &[
+        ("PrivateMessageEvent", 106),
+        ("GroupMessageEvent", 101),
+        ("GroupUploadEvent", 101),
+        ("DiscussMessageEvent", 103),
+    ]),
+    (&["handle"], &[("AddFriendRequestEvent", 150), ("AddGroupRequestEvent", 151)]),
+];
+
+/// sdk中调用了api的函数
+const FUNCTIONS: &[(&str, &[usize])] = &[
+    ("Group::new", &[132]),
+    ("User::new", &[131]),
+    ("get_group", &[132]),
+    ("get_group_member", &[130]),
+    ("get_user", &[131]),
+    ("get_friends", &[162]),
+];
+
+/// 获取[`Lazy`](https://docs.rs/coolq-sdk-rust/latest/coolq_sdk_rust/targets/lazy/struct.Lazy.html)中信息的方法
+const LAZY_METHODS: &[&str] = &["get", "get_async", "into_inner"];
+
+/// 事件中延迟获取的群和用户信息，调用了[`LAZY_METHODS`]时才需要
+const EVENTS: &[(&str, &[usize])] = &[
+    ("PrivateMessageEvent", &[131]),
+    ("GroupMessageEvent", &[130, 131, 132]),
+    ("DiscussMessageEvent", &[131]),
+    ("GroupUploadEvent", &[130, 131, 132]),
+    ("GroupAdminEvent", &[130, 131, 132]),
+    ("GroupMemberDecreaseEvent", &[130, 131, 132]),
+    ("GroupMemberIncreaseEvent", &[130, 131, 132]),
+    ("GroupBanEvent", &[130, 131, 132]),
+    ("FriendAddEvent", &[131]),
+    ("AddFriendRequestEvent", &[131]),
+    ("AddGroupRequestEvent", &[131, 132]),
+];
+
+/// [OneBot](https://docs.rs/coolq-sdk-rust/latest/coolq_sdk_rust/onebot/)可以调用几乎全部api，
+/// 用到了`onebot`模块时申请全部auth
+fn uses_onebot(sources: &Sources) -> bool {
+    sources.names.contains("onebot")
+        || sources.names.contains("OneBot")
+        || sources.paths.iter().any(|path| path.starts_with("onebot::"))
+}
+
+pub(crate) fn is_known(auth: usize) -> bool {
+    AUTHS.iter().any(|(id, _, _)| *id == auth)
+}
+
+pub(crate) fn name(auth: usize) -> &'static str {
+    AUTHS
+        .iter()
+        .find(|(id, _, _)| *id == auth)
+        .map_or("unknown", |(_, name, _)| name)
+}
+
+pub(crate) fn all() -> Vec<usize> {
+    AUTHS.iter().map(|(id, _, _)| *id).collect()
+}
+
+/// 源码中用到的权限
+pub(crate) fn infer(sources: &Sources) -> BTreeSet<usize> {
+    let mut auths = BTreeSet::new();
+    if uses_onebot(sources) {
+        auths.extend(all());
+    }
+    for (id, _, funcs) in AUTHS {
+        if funcs.iter().any(|func| sources.paths.contains(*func)) {
+            auths.insert(*id);
+        }
+    }
+    for (func, ids) in FUNCTIONS {
+        if sources.paths.contains(*func) {
+            auths.extend(ids.iter());
+        }
+    }
+    let uses = |name: &&str| {
+        sources.names.contains(*name) || sources.listeners.iter().any(|l| l.event == *name)
+    };
+    for (method, ids, types) in METHODS {
+        if sources.methods.contains(*method) && (types.is_empty() || types.iter().any(uses)) {
+            auths.extend(ids.iter());
+        }
+    }
+    for (methods, events) in EVENT_METHODS {
+        if methods.iter().any(|method| sources.methods.contains(*method)) {
+            auths.extend(events.iter().filter(|(event, _)| uses(event)).map(|(_, id)| *id));
+        }
+    }
+    if LAZY_METHODS.iter().any(|method| sources.methods.contains(*method)) {
+        for listener in &sources.listeners {
+            if let Some((_, ids)) = EVENTS.iter().find(|(event, _)| *event == listener.event) {
+                auths.extend(ids.iter());
+            }
+        }
+    }
+    auths
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn infer_code(code: &str) -> Vec<usize> {
+        let mut sources = Sources::default();
+        sources.scan_file(&syn::parse_file(code).unwrap());
+        infer(&sources).into_iter().collect()
+    }
+
+    #[test]
+    fn infer_table() {
+        let cases: &[(&str, &[usize])] = &[
+            ("fn a() { coolq_sdk_rust::api::send_group_msg(1, \"hi\"); }", &[101]),
+            ("fn a() { api::delete_msg(1); }", &[180]),
+            ("fn a() { let g = Group::new(1); }", &[132]),
+            ("fn a() { format!(\"{:?}\", get_cookies(\"\")); }", &[20]),
+            ("fn a(group: &Group) { group.set_ban(1, 60); }", &[121]),
+            // 常见的方法名需要用到sdk的类型
+            ("fn a(tx: Sender<i32>) { tx.send(1); }", &[]),
+            ("fn a(map: Cache) { map.delete(1); map.update(); }", &[]),
+            ("fn a(v: Thing) { v.ban(); v.handle(); v.leave(); }", &[]),
+            (
+                "use coolq_sdk_rust::prelude::*; fn a(group: Group) { group.send(\"hi\"); }",
+                &[101, 103, 106],
+            ),
+            ("fn a(msg: Message) { msg.delete(); }", &[180]),
+            ("fn a(mut user: User) { user.update(); }", &[131, 132]),
+            (
+                "use coolq_sdk_rust::prelude::*; #[listener] fn a(e: AddFriendRequestEvent) { e.handle(true, \"\"); }",
+                &[150],
+            ),
+            // reply只需要事件来源的权限
+            (
+                "use coolq_sdk_rust::prelude::*; #[listener] fn a(e: PrivateMessageEvent) { e.reply(\"hi\"); }",
+                &[106],
+            ),
+            (
+                "use coolq_sdk_rust::prelude::*; #[listener] fn a(e: GroupMessageEvent) { e.reply_at(\"hi\"); }",
+                &[101],
+            ),
+            (
+                "use coolq_sdk_rust::prelude::*; #[listener] fn a(e: DiscussMessageEvent) { e.reply(\"hi\"); }",
+                &[103],
+            ),
+            // 获取了事件中的信息才需要查询权限
+            (
+                "use coolq_sdk_rust::prelude::*; #[listener] fn a(e: GroupBanEvent) { e.group.id(); }",
+                &[],
+            ),
+            (
+                "use coolq_sdk_rust::prelude::*; #[listener] fn a(e: GroupBanEvent) { e.group.get(); }",
+                &[130, 131, 132],
+            ),
+        ];
+        for (code, auths) in cases {
+            assert_eq!(infer_code(code), *auths, "{}", code);
+        }
+    }
+
+    #[test]
+    fn onebot() {
+        let cases = [
+            "use coolq_sdk_rust::onebot::{self, OneBot}; fn a() { module::register(OneBot::DEFAULT); }",
+            "fn a() { coolq_sdk_rust::onebot::start(&Default::default()); }",
+            "use coolq_sdk_rust::onebot; fn a(e: &Event) { onebot::broadcast(e); }",
+        ];
+        for code in &cases {
+            assert_eq!(infer_code(code), all(), "{}", code);
+        }
+    }
+
+    #[test]
+    fn names() {
+        assert!(is_known(101));
+        assert!(!is_known(102));
+        assert_eq!(name(180), "撤回消息");
+        assert_eq!(name(1), "unknown");
+        assert_eq!(all().len(), AUTHS.len());
+    }
+}
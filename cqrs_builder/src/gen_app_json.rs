@@ -1,14 +1,9 @@
 //! 在编译时生成app.json
 //!
-//! 默认情况下，事件回调函数全部为中优先级
+//! `finish`时会扫描`src`目录下的源码，只为定义了的`#[listener]`按其优先级生成事件，
+//! 事件不存在、优先级错误、同一事件同一优先级有多个listener或者缺少`#[coolq_sdk_rust::main]`时会编译失败。
 //!
-//! 若要启用全部优先级，请打开 full-priority feature:
-//!
-//! Cargo.toml
-//! ```toml
-//! [build-dependencies]
-//! cqrs_builder = { version = "0.1", features = ["full-priority"] } # 在dependencies中打开feature，才会生成对应的函数
-//! ```
+//! `full-priority` feature已经不再需要，保留只是为了兼容。
 //!
 //! # Examples
 //! ```should_panic
@@ -24,6 +19,24 @@
 //! }
 //! ```
 //!
+//! ## 菜单和悬浮窗
+//!
+//! `#[menu(name = "...")]`、`#[status(name = "...")]`标注的函数会被自动添加为菜单和悬浮窗，
+//! 不需要再调用`add_menu`、`add_status`。
+//!
+//! 开启`config` feature时会添加重载[配置](https://docs.rs/coolq-sdk-rust/latest/coolq_sdk_rust/config/)的菜单`cqrs_reload_config`，
+//! 需要同时开启coolq-sdk-rust的`config` feature。
+//!
+//! `#[coolq_sdk_rust::main(modules(..))]`中声明了[模块](https://docs.rs/coolq-sdk-rust/latest/coolq_sdk_rust/module/)时，
+//! 会以中优先级为全部事件添加`module_on_*`函数。
+//!
+//! `#[coolq_sdk_rust::main]`还为全部事件导出了`catch_all_on_*`函数，用于调用`listen_all`注册的回调。
+//! 只有源码中调用了`listen_all`或者声明了模块时才会以最高优先级添加，每个事件只会调用一次。
+//! 它们与`highest`的listener同为10000，两者的先后顺序由酷q决定。
+//! 在依赖的库中调用`listen_all`时需要自己`add_event`。
+//!
+//! 可以通过`no_scan`关闭源码扫描，此时需要自己`add_event`和`add_menu`。
+//!
 //! ## 不使用sdk的事件处理，自定义处理函数。
 //! ```should_panic
 //! // build.rs
@@ -37,7 +50,33 @@
 //! }
 //! ```
 //!
-//! ## 不使用sdk默认生成的全部auth，根据需要自己生成
+//! ## 检查
+//!
+//! `finish`时会按[酷q文档](https://docs.cqp.im/dev/v9/app.json/)检查appid、事件类型、优先级、id是否重复、
+//! 菜单和悬浮窗的函数名等，不符合时编译失败并列出全部错误。
+//!
+//! ## 模板
+//!
+//! 可以用`template`读取一个已有的app.json，其中的字段会被合并进来，之后调用的setter会覆盖模板中的值。
+//! ```should_panic
+//! // build.rs
+//! fn main() {
+//!     cqrs_builder::AppJson::new("dev.gugugu.example")
+//!         .template("app.base.json")
+//!         .version("0.0.2".to_owned())
+//!         .finish()
+//! }
+//! ```
+//!
+//! ## 权限
+//!
+//! `finish`时会根据源码中调用的api（包括sdk中调用了api的方法，以及事件中延迟获取的群和用户信息）推断需要的auth，
+//! 手动`add_auth`但没有用到的auth会产生警告。
+//! 推断只看方法名和用到的类型：`reply`按listener的事件类型申请对应的发送权限，调用了`get`等方法时才申请事件中群和用户信息的权限，
+//! `send`无法确定目标时会申请私聊、群、讨论组全部的发送权限。
+//! 声明了模块时无法推断模块中用到的api，会申请全部auth，可以用`remove_auth`去掉不需要的。`add_auth`、`remove_auth`只接受[酷q文档](https://docs.cqp.im/dev/v9/app.json/auth/)中的auth。
+//!
+//! ## 不推断auth，根据需要自己生成
 //! ```should_panic
 //! // build.rs
 //! fn main() {
@@ -53,7 +92,7 @@
 
 use std::{
     env,
-    fs::File,
+    fs::{read_to_string, File},
     io::Write,
     path::Path,
     sync::atomic::{AtomicUsize, Ordering},
@@ -62,6 +101,8 @@ use std::{
 use serde::Serialize;
 use serde_json::{json, Value};
 
+use crate::{auth, scan::Sources, validate};
+
 static EVENT_ID: AtomicUsize = AtomicUsize::new(1);
 
 macro_rules! gen_setters {
@@ -77,41 +118,30 @@ macro_rules! gen_setters {
     };
 }
 
-macro_rules! gen_event_json {
-    ($type: expr, $name: expr, $func_name: expr, $priority: expr, $priority_name: expr) => {
-        json!({
-            "id": EVENT_ID.fetch_add(1, Ordering::SeqCst),
-            "type": $type,
-            "name": format!("{}{}", $name, $priority_name).to_string(),
-            "priority": $priority,
-            "function": format!("{}{}", $func_name, $priority_name).to_string()
-        })
-    };
-}
+/// 事件类型，名字，sdk中的事件，导出函数名
+const EVENTS: &[(usize, &str, &str, &str)] = &[
+    (1001, "酷Q启动", "StartEvent", "on_start"),
+    (1002, "酷Q退出", "ExitEvent", "on_exit"),
+    (1004, "插件停用", "DisableEvent", "on_disable"),
+    (21, "私聊消息", "PrivateMessageEvent", "on_private_msg"),
+    (2, "群消息", "GroupMessageEvent", "on_group_msg"),
+    (4, "讨论组消息", "DiscussMessageEvent", "on_discuss_msg"),
+    (11, "群文件上传", "GroupUploadEvent", "on_group_upload"),
+    (101, "群管理员变动", "GroupAdminEvent", "on_group_admin"),
+    (102, "群成员减少", "GroupMemberDecreaseEvent", "on_group_member_decrease"),
+    (103, "群成员增加", "GroupMemberIncreaseEvent", "on_group_member_increase"),
+    (104, "群禁言", "GroupBanEvent", "on_group_ban"),
+    (201, "好友添加", "FriendAddEvent", "on_friend_add"),
+    (301, "加好友请求", "AddFriendRequestEvent", "on_add_friend_request"),
+    (302, "加群请求／邀请", "AddGroupRequestEvent", "on_add_group_request"),
+];
 
-macro_rules! default_events {
-    ($({type: $type: expr, name: $name: expr, function: $func_name: expr}),*) => {
-        vec![
-            // 特殊处理这4个事件，因为我认为这4个事件没必要分优先级，而且也不应该进行拦截
-            gen_event_json!(1001, "酷Q启动", "on_start", 10000, ""),
-            gen_event_json!(1002, "酷Q退出", "on_exit", 10000, ""),
-            gen_event_json!(1003, "插件启用", "on_enable", 10000, ""),
-            gen_event_json!(1004, "插件停用", "on_disable", 10000, ""),
-            $(
-                #[cfg(feature = "full-priority")]
-                gen_event_json!($type, $name, $func_name, 10000, "_highest"),
-                #[cfg(feature = "full-priority")]
-                gen_event_json!($type, $name, $func_name, 20000, "_high"),
-                #[cfg(feature = "full-priority")]
-                gen_event_json!($type, $name, $func_name, 30000, "_medium"),
-                #[cfg(feature = "full-priority")]
-                gen_event_json!($type, $name, $func_name, 40000, "_low"),
-                #[cfg(not(feature = "full-priority"))]
-                gen_event_json!($type, $name, $func_name, 30000, "_medium")
-            ),*
-        ]
-    };
-}
+const PRIORITIES: &[(&str, usize)] = &[
+    ("highest", 10000),
+    ("high", 20000),
+    ("medium", 30000),
+    ("low", 40000),
+];
 
 #[derive(Serialize)]
 pub struct AppJson {
@@ -124,8 +154,17 @@ pub struct AppJson {
     author: String,
     description: String,
     auth: Vec<usize>,
+    #[serde(skip)]
+    removed_auth: Vec<usize>,
+    #[serde(skip)]
+    default_auth: bool,
     event: Vec<Value>,
-    menu: Vec<Value>
+    menu: Vec<Value>,
+    status: Vec<Value>,
+    #[serde(skip)]
+    scan: bool,
+    #[serde(skip)]
+    default_event: bool
 }
 
 impl AppJson {
@@ -135,23 +174,162 @@ impl AppJson {
         aj
     }
 
+    /// 即使用到了也不申请该auth
     pub fn remove_auth(&mut self, auth: usize) -> &mut Self {
-        self.auth.remove(
-            self.auth
-                .iter()
-                .position(|auth| auth == auth)
-                .expect(format!("auth.{} not found", auth).as_ref()),
-        );
+        assert!(auth::is_known(auth), "unknown auth.{}", auth);
+        self.auth.retain(|a| *a != auth);
+        self.removed_auth.push(auth);
         self
     }
 
+    /// 不推断auth，只使用`add_auth`添加的
     pub fn no_default_auth(&mut self) -> &mut Self {
+        self.default_auth = false;
         self.auth.clear();
         self
     }
 
     pub fn add_auth(&mut self, auth: usize) -> &mut Self {
-        self.auth.push(auth);
+        assert!(auth::is_known(auth), "unknown auth.{}", auth);
+        if !self.auth.contains(&auth) {
+            self.auth.push(auth);
+        }
+        self.removed_auth.retain(|a| *a != auth);
+        self
+    }
+
+    fn add_inferred_auth(&mut self, sources: &Sources) {
+        if sources.has_modules {
+            // 模块中用到的auth无法推断
+            self.auth = auth::all();
+        }
+        let inferred = auth::infer(sources);
+        for auth in &self.auth {
+            if !inferred.contains(auth) && !sources.has_modules {
+                println!(
+                    "cargo:warning=auth.{} ({}) is granted but not used.",
+                    auth,
+                    auth::name(*auth)
+                );
+            }
+        }
+        for auth in &self.removed_auth {
+            if inferred.contains(auth) {
+                println!(
+                    "cargo:warning=auth.{} ({}) is removed but used.",
+                    auth,
+                    auth::name(*auth)
+                );
+            }
+        }
+        self.auth.extend(inferred);
+        let removed = &self.removed_auth;
+        self.auth.retain(|auth| !removed.contains(auth));
+        self.auth.sort();
+        self.auth.dedup();
+    }
+
+    /// 合并一个app.json模板
+    ///
+    /// 模板中的name、version等字段会覆盖之前设置的值，event、menu、status、auth会被追加。
+    /// 模板中的appid只在没有设置appid时使用。
+    pub fn template(&mut self, path: impl AsRef<Path>) -> &mut Self {
+        let path = path.as_ref();
+        println!("cargo:rerun-if-changed={}", path.display());
+        let content = read_to_string(path)
+            .unwrap_or_else(|err| panic!("cannot read template {}: {}", path.display(), err));
+        let template: Value = serde_json::from_str(&content)
+            .unwrap_or_else(|err| panic!("invalid template {}: {}", path.display(), err));
+        let str_field = |key: &str| template.get(key).and_then(Value::as_str).map(str::to_owned);
+        let num_field = |key: &str| template.get(key).and_then(Value::as_u64).map(|n| n as usize);
+        let array_field = |key: &str| {
+            template
+                .get(key)
+                .and_then(Value::as_array)
+                .cloned()
+                .unwrap_or_default()
+        };
+
+        if self.appid.is_empty() {
+            self.appid = str_field("appid").unwrap_or_default();
+        }
+        macro_rules! merge {
+            ($($field: ident: $get: ident),*) => {
+                $(if let Some(value) = $get(stringify!($field)) {
+                    self.$field = value;
+                })*
+            };
+        }
+        merge!(
+            name: str_field,
+            version: str_field,
+            author: str_field,
+            description: str_field,
+            ret: num_field,
+            apiver: num_field,
+            version_id: num_field
+        );
+        for event in array_field("event") {
+            self.add_event(
+                event.get("type").and_then(Value::as_u64).unwrap_or_default() as usize,
+                event.get("name").and_then(Value::as_str).unwrap_or_default(),
+                event.get("priority").and_then(Value::as_u64).unwrap_or_default() as usize,
+                event.get("function").and_then(Value::as_str).unwrap_or_default(),
+            );
+        }
+        self.menu.extend(array_field("menu"));
+        self.status.extend(array_field("status"));
+        for auth in array_field("auth") {
+            if let Some(auth) = auth.as_u64() {
+                self.add_auth(auth as usize);
+            }
+        }
+        self
+    }
+
+    /// 悬浮窗，`period`为刷新周期（毫秒）
+    pub fn add_status(&mut self, name: &str, title: &str, func_name: &str, period: usize) -> &mut Self {
+        let id = self
+            .status
+            .iter()
+            .filter_map(|s| s.get("id").and_then(Value::as_u64))
+            .max()
+            .unwrap_or(0)
+            + 1;
+        self.status.push(json!({
+            "id": id,
+            "name": name,
+            "title": title,
+            "function": func_name,
+            "period": period.to_string()
+        }));
+        self
+    }
+
+    fn validate(&self) {
+        let mut errors = Vec::new();
+        validate::appid(&self.appid, &mut errors);
+        validate::fields(
+            &self.name,
+            &self.version,
+            &self.author,
+            &self.description,
+            self.ret,
+            self.apiver,
+            &mut errors,
+        );
+        validate::events(&self.event, &mut errors);
+        validate::menus(&self.menu, &mut errors);
+        validate::status(&self.status, &mut errors);
+        validate::auths(&self.auth, &mut errors);
+        if !errors.is_empty() {
+            panic!("invalid app.json:\n{}", errors.join("\n"));
+        }
+    }
+
+    /// 不扫描源码
+    pub fn no_scan(&mut self) -> &mut Self {
+        self.scan = false;
         self
     }
 
@@ -163,6 +341,12 @@ impl AppJson {
         self
     }
 
+    fn has_menu(&self, func_name: &str) -> bool {
+        self.menu
+            .iter()
+            .any(|m| m.get("function").and_then(Value::as_str) == Some(func_name))
+    }
+
     /// 事件类型，名字，优先度，函数名字。具体查看[酷q文档](https://docs.cqp.im/dev/v9/app.json/event/)
     pub fn add_event(
         &mut self, _type: usize, name: &str, priority: usize, func_name: &str,
@@ -178,6 +362,7 @@ impl AppJson {
     }
 
     pub fn no_default_event(&mut self) -> &mut Self {
+        self.default_event = false;
         self.event.clear();
         self
     }
@@ -200,7 +385,132 @@ impl AppJson {
         self
     }
 
+    /// sdk的事件和源码中的listener
+    fn add_listener_events(&mut self, sources: &Sources) {
+        let mut errors = Vec::new();
+        if !sources.has_main {
+            errors.push("`#[coolq_sdk_rust::main]` not found.".to_owned());
+        }
+        // 由`#[coolq_sdk_rust::main]`导出
+        self.add_event(1003, "插件启用", 10000, "on_enable");
+        self.add_event(1004, "插件停用", 10000, "on_disable");
+        self.add_event(1002, "酷Q退出", 10000, "on_exit");
+        // 模块的库没有被扫描，可能调用了`listen_all`
+        if sources.has_modules || sources.paths.contains("listen_all") {
+            for (event_type, name, _, func_name) in EVENTS {
+                self.add_event(
+                    *event_type,
+                    &format!("{}_catch_all", name),
+                    10000,
+                    &format!("catch_all_{}", func_name),
+                );
+            }
+        }
+
+        // 由`#[coolq_sdk_rust::main(modules(..))]`导出
+        if sources.has_modules {
+            for (event_type, name, _, func_name) in EVENTS {
+                if *event_type != 1002 && *event_type != 1004 {
+                    self.add_event(
+                        *event_type,
+                        &format!("{}_modules", name),
+                        30000,
+                        &format!("module_{}", func_name),
+                    );
+                }
+            }
+        }
+
+        let mut defined: Vec<(&str, &str, &str)> = Vec::new();
+        for listener in &sources.listeners {
+            let (event_type, name, _, func_name) = match EVENTS
+                .iter()
+                .find(|(_, _, event, _)| *event == listener.event)
+            {
+                Some(event) => event,
+                None => {
+                    errors.push(format!(
+                        "{}: cannot find event `{}`.",
+                        listener.function, listener.event
+                    ));
+                    continue;
+                },
+            };
+            let priority = match PRIORITIES
+                .iter()
+                .find(|(priority, _)| *priority == listener.priority)
+            {
+                Some((_, priority)) => *priority,
+                None => {
+                    errors.push(format!(
+                        "{}: priority can only be {}.",
+                        listener.function,
+                        PRIORITIES.iter().map(|(p, _)| *p).collect::<Vec<_>>().join(",")
+                    ));
+                    continue;
+                },
+            };
+            if let Some((_, _, other)) = defined
+                .iter()
+                .find(|(event, p, _)| *event == listener.event && *p == listener.priority)
+            {
+                errors.push(format!(
+                    "{} and {} both listen to `{}` with priority `{}`.",
+                    other, listener.function, listener.event, listener.priority
+                ));
+                continue;
+            }
+            defined.push((&listener.event, &listener.priority, &listener.function));
+            self.add_event(
+                *event_type,
+                &format!("{}_{}", name, listener.priority),
+                priority,
+                &format!("{}_{}", func_name, listener.priority),
+            );
+        }
+        if !errors.is_empty() {
+            panic!("\n{}", errors.join("\n"));
+        }
+    }
+
     pub fn finish(&mut self) {
+        if self.scan {
+            let sources = Sources::scan();
+            if self.default_event {
+                self.add_listener_events(&sources);
+            }
+            if self.default_auth {
+                self.add_inferred_auth(&sources);
+            }
+            for menu in sources.menus {
+                if !self.has_menu(&menu.function) {
+                    self.add_menu(&menu.name, &menu.function);
+                }
+            }
+            for status in sources.status {
+                let exists = self
+                    .status
+                    .iter()
+                    .any(|s| s.get("function").and_then(Value::as_str) == Some(&status.function));
+                if !exists {
+                    self.add_status(&status.name, &status.title, &status.function, status.period);
+                }
+            }
+        } else if self.default_auth {
+            // 无法推断时申请全部auth
+            let removed = &self.removed_auth;
+            self.auth = auth::all()
+                .into_iter()
+                .filter(|auth| !removed.contains(auth))
+                .collect();
+        }
+        #[cfg(feature = "config")]
+        {
+            if !self.has_menu("cqrs_reload_config") {
+                self.add_menu("重载配置", "cqrs_reload_config");
+            }
+        }
+        self.validate();
         let out_dir = env::var("OUT_DIR").unwrap();
         let app_json = Path::new(&out_dir)
             .parent()
@@ -237,75 +547,66 @@ impl Default for AppJson {
         AppJson {
             appid: "".to_owned(),
             ret: 1,
-            apiver: 9,
+            apiver: validate::APIVER,
             name: String::from("example app"),
             version: String::from("0.0.1"),
             version_id: 1,
             author: String::from("hao are you?"),
             description: String::from("rust sdk example"),
-            event: default_events![
-                {
-                    type: 21,
-                    name: "私聊消息",
-                    function: "on_private_msg"
-                },
-                {
-                    type: 2,
-                    name: "群消息",
-                    function: "on_group_msg"
-                },
-                {
-                    type: 4,
-                    name: "讨论组消息",
-                    function: "on_discuss_msg"
-
-                },
-                {
-                    type: 11,
-                    name: "群文件上传",
-                    function: "on_group_upload"
-                },
-                {
-                    type: 101,
-                    name: "群管理员变动",
-                    function: "on_group_admin"
-                },
-                {
-                    type: 102,
-                    name: "群成员减少",
-                    function: "on_group_member_decrease"
-                },
-                {
-                    type: 103,
-                    name: "群成员增加",
-                    function: "on_group_member_increase"
-                },
-                {
-                    type: 104,
-                    name: "群禁言",
-                    function: "on_group_ban"
-                },
-                {
-                    type: 201,
-                    name: "好友添加",
-                    function: "on_friend_add"
-                },
-                {
-                    type: 301,
-                    name: "加好友请求",
-                    function: "on_add_friend_request"
-                },
-                {
-                    type: 302,
-                    name: "加群请求／邀请",
-                    function: "on_add_group_request"
-                }
-            ],
-            auth: vec![
-                20, 30, 101, 103, 106, 110, 120, 121, 122, 123, 124, 125, 126, 127, 128, 130, 131,
-                132, 140, 150, 151, 160, 161, 162, 180,
-            ],
-            menu: Vec::new()
+            auth: Vec::new(),
+            removed_auth: Vec::new(),
+            default_auth: true,
+            event: Vec::new(),
+            menu: Vec::new(),
+            status: Vec::new(),
+            scan: true,
+            default_event: true
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn catch_all_events(sources: &Sources) -> usize {
+        let mut app = AppJson::new("dev.gugugu.example");
+        app.add_listener_events(sources);
+        app.event
+            .iter()
+            .filter(|e| e["function"].as_str().unwrap().starts_with("catch_all_"))
+            .count()
+    }
+
+    #[test]
+    fn catch_all_only_when_used() {
+        let mut sources = Sources {
+            has_main: true,
+            ..Default::default()
+        };
+        assert_eq!(catch_all_events(&sources), 0);
+        sources.paths.insert("listen_all".to_owned());
+        assert_eq!(catch_all_events(&sources), EVENTS.len());
+        sources.paths.clear();
+        sources.has_modules = true;
+        assert_eq!(catch_all_events(&sources), EVENTS.len());
+    }
+
+    #[test]
+    fn modules_grant_all_auths() {
+        let sources = Sources {
+            has_main: true,
+            has_modules: true,
+            ..Default::default()
+        };
+        let mut app = AppJson::new("dev.gugugu.example");
+        app.remove_auth(20).add_inferred_auth(&sources);
+        let mut expected = auth::all();
+        expected.retain(|auth| *auth != 20);
+        assert_eq!(app.auth, expected);
+
+        let mut app = AppJson::new("dev.gugugu.example");
+        app.add_inferred_auth(&Sources::default());
+        assert!(app.auth.is_empty());
+    }
+}
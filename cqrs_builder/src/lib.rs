@@ -1,3 +1,6 @@
 pub use gen_app_json::AppJson;
 
-mod gen_app_json;
\ No newline at end of file
+mod auth;
+mod gen_app_json;
+mod scan;
+mod validate;
\ No newline at end of file
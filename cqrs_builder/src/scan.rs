@@ -0,0 +1,551 @@
+//! 扫描插件源码中的宏
+//!
+//! 只识别`coolq_sdk_rust::`、`cqrs_macro::`开头的属性，或者从这两个crate导入（包括`prelude::*`）的属性，
+//! 如`#[tokio::main]`不会被当作`#[coolq_sdk_rust::main]`。
+//!
+//! `#[cfg(..)]`按build script中cargo提供的`CARGO_FEATURE_*`、`CARGO_CFG_*`环境变量求值，
+//! 不满足条件的item和`mod`对应的文件会被跳过。
+
+use std::{
+    collections::{HashMap, HashSet},
+    env,
+    fs::{read_dir, read_to_string},
+    path::{Path, PathBuf},
+};
+
+use proc_macro2::{TokenStream, TokenTree};
+use syn::{
+    visit::{self, Visit},
+    Attribute, ExprMethodCall, ExprPath, FnArg, Item, ItemFn, Lit, Macro, Meta, NestedMeta, Type,
+    TypePath, UseTree,
+};
+
+/// 提供属性宏的crate
+const MACRO_CRATES: &[&str] = &["coolq_sdk_rust", "cqrs_macro"];
+
+/// `#[menu(name = "...")]`
+#[derive(Debug)]
+pub(crate) struct Menu {
+    pub name: String,
+    pub function: String,
+}
+
+/// `#[listener(priority = "...", event = "...")]`
+#[derive(Debug)]
+pub(crate) struct Listener {
+    /// `event`参数或第一个参数的类型名
+    pub event: String,
+    pub priority: String,
+    pub function: String,
+}
+
+/// `#[status(name = "...", title = "...", period = ...)]`
+#[derive(Debug)]
+pub(crate) struct Status {
+    pub name: String,
+    pub title: String,
+    pub period: usize,
+    pub function: String,
+}
+
+#[derive(Debug, Default)]
+pub(crate) struct Sources {
+    pub has_main: bool,
+    /// `#[main(modules(..))]`中声明了模块
+    pub has_modules: bool,
+    pub listeners: Vec<Listener>,
+    pub menus: Vec<Menu>,
+    pub status: Vec<Status>,
+    /// 用到的路径的最后一段和最后两段，如`send_group_msg`、`Group::new`
+    pub paths: HashSet<String>,
+    /// 调用过的方法名
+    pub methods: HashSet<String>,
+    /// 用到的类型名，以及从sdk导入的名字和模块（如`prelude`、`SendMessage`）
+    pub names: HashSet<String>,
+}
+
+impl Sources {
+    /// 扫描`CARGO_MANIFEST_DIR/src`下的全部rs文件
+    pub fn scan() -> Sources {
+        let src = PathBuf::from(env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR not found"))
+            .join("src");
+        let mut sources = Sources::default();
+        let mut files = Vec::new();
+        collect_files(&src, &mut files);
+        files.sort();
+        let mut parsed = Vec::new();
+        let mut disabled = Vec::new();
+        for file in files {
+            println!("cargo:rerun-if-changed={}", file.display());
+            let content = read_to_string(&file)
+                .unwrap_or_else(|err| panic!("cannot read {}: {}", file.display(), err));
+            let mut ast = syn::parse_file(&content)
+                .unwrap_or_else(|err| panic!("cannot parse {}: {}", file.display(), err));
+            let dir = module_dir(&file);
+            strip_cfg(&mut ast.items, &dir, &mut disabled);
+            parsed.push((file, ast));
+        }
+        for (file, ast) in parsed {
+            if disabled.iter().any(|path| file.starts_with(path)) {
+                continue;
+            }
+            sources.scan_file(&ast);
+        }
+        sources
+    }
+
+    /// 扫描已经去掉了cfg不满足的item的文件
+    pub(crate) fn scan_file(&mut self, ast: &syn::File) {
+        let mut imports = Imports::default();
+        imports.collect(&ast.items);
+        self.names.extend(imports.modules.iter().cloned());
+        self.names.extend(imports.names.values().cloned());
+        self.scan_items(&ast.items, &imports);
+        self.visit_file(ast);
+    }
+
+    fn scan_items(&mut self, items: &[Item], imports: &Imports) {
+        for item in items {
+            match item {
+                Item::Fn(func) => {
+                    for attr in &func.attrs {
+                        if let Some(name) = imports.macro_name(attr) {
+                            self.scan_attr(&name, attr, func);
+                        }
+                    }
+                },
+                Item::Mod(module) => {
+                    if let Some((_, items)) = &module.content {
+                        self.scan_items(items, imports);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn scan_attr(&mut self, ident: &str, attr: &Attribute, func: &ItemFn) {
+        let func_name = func.sig.ident.to_string();
+        match ident {
+            "main" => {
+                self.has_main = true;
+                self.has_modules = !list_arg(attr, "modules").is_empty();
+            },
+            "listener" => self.listeners.push(Listener {
+                event: string_arg(attr, "event")
+                    .and_then(|event| event.rsplit("::").next().map(str::to_owned))
+                    .or_else(|| event_type(func))
+                    .unwrap_or_default(),
+                priority: string_arg(attr, "priority").unwrap_or_else(|| "medium".to_owned()),
+                function: func_name,
+            }),
+            "menu" => {
+                if let Some(name) = string_arg(attr, "name") {
+                    self.menus.push(Menu {
+                        name,
+                        function: format!("menu_{}", func_name),
+                    });
+                }
+            },
+            "status" => {
+                if let Some(name) = string_arg(attr, "name") {
+                    self.status.push(Status {
+                        title: string_arg(attr, "title").unwrap_or_else(|| name.clone()),
+                        name,
+                        period: int_arg(attr, "period").unwrap_or(1000),
+                        function: format!("status_{}", func_name),
+                    });
+                }
+            },
+            _ => {},
+        }
+    }
+}
+
+impl<'ast> Visit<'ast> for Sources {
+    fn visit_expr_path(&mut self, expr: &'ast ExprPath) {
+        let segments = expr
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        self.add_path(&segments);
+        visit::visit_expr_path(self, expr);
+    }
+
+    fn visit_type_path(&mut self, ty: &'ast TypePath) {
+        if let Some(segment) = ty.path.segments.last() {
+            self.names.insert(segment.ident.to_string());
+        }
+        visit::visit_type_path(self, ty);
+    }
+
+    fn visit_expr_method_call(&mut self, call: &'ast ExprMethodCall) {
+        self.methods.insert(call.method.to_string());
+        visit::visit_expr_method_call(self, call);
+    }
+
+    /// 宏的参数无法解析，只按token匹配
+    fn visit_macro(&mut self, mac: &'ast Macro) {
+        self.scan_tokens(mac.tokens.clone());
+        visit::visit_macro(self, mac);
+    }
+}
+
+impl Sources {
+    fn add_path(&mut self, segments: &[String]) {
+        if let Some(last) = segments.last() {
+            self.paths.insert(last.clone());
+        }
+        if segments.len() >= 2 {
+            self.paths.insert(segments[segments.len() - 2..].join("::"));
+        }
+    }
+
+    fn scan_tokens(&mut self, tokens: TokenStream) {
+        let mut prev: Vec<String> = Vec::new();
+        for token in tokens {
+            match token {
+                TokenTree::Group(group) => {
+                    self.scan_tokens(group.stream());
+                    prev.clear();
+                },
+                TokenTree::Ident(ident) => {
+                    let ident = ident.to_string();
+                    if prev.last().map(String::as_str) == Some(".") {
+                        self.methods.insert(ident.clone());
+                    }
+                    match prev.as_slice() {
+                        [.., ty, colon, colon2] if colon == ":" && colon2 == ":" => {
+                            self.add_path(&[ty.clone(), ident.clone()])
+                        },
+                        _ => self.add_path(std::slice::from_ref(&ident)),
+                    }
+                    prev.push(ident);
+                },
+                TokenTree::Punct(punct) => prev.push(punct.as_char().to_string()),
+                TokenTree::Literal(_) => prev.clear(),
+            }
+        }
+    }
+}
+
+/// 文件中从[`MACRO_CRATES`]导入的名字
+#[derive(Debug, Default)]
+struct Imports {
+    /// 导入后的名字 -> 原来的名字
+    names: HashMap<String, String>,
+    /// `use coolq_sdk_rust::prelude::*`
+    glob: bool,
+    /// 导入路径中的模块，如`prelude`、`message`
+    modules: HashSet<String>,
+}
+
+impl Imports {
+    fn collect(&mut self, items: &[Item]) {
+        for item in items {
+            match item {
+                Item::Use(item) => self.collect_tree(&item.tree, &mut Vec::new()),
+                Item::Mod(module) => {
+                    if let Some((_, items)) = &module.content {
+                        self.collect(items);
+                    }
+                },
+                _ => {},
+            }
+        }
+    }
+
+    fn collect_tree(&mut self, tree: &UseTree, prefix: &mut Vec<String>) {
+        let ours = |prefix: &[String]| {
+            prefix
+                .first()
+                .is_some_and(|first| MACRO_CRATES.contains(&first.as_str()))
+        };
+        match tree {
+            UseTree::Path(path) => {
+                prefix.push(path.ident.to_string());
+                if ours(prefix) {
+                    self.modules.insert(path.ident.to_string());
+                }
+                self.collect_tree(&path.tree, prefix);
+                prefix.pop();
+            },
+            UseTree::Name(name) if ours(prefix) => {
+                self.names.insert(name.ident.to_string(), name.ident.to_string());
+            },
+            UseTree::Rename(rename) if ours(prefix) => {
+                self.names.insert(rename.rename.to_string(), rename.ident.to_string());
+            },
+            UseTree::Glob(_) if ours(prefix) => self.glob = true,
+            UseTree::Group(group) => {
+                for tree in &group.items {
+                    self.collect_tree(tree, prefix);
+                }
+            },
+            _ => {},
+        }
+    }
+
+    /// 属性是sdk的宏时返回宏的名字
+    fn macro_name(&self, attr: &Attribute) -> Option<String> {
+        let segments = attr
+            .path
+            .segments
+            .iter()
+            .map(|segment| segment.ident.to_string())
+            .collect::<Vec<_>>();
+        match segments.as_slice() {
+            [name] => match self.names.get(name) {
+                Some(name) => Some(name.clone()),
+                None if self.glob => Some(name.clone()),
+                None => None,
+            },
+            [first, .., name] if MACRO_CRATES.contains(&first.as_str()) => Some(name.clone()),
+            _ => None,
+        }
+    }
+}
+
+/// `path`中声明的`mod xxx;`对应的文件所在的目录
+fn module_dir(path: &Path) -> PathBuf {
+    let dir = path.parent().unwrap_or_else(|| Path::new(""));
+    match path.file_stem().and_then(|stem| stem.to_str()) {
+        Some("lib") | Some("main") | Some("mod") | None => dir.to_owned(),
+        Some(stem) => dir.join(stem),
+    }
+}
+
+/// 去掉cfg不满足的item，被去掉的`mod xxx;`对应的文件和目录加入`disabled`
+fn strip_cfg(items: &mut Vec<Item>, dir: &Path, disabled: &mut Vec<PathBuf>) {
+    items.retain(|item| {
+        let enabled = cfg_enabled(item_attrs(item));
+        if let (false, Item::Mod(module)) = (enabled, item) {
+            if module.content.is_none() {
+                let name = module.ident.to_string();
+                disabled.push(dir.join(format!("{}.rs", name)));
+                disabled.push(dir.join(name));
+            }
+        }
+        enabled
+    });
+    for item in items {
+        if let Item::Mod(module) = item {
+            if let Some((_, items)) = &mut module.content {
+                strip_cfg(items, &dir.join(module.ident.to_string()), disabled);
+            }
+        }
+    }
+}
+
+fn item_attrs(item: &Item) -> &[Attribute] {
+    match item {
+        Item::Const(item) => &item.attrs,
+        Item::Enum(item) => &item.attrs,
+        Item::ExternCrate(item) => &item.attrs,
+        Item::Fn(item) => &item.attrs,
+        Item::ForeignMod(item) => &item.attrs,
+        Item::Impl(item) => &item.attrs,
+        Item::Macro(item) => &item.attrs,
+        Item::Mod(item) => &item.attrs,
+        Item::Static(item) => &item.attrs,
+        Item::Struct(item) => &item.attrs,
+        Item::Trait(item) => &item.attrs,
+        Item::Type(item) => &item.attrs,
+        Item::Union(item) => &item.attrs,
+        Item::Use(item) => &item.attrs,
+        _ => &[],
+    }
+}
+
+/// 全部`#[cfg(..)]`都满足，无法解析的cfg当作满足
+pub(crate) fn cfg_enabled(attrs: &[Attribute]) -> bool {
+    attrs
+        .iter()
+        .filter(|attr| attr.path.is_ident("cfg"))
+        .all(|attr| match attr.parse_meta() {
+            Ok(Meta::List(list)) if list.nested.len() == 1 => eval_cfg(&list.nested[0]),
+            _ => true,
+        })
+}
+
+fn eval_cfg(predicate: &NestedMeta) -> bool {
+    let meta = match predicate {
+        NestedMeta::Meta(meta) => meta,
+        NestedMeta::Lit(_) => return true,
+    };
+    let name = match meta.path().get_ident() {
+        Some(ident) => ident.to_string(),
+        None => return true,
+    };
+    match meta {
+        Meta::List(list) => match name.as_str() {
+            "all" => list.nested.iter().all(eval_cfg),
+            "any" => list.nested.iter().any(eval_cfg),
+            "not" => !list.nested.iter().all(eval_cfg),
+            _ => true,
+        },
+        Meta::NameValue(nv) => {
+            let value = match &nv.lit {
+                Lit::Str(s) => s.value(),
+                _ => return true,
+            };
+            if name == "feature" {
+                let var = format!("CARGO_FEATURE_{}", value.to_uppercase().replace('-', "_"));
+                return env::var_os(var).is_some();
+            }
+            env::var(format!("CARGO_CFG_{}", name.to_uppercase()))
+                .is_ok_and(|values| values.split(',').any(|v| v == value))
+        },
+        // build script不会以test编译插件
+        Meta::Path(_) if name == "test" => false,
+        Meta::Path(_) => env::var_os(format!("CARGO_CFG_{}", name.to_uppercase())).is_some(),
+    }
+}
+
+fn collect_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    println!("cargo:rerun-if-changed={}", dir.display());
+    let entries = match read_dir(dir) {
+        Ok(entries) => entries,
+        Err(_) => return,
+    };
+    for entry in entries.filter_map(Result::ok) {
+        let path = entry.path();
+        if path.is_dir() {
+            collect_files(&path, files);
+        } else if path.extension().is_some_and(|ext| ext == "rs") {
+            files.push(path);
+        }
+    }
+}
+
+/// 第一个参数的类型名
+fn event_type(func: &ItemFn) -> Option<String> {
+    if let FnArg::Typed(arg) = func.sig.inputs.first()? {
+        return type_name(&arg.ty);
+    }
+    None
+}
+
+fn type_name(ty: &Type) -> Option<String> {
+    match ty {
+        Type::Path(path) => Some(path.path.segments.last()?.ident.to_string()),
+        Type::Reference(reference) => type_name(&reference.elem),
+        Type::Paren(paren) => type_name(&paren.elem),
+        Type::Group(group) => type_name(&group.elem),
+        _ => None,
+    }
+}
+
+fn arg(attr: &Attribute, key: &str) -> Option<Lit> {
+    if let Ok(Meta::List(list)) = attr.parse_meta() {
+        for nested in list.nested {
+            if let NestedMeta::Meta(Meta::NameValue(nv)) = nested {
+                if nv.path.is_ident(key) {
+                    return Some(nv.lit);
+                }
+            }
+        }
+    }
+    None
+}
+
+/// 读取`#[xxx(key(a, b))]`中的a、b
+pub(crate) fn list_arg(attr: &Attribute, key: &str) -> Vec<NestedMeta> {
+    if let Ok(Meta::List(list)) = attr.parse_meta() {
+        for nested in list.nested {
+            if let NestedMeta::Meta(Meta::List(list)) = nested {
+                if list.path.is_ident(key) {
+                    return list.nested.into_iter().collect();
+                }
+            }
+        }
+    }
+    Vec::new()
+}
+
+/// 读取`#[xxx(key = "value")]`中的value
+pub(crate) fn string_arg(attr: &Attribute, key: &str) -> Option<String> {
+    match arg(attr, key)? {
+        Lit::Str(s) => Some(s.value()),
+        _ => None,
+    }
+}
+
+/// 读取`#[xxx(key = 1)]`中的value
+pub(crate) fn int_arg(attr: &Attribute, key: &str) -> Option<usize> {
+    match arg(attr, key)? {
+        Lit::Int(i) => i.base10_parse().ok(),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(code: &str) -> (Sources, Vec<PathBuf>) {
+        let mut ast = syn::parse_file(code).unwrap();
+        let mut disabled = Vec::new();
+        strip_cfg(&mut ast.items, Path::new("src"), &mut disabled);
+        let mut sources = Sources::default();
+        sources.scan_file(&ast);
+        (sources, disabled)
+    }
+
+    #[test]
+    fn attribute_paths() {
+        let cases = [
+            ("#[coolq_sdk_rust::main] fn main() {}", true),
+            ("#[::coolq_sdk_rust::main] fn main() {}", true),
+            ("#[tokio::main] fn main() {}", false),
+            ("#[main] fn main() {}", false),
+            ("use coolq_sdk_rust::main; #[main] fn start() {}", true),
+            ("use coolq_sdk_rust::{main as plugin}; #[plugin] fn start() {}", true),
+            ("use tokio::main; #[main] fn start() {}", false),
+        ];
+        for (code, has_main) in &cases {
+            assert_eq!(scan(code).0.has_main, *has_main, "{}", code);
+        }
+
+        let cases = [
+            ("use coolq_sdk_rust::prelude::*; #[listener] fn a(e: PrivateMessageEvent) {}", 1),
+            ("#[cqrs_macro::listener] fn a(e: PrivateMessageEvent) {}", 1),
+            ("#[listener] fn a(e: PrivateMessageEvent) {}", 0),
+            ("use other::listener; #[listener] fn a(e: PrivateMessageEvent) {}", 0),
+        ];
+        for (code, listeners) in &cases {
+            assert_eq!(scan(code).0.listeners.len(), *listeners, "{}", code);
+        }
+    }
+
+    #[test]
+    fn cfg() {
+        env::set_var("CARGO_FEATURE_SCAN_TEST", "1");
+        env::set_var("CARGO_CFG_TARGET_OS", "windows");
+        let cases = [
+            (r#"#[cfg(feature = "scan-test")]"#, true),
+            (r#"#[cfg(feature = "scan-test-missing")]"#, false),
+            (r#"#[cfg(not(feature = "scan-test"))]"#, false),
+            (r#"#[cfg(all(feature = "scan-test", target_os = "windows"))]"#, true),
+            (r#"#[cfg(any(feature = "scan-test-missing", target_os = "linux"))]"#, false),
+            ("#[cfg(test)]", false),
+            ("#[cfg(not(test))]", true),
+        ];
+        for (cfg, enabled) in &cases {
+            let code = format!(
+                r#"{} #[coolq_sdk_rust::menu(name = "a")] fn a() {{ send_group_msg(1, ""); }}"#,
+                cfg
+            );
+            let (sources, _) = scan(&code);
+            assert_eq!(sources.menus.len() == 1, *enabled, "{}", cfg);
+            assert_eq!(sources.paths.contains("send_group_msg"), *enabled, "{}", cfg);
+        }
+
+        let (_, disabled) = scan(r#"#[cfg(feature = "scan-test-missing")] mod extra;"#);
+        assert_eq!(disabled, vec![PathBuf::from("src/extra.rs"), PathBuf::from("src/extra")]);
+        assert_eq!(module_dir(Path::new("src/lib.rs")), PathBuf::from("src"));
+        assert_eq!(module_dir(Path::new("src/handlers.rs")), PathBuf::from("src/handlers"));
+    }
+}
@@ -0,0 +1,242 @@
+//! 检查app.json是否符合酷q的要求
+//!
+//! 见[酷q文档](https://docs.cqp.im/dev/v9/app.json/)
+
+use std::collections::HashSet;
+
+use serde_json::Value;
+
+use crate::auth;
+
+/// 可以在app.json中使用的事件类型
+const EVENT_TYPES: &[u64] = &[
+    1001, 1002, 1003, 1004, 21, 2, 4, 11, 101, 102, 103, 104, 201, 301, 302,
+];
+
+const PRIORITIES: &[u64] = &[10000, 20000, 30000, 40000];
+
+pub(crate) const APIVER: usize = 9;
+
+/// appid由小写字母、数字、下划线和点组成，每一段以小写字母开头，如`dev.gugugu.example`
+pub(crate) fn appid(appid: &str, errors: &mut Vec<String>) {
+    let valid = !appid.is_empty()
+        && appid.split('.').all(|segment| {
+            segment.starts_with(|c: char| c.is_ascii_lowercase())
+                && segment
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_')
+        });
+    if !valid {
+        errors.push(format!(
+            "appid `{}` is invalid: only lowercase letters, digits, `_` and `.` are allowed, and each part must start with a letter.",
+            appid
+        ));
+    }
+}
+
+/// 导出函数名
+fn function(kind: &str, function: Option<&str>, errors: &mut Vec<String>) {
+    match function {
+        Some(f)
+            if f.starts_with(|c: char| c.is_ascii_alphabetic() || c == '_')
+                && f.chars().all(|c| c.is_ascii_alphanumeric() || c == '_') => {},
+        Some(f) => errors.push(format!("{}: `{}` is not a valid function name.", kind, f)),
+        None => errors.push(format!("{}: missing function.", kind)),
+    }
+}
+
+fn non_empty(field: &str, value: &str, errors: &mut Vec<String>) {
+    if value.trim().is_empty() {
+        errors.push(format!("`{}` cannot be empty.", field));
+    }
+}
+
+pub(crate) fn fields(
+    name: &str, version: &str, author: &str, description: &str, ret: usize, apiver: usize,
+    errors: &mut Vec<String>,
+) {
+    non_empty("name", name, errors);
+    non_empty("version", version, errors);
+    non_empty("author", author, errors);
+    non_empty("description", description, errors);
+    if ret != 1 {
+        errors.push(format!("`ret` must be 1, found {}.", ret));
+    }
+    if apiver != APIVER {
+        errors.push(format!("`apiver` must be {}, found {}.", APIVER, apiver));
+    }
+}
+
+pub(crate) fn events(events: &[Value], errors: &mut Vec<String>) {
+    let mut ids = HashSet::new();
+    let mut defined = HashSet::new();
+    for event in events {
+        let id = event.get("id").and_then(Value::as_u64);
+        let _type = event.get("type").and_then(Value::as_u64);
+        let priority = event.get("priority").and_then(Value::as_u64);
+        let func = event.get("function").and_then(Value::as_str);
+        let kind = format!("event `{}`", event.get("name").and_then(Value::as_str).unwrap_or(""));
+        match id {
+            Some(id) if !ids.insert(id) => errors.push(format!("{}: duplicate id {}.", kind, id)),
+            None => errors.push(format!("{}: missing id.", kind)),
+            _ => {},
+        }
+        match _type {
+            Some(t) if EVENT_TYPES.contains(&t) => {},
+            Some(t) => errors.push(format!("{}: unknown type {}.", kind, t)),
+            None => errors.push(format!("{}: missing type.", kind)),
+        }
+        match priority {
+            Some(p) if PRIORITIES.contains(&p) => {},
+            Some(p) => errors.push(format!(
+                "{}: priority must be one of 10000, 20000, 30000, 40000, found {}.",
+                kind, p
+            )),
+            None => errors.push(format!("{}: missing priority.", kind)),
+        }
+        function(&kind, func, errors);
+        if !defined.insert((_type, priority, func)) {
+            errors.push(format!(
+                "{}: `{}` is registered twice with the same type and priority.",
+                kind,
+                func.unwrap_or("")
+            ));
+        }
+    }
+}
+
+pub(crate) fn menus(menus: &[Value], errors: &mut Vec<String>) {
+    let mut functions = HashSet::new();
+    for menu in menus {
+        let name = menu.get("name").and_then(Value::as_str).unwrap_or("");
+        let func = menu.get("function").and_then(Value::as_str);
+        let kind = format!("menu `{}`", name);
+        if name.trim().is_empty() {
+            errors.push("menu name cannot be empty.".to_owned());
+        }
+        function(&kind, func, errors);
+        if let Some(func) = func {
+            if !functions.insert(func) {
+                errors.push(format!("{}: duplicate function `{}`.", kind, func));
+            }
+        }
+    }
+}
+
+pub(crate) fn status(status: &[Value], errors: &mut Vec<String>) {
+    let mut ids = HashSet::new();
+    for s in status {
+        let name = s.get("name").and_then(Value::as_str).unwrap_or("");
+        let kind = format!("status `{}`", name);
+        if name.trim().is_empty() {
+            errors.push("status name cannot be empty.".to_owned());
+        }
+        match s.get("id").and_then(Value::as_u64) {
+            Some(id) if !ids.insert(id) => errors.push(format!("{}: duplicate id {}.", kind, id)),
+            None => errors.push(format!("{}: missing id.", kind)),
+            _ => {},
+        }
+        if s.get("title").and_then(Value::as_str).map_or(true, |t| t.trim().is_empty()) {
+            errors.push(format!("{}: title cannot be empty.", kind));
+        }
+        let period = s.get("period").and_then(|p| match p {
+            Value::String(p) => p.parse::<u64>().ok(),
+            p => p.as_u64(),
+        });
+        if period.map_or(true, |p| p == 0) {
+            errors.push(format!("{}: period must be a positive number of milliseconds.", kind));
+        }
+        function(&kind, s.get("function").and_then(Value::as_str), errors);
+    }
+}
+
+pub(crate) fn auths(auths: &[usize], errors: &mut Vec<String>) {
+    for auth in auths {
+        if !auth::is_known(*auth) {
+            errors.push(format!("unknown auth.{}.", auth));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn check(f: impl FnOnce(&mut Vec<String>)) -> Vec<String> {
+        let mut errors = Vec::new();
+        f(&mut errors);
+        errors
+    }
+
+    #[test]
+    fn appid_table() {
+        let cases = [
+            ("dev.gugugu.example", true),
+            ("example", true),
+            ("dev.gugugu.example_2", true),
+            ("", false),
+            ("Dev.gugugu.example", false),
+            ("dev.gugugu.2example", false),
+            ("dev..example", false),
+            ("dev.gugugu-example", false),
+            ("dev.gugugu.", false),
+        ];
+        for (appid, valid) in &cases {
+            assert_eq!(check(|e| super::appid(appid, e)).is_empty(), *valid, "{}", appid);
+        }
+    }
+
+    #[test]
+    fn events_table() {
+        let event = |id: u64, ty: u64, priority: u64, function: &str| {
+            json!({"id": id, "type": ty, "name": "e", "priority": priority, "function": function})
+        };
+        let cases = vec![
+            (vec![event(1, 2, 30000, "a"), event(2, 2, 30000, "b")], None),
+            (vec![event(1, 2, 30000, "a"), event(1, 21, 30000, "b")], Some("duplicate id 1")),
+            (vec![event(1, 2, 30000, "a"), event(2, 2, 30000, "a")], Some("registered twice")),
+            (vec![event(1, 2, 30000, "a"), event(2, 2, 20000, "a")], None),
+            (vec![event(1, 2, 30000, "a"), event(2, 21, 30000, "a")], None),
+            (vec![event(1, 5, 30000, "a")], Some("unknown type 5")),
+            (vec![event(1, 2, 15000, "a")], Some("priority must be one of")),
+            (vec![event(1, 2, 30000, "on-msg")], Some("not a valid function name")),
+            (vec![json!({"type": 2, "name": "e", "priority": 30000})], Some("missing id")),
+        ];
+        for (events, error) in cases {
+            let errors = check(|e| super::events(&events, e));
+            match error {
+                Some(error) => assert!(
+                    errors.iter().any(|e| e.contains(error)),
+                    "expected `{}` in {:?}",
+                    error,
+                    errors
+                ),
+                None => assert!(errors.is_empty(), "{:?}", errors),
+            }
+        }
+    }
+
+    #[test]
+    fn menus_and_status() {
+        let menus = [
+            json!({"name": "a", "function": "menu_a"}),
+            json!({"name": "b", "function": "menu_a"}),
+        ];
+        let errors = check(|e| super::menus(&menus, e));
+        assert_eq!(errors, vec!["menu `b`: duplicate function `menu_a`."]);
+
+        let status = [
+            json!({"id": 1, "name": "a", "title": "A", "function": "status_a", "period": "1000"}),
+            json!({"id": 1, "name": "b", "title": "", "function": "status_b", "period": 0}),
+        ];
+        let errors = check(|e| super::status(&status, e));
+        assert_eq!(errors, vec![
+            "status `b`: duplicate id 1.",
+            "status `b`: title cannot be empty.",
+            "status `b`: period must be a positive number of milliseconds.",
+        ]);
+
+        assert_eq!(check(|e| auths(&[101, 102], e)), vec!["unknown auth.102."]);
+    }
+}
@@ -2,24 +2,61 @@
 
 extern crate proc_macro;
 
-use darling::FromMeta;
+#[cfg(not(test))]
+use darling::util::PathList;
+use darling::{util::SpannedValue, FromMeta};
 use proc_macro2::TokenStream;
 use syn::{FnArg, ReturnType};
 
 use quote::quote;
-use std::borrow::Borrow;
 
 macro_rules! error {
     ($tokens: expr, $message: expr) => {
         return syn::Error::new_spanned($tokens, $message)
             .to_compile_error()
-            .into();
+            .into()
     };
 }
 
+#[cfg(not(test))]
+#[derive(Debug, FromMeta)]
+struct MainArgs {
+    #[darling(default)]
+    middleware: PathList,
+    #[darling(default)]
+    modules: PathList,
+    /// 配置文件对应的类型
+    #[darling(default)]
+    config: Option<syn::Path>,
+    #[darling(default)]
+    config_file: Option<String>,
+}
+
 #[cfg(not(test))]
 #[proc_macro_attribute]
-pub fn main(_: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+pub fn main(attr: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_macro::TokenStream {
+    let args = match MainArgs::from_list(&syn::parse_macro_input!(attr as syn::AttributeArgs)) {
+        Ok(args) => args,
+        Err(err) => return err.write_errors().into(),
+    };
+    let middlewares = args.middleware.iter();
+    let modules = args.modules.iter();
+    // 声明了模块时为全部事件导出分发给模块的函数
+    let module_funcs = if args.modules.is_empty() {
+        Vec::new()
+    } else {
+        MODULE_EVENTS.iter().map(|event| module_func(event)).collect()
+    };
+    let catch_all_funcs = MODULE_EVENTS
+        .iter()
+        .chain(&["ExitEvent", "DisableEvent"])
+        .map(|event| catch_all_func(event));
+    let config = args.config.as_ref().map(|config| {
+        let file = args.config_file.as_deref().unwrap_or("config.toml");
+        quote! {
+            coolq_sdk_rust::config::init::<#config>(#file);
+        }
+    });
     let func = syn::parse_macro_input!(item as syn::ItemFn);
     let func_name = &func.sig.ident;
     let attrs = &func.attrs;
@@ -43,21 +80,125 @@ pub fn main(_: proc_macro::TokenStream, item: proc_macro::TokenStream) -> proc_m
 
     (quote! {
         #[export_name = "AppInfo"]
-        pub extern "stdcall" fn app_info() -> *const ::std::os::raw::c_char {
+        pub extern "system" fn app_info() -> *const ::std::os::raw::c_char {
             coolq_sdk_rust::api::Convert::from(format!("{},{}", coolq_sdk_rust::APIVER, include_str!(concat!(env!("OUT_DIR"), "/appid")))).into()
         }
 
         #[no_mangle]
-        pub extern "stdcall" fn on_enable() -> i32 {
+        pub extern "system" fn on_enable() -> i32 {
             #(#attrs)*
             #[inline]
             #func
+            #(coolq_sdk_rust::middleware::register(#middlewares);)*
+            #(coolq_sdk_rust::module::register(#modules);)*
+            #config
             #call
+            coolq_sdk_rust::enable();
+            0
+        }
+
+        #[no_mangle]
+        pub extern "system" fn on_disable() -> i32 {
+            coolq_sdk_rust::disable();
+            0
+        }
+
+        #[no_mangle]
+        pub extern "system" fn on_exit() -> i32 {
+            coolq_sdk_rust::disable();
             0
         }
+
+        #(#module_funcs)*
+
+        #(#catch_all_funcs)*
     }).into()
 }
 
+#[cfg(not(test))]
+/// 模块接收的事件，插件停用和酷q退出由`main`导出的函数处理
+const MODULE_EVENTS: &[&str] = &[
+    "StartEvent",
+    "PrivateMessageEvent",
+    "GroupMessageEvent",
+    "DiscussMessageEvent",
+    "GroupUploadEvent",
+    "GroupAdminEvent",
+    "GroupMemberDecreaseEvent",
+    "GroupMemberIncreaseEvent",
+    "GroupBanEvent",
+    "FriendAddEvent",
+    "AddFriendRequestEvent",
+    "AddGroupRequestEvent",
+];
+
+#[cfg(not(test))]
+/// 导出`module_{事件函数名}`，把事件分发给注册的模块
+fn module_func(event: &str) -> TokenStream {
+    let (func_name, args_name_t, args_name, result_type) = get_event_func(event).unwrap();
+    let extern_func_name = quote::format_ident!("module_{}", func_name);
+    let event = quote::format_ident!("{}", event);
+    let new_event = new_event(&func_name, &extern_func_name, &event, &args_name_t, &args_name);
+    let args_name_t = args_name_t.parse::<TokenStream>().unwrap();
+    let result_type = result_type.parse::<TokenStream>().unwrap();
+    quote! {
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "system" fn #extern_func_name(#args_name_t) -> #result_type {
+            coolq_sdk_rust::events::dispatch(#new_event, |event| {
+                coolq_sdk_rust::module::dispatch(event.into())
+            })
+        }
+    }
+}
+
+#[cfg(not(test))]
+/// 导出`catch_all_{事件函数名}`，把事件传给`listen_all`注册的回调
+fn catch_all_func(event: &str) -> TokenStream {
+    let (func_name, args_name_t, args_name, result_type) = get_event_func(event).unwrap();
+    let extern_func_name = quote::format_ident!("catch_all_{}", func_name);
+    let event = quote::format_ident!("{}", event);
+    let new_event = new_event(&func_name, &extern_func_name, &event, &args_name_t, &args_name);
+    let args_name_t = args_name_t.parse::<TokenStream>().unwrap();
+    let result_type = result_type.parse::<TokenStream>().unwrap();
+    quote! {
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "system" fn #extern_func_name(#args_name_t) -> #result_type {
+            coolq_sdk_rust::events::dispatch_all(#new_event)
+        }
+    }
+}
+
+/// 用酷q传入的参数构造事件，开启录制时同时记录原始参数
+///
+/// 酷q传入的字符串指针在回调期间有效，所以可以在这里读取
+fn new_event(
+    func_name: &str, extern_func_name: &syn::Ident, event: &impl quote::ToTokens, args_name_t: &str,
+    args_name: &str,
+) -> TokenStream {
+    let raw_args = args_name_t
+        .split(',')
+        .filter_map(|arg| arg.split_once(':'))
+        .map(|(arg, t)| {
+            let arg = quote::format_ident!("{}", arg.trim());
+            if t.contains('*') {
+                quote!(unsafe { coolq_sdk_rust::events::RawValue::from_ptr(#arg) })
+            } else {
+                quote!(coolq_sdk_rust::events::RawValue::from(#arg))
+            }
+        });
+    let args_name = args_name.parse::<TokenStream>().unwrap();
+    quote! {
+        coolq_sdk_rust::events::record(
+            #func_name,
+            stringify!(#extern_func_name),
+            || vec![#(#raw_args),*],
+            coolq_sdk_rust::events::#event::new(#args_name),
+        )
+    }
+}
+
 #[proc_macro_attribute]
 pub fn block_on(
     _: proc_macro::TokenStream, item: proc_macro::TokenStream,
@@ -65,103 +206,362 @@ pub fn block_on(
     item
 }
 
+#[derive(Debug, FromMeta)]
+struct MenuArgs {
+    name: String,
+}
+
+/// 菜单回调，导出的函数名为`menu_{函数名}`
+///
+/// cqrs_builder会扫描源码，自动把菜单添加到app.json中。
+#[proc_macro_attribute]
+pub fn menu(
+    attr: proc_macro::TokenStream, item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = match MenuArgs::from_list(&syn::parse_macro_input!(attr as syn::AttributeArgs)) {
+        Ok(args) => args,
+        Err(err) => return err.write_errors().into(),
+    };
+    let func = syn::parse_macro_input!(item as syn::ItemFn);
+    let func_name = &func.sig.ident;
+    let attrs = &func.attrs;
+
+    if args.name.is_empty() {
+        error!(&func.sig.ident, "Menu name cannot be empty.")
+    }
+    if !func.sig.inputs.is_empty() {
+        error!(&func.sig.inputs, "Menu function should not have parameters.")
+    }
+    if let ReturnType::Type(_, _) = func.sig.output {
+        error!(func.sig.output, "should return '()'.")
+    }
+
+    let call = if func.sig.asyncness.is_some() {
+        if cfg!(not(feature = "async-listener")) {
+            error!(&func.sig.asyncness, "No 'async-listener' feature support.")
+        }
+        quote! {
+            coolq_sdk_rust::ASYNC_RUNTIME.spawn(#func_name());
+        }
+    } else {
+        // panic会被panic_guard记录，不能跨越extern函数
+        quote! {
+            let _ = ::std::panic::catch_unwind(#func_name);
+        }
+    };
+    let extern_func_name = quote::format_ident!("menu_{}", func_name);
+
+    (quote! {
+        #[no_mangle]
+        pub extern "system" fn #extern_func_name() -> i32 {
+            #(#attrs)*
+            #[inline]
+            #func
+            #call
+            0
+        }
+    })
+    .into()
+}
+
+#[derive(Debug, FromMeta)]
+struct StatusArgs {
+    name: String,
+    #[darling(default)]
+    title: Option<String>,
+    #[darling(default)]
+    period: Option<u64>,
+}
+
+/// 悬浮窗状态，导出的函数名为`status_{函数名}`
+///
+/// 函数的返回值需要可以转换为`coolq_sdk_rust::status::Status`。
+/// cqrs_builder会扫描源码，自动把状态添加到app.json中。
+#[proc_macro_attribute]
+pub fn status(
+    attr: proc_macro::TokenStream, item: proc_macro::TokenStream,
+) -> proc_macro::TokenStream {
+    let args = match StatusArgs::from_list(&syn::parse_macro_input!(attr as syn::AttributeArgs)) {
+        Ok(args) => args,
+        Err(err) => return err.write_errors().into(),
+    };
+    let func = syn::parse_macro_input!(item as syn::ItemFn);
+    let func_name = &func.sig.ident;
+    let attrs = &func.attrs;
+
+    if args.name.is_empty() {
+        error!(&func.sig.ident, "Status name cannot be empty.")
+    }
+    // title和period只由cqrs_builder使用，这里只做检查
+    if args.title.as_deref() == Some("") {
+        error!(&func.sig.ident, "Status title cannot be empty.")
+    }
+    if args.period == Some(0) {
+        error!(&func.sig.ident, "Status period must be greater than 0.")
+    }
+    if func.sig.asyncness.is_some() {
+        error!(&func.sig.asyncness, "Status function cannot be async.")
+    }
+    if !func.sig.inputs.is_empty() {
+        error!(&func.sig.inputs, "Status function should not have parameters.")
+    }
+    if let ReturnType::Default = func.sig.output {
+        error!(&func.sig.ident, "Status function should return a value.")
+    }
+    let extern_func_name = quote::format_ident!("status_{}", func_name);
+    let extern_func_name_str = extern_func_name.to_string();
+
+    (quote! {
+        #[no_mangle]
+        pub extern "system" fn #extern_func_name() -> *const ::std::os::raw::c_char {
+            #(#attrs)*
+            #[inline]
+            #func
+            coolq_sdk_rust::status::encode(#extern_func_name_str, || #func_name().into())
+        }
+    })
+    .into()
+}
+
 #[derive(Debug, FromMeta)]
 struct MacroArgs {
-    //event: String,
+    /// 监听的事件，参数类型为类型别名时需要指定
+    #[darling(default)]
+    event: Option<syn::Path>,
     #[darling(default)]
-    priority: Option<String>,
+    priority: Option<SpannedValue<String>>,
+}
+
+/// listener的参数是事件本身还是它的引用
+enum EventArg {
+    Value,
+    Ref,
+    RefMut,
 }
 
 #[proc_macro_attribute]
 pub fn listener(
     attr: proc_macro::TokenStream, item: proc_macro::TokenStream,
 ) -> proc_macro::TokenStream {
-    let args = attr.clone();
-    let args = MacroArgs::from_list(&syn::parse_macro_input!(args as syn::AttributeArgs)).unwrap();
+    let args = match MacroArgs::from_list(&syn::parse_macro_input!(attr as syn::AttributeArgs)) {
+        Ok(args) => args,
+        Err(err) => return err.write_errors().into(),
+    };
     let func = syn::parse_macro_input!(item as syn::ItemFn);
     let func_name = &func.sig.ident;
     let attrs = &func.attrs;
 
-    let find_event_name = || -> Option<String> {
-        if let FnArg::Typed(t) = &func.sig.inputs.first()? {
-            if let syn::Type::Path(tr) = t.ty.borrow() {
-                return Some((&tr.path.segments.first()?.ident).to_string());
-            }
-        }
-        None
+    let (event, event_arg) = match find_event(&args, &func) {
+        Ok(event) => event,
+        Err(err) => return err.to_compile_error().into(),
     };
-    let event_name = match find_event_name() {
-        Some(some) => some,
-        None => {
+
+    if let ReturnType::Type(_, ty) = &func.sig.output {
+        if !is_listener_return_type(ty) {
             error!(
-                &func.sig.inputs.first(),
-                r#"The first parameter of the function must be "event: [AnyEvent]"."#
+                ty,
+                "Listener should return (), EventResult, i32 or Result<EventResult, E>."
             )
         }
-    };
+    }
 
-    if let Some(extern_func_info) = get_event_func(event_name.as_ref()) {
-        let event = event_name.parse::<TokenStream>().unwrap();
-        let extern_func_name = if let Some(priority) = args.priority {
-            let prioritys = vec!["highest", "high", "medium", "low"];
-            if !prioritys.contains(&priority.as_ref()) {
-                error!(
-                    TokenStream::from(attr),
-                    format!("Priority can only be {}.", prioritys.join(","))
+    let extern_func_info = get_event_func(&event.to_string()).unwrap();
+    let priority = match &args.priority {
+        Some(priority) => {
+            let prioritys = ["highest", "high", "medium", "low"];
+            if !prioritys.contains(&priority.as_str()) {
+                return syn::Error::new(
+                    priority.span(),
+                    format!("Priority can only be {}.", prioritys.join(",")),
                 )
+                .to_compile_error()
+                .into();
             }
-            format!("{}_{}", extern_func_info.0, priority)
-        } else {
-            format!("{}_medium", extern_func_info.0)
+            priority.as_str()
+        },
+        None => "medium",
+    };
+    let extern_func_name = quote::format_ident!("{}_{}", extern_func_info.0, priority);
+    let args_name_t = extern_func_info.1.parse::<TokenStream>().unwrap();
+    let result_type = extern_func_info.3.parse::<TokenStream>().unwrap();
+
+    let new_event = new_event(
+        &extern_func_info.0,
+        &extern_func_name,
+        &event,
+        &extern_func_info.1,
+        &extern_func_info.2,
+    );
+
+    let (event_param, event_expr) = match event_arg {
+        EventArg::Value => (quote!(event), quote!(event)),
+        EventArg::Ref => (quote!(event), quote!(&event)),
+        EventArg::RefMut => (quote!(mut event), quote!(&mut event)),
+    };
+
+    let call = if func.sig.asyncness.is_some() {
+        if cfg!(not(feature = "async-listener")) {
+            error!(&func.sig.asyncness, "No 'async-listener' feature support.")
         }
-        .parse::<TokenStream>()
-        .unwrap();
-        let args_name_t = extern_func_info.1.parse::<TokenStream>().unwrap();
-        let args_name = extern_func_info.2.parse::<TokenStream>().unwrap();
-        let result_type = extern_func_info.3.parse::<TokenStream>().unwrap();
-
-        let call = if func.sig.asyncness.is_some() {
-            if cfg!(not(feature = "async-listener")) {
-                error!(&func.sig.asyncness, "No 'async-listener' feature support.")
-            }
-            if attrs
-                .iter()
-                .find(|attr| {
-                    attr.path
-                        .segments
-                        .iter()
-                        .find(|ps| ps.ident.to_string() == "block_on")
-                        .is_some()
-                })
-                .is_some()
-            {
-                quote! {
-                    coolq_sdk_rust::api::Convert::from(coolq_sdk_rust::block_on(#func_name(coolq_sdk_rust::events::#event::new(#args_name)))).into()
-                }
-            } else {
-                quote! {
-                    coolq_sdk_rust::ASYNC_RUNTIME.spawn(#func_name(coolq_sdk_rust::events::#event::new(#args_name)));
-                    0
-                }
+        if attrs
+            .iter()
+            .find(|attr| {
+                attr.path
+                    .segments
+                    .iter()
+                    .find(|ps| ps.ident.to_string() == "block_on")
+                    .is_some()
+            })
+            .is_some()
+        {
+            quote! {
+                coolq_sdk_rust::events::IntoEventResult::into_event_result(coolq_sdk_rust::block_on(#func_name(#event_expr)))
             }
         } else {
             quote! {
-                coolq_sdk_rust::api::Convert::from(#func_name(coolq_sdk_rust::events::#event::new(#args_name))).into()
+                coolq_sdk_rust::ASYNC_RUNTIME.spawn(coolq_sdk_rust::panic_guard::guard_future(async move {
+                    coolq_sdk_rust::events::IntoEventResult::into_event_result(#func_name(#event_expr).await);
+                }));
+                coolq_sdk_rust::events::EventResult::Ignore
             }
-        };
+        }
+    } else {
+        quote! {
+            coolq_sdk_rust::events::IntoEventResult::into_event_result(#func_name(#event_expr))
+        }
+    };
 
-        (quote! {
-            #[no_mangle]
-            pub extern "stdcall" fn #extern_func_name(#args_name_t) -> #result_type {
-                #(#attrs)*
-                #[inline]
-                #func
+    (quote! {
+        #[no_mangle]
+        #[allow(clippy::not_unsafe_ptr_arg_deref)]
+        pub extern "system" fn #extern_func_name(#args_name_t) -> #result_type {
+            #(#attrs)*
+            #[inline]
+            #func
+            coolq_sdk_rust::events::dispatch(#new_event, |#event_param| {
                 #call
+            })
+        }
+    })
+    .into()
+}
+
+/// 找到listener监听的事件
+///
+/// 优先使用`event = ".."`参数，否则使用第一个参数的类型，支持完整路径和引用。
+fn find_event(args: &MacroArgs, func: &syn::ItemFn) -> syn::Result<(syn::Ident, EventArg)> {
+    let sig = &func.sig;
+    if !sig.generics.params.is_empty() {
+        return Err(syn::Error::new_spanned(&sig.generics, "Listener cannot be generic."));
+    }
+    let mut inputs = sig.inputs.iter();
+    let ty = match inputs.next() {
+        Some(FnArg::Typed(arg)) => &*arg.ty,
+        Some(receiver) => {
+            return Err(syn::Error::new_spanned(
+                receiver,
+                "Listener cannot take `self`.",
+            ))
+        },
+        None => {
+            return Err(syn::Error::new(
+                sig.paren_token.span,
+                r#"The first parameter of the function must be "event: [AnyEvent]"."#,
+            ))
+        },
+    };
+    if let Some(extra) = inputs.next() {
+        return Err(syn::Error::new_spanned(
+            extra,
+            "Listener can only have one parameter.",
+        ));
+    }
+
+    let (ty, event_arg) = match strip_type(ty) {
+        syn::Type::Reference(reference) => (
+            strip_type(&reference.elem),
+            if reference.mutability.is_some() {
+                EventArg::RefMut
+            } else {
+                EventArg::Ref
+            },
+        ),
+        ty => (ty, EventArg::Value),
+    };
+
+    if let Some(path) = &args.event {
+        let segment = path.segments.last().unwrap();
+        return if get_event_func(&segment.ident.to_string()).is_some() {
+            Ok((segment.ident.clone(), event_arg))
+        } else {
+            Err(syn::Error::new_spanned(
+                path,
+                format!("Unknown event `{}`.", segment.ident),
+            ))
+        };
+    }
+
+    match ty {
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let segment = path.path.segments.last().unwrap();
+            if !segment.arguments.is_empty() {
+                Err(syn::Error::new_spanned(
+                    &segment.arguments,
+                    "Event type should not have generic arguments.",
+                ))
+            } else if get_event_func(&segment.ident.to_string()).is_some() {
+                Ok((segment.ident.clone(), event_arg))
+            } else {
+                Err(syn::Error::new_spanned(
+                    path,
+                    format!(
+                        "Cannot find event `{}`. If it is a type alias, specify the event with `#[listener(event = \"GroupMessageEvent\")]`.",
+                        segment.ident
+                    ),
+                ))
             }
-        })
-        .into()
-    } else {
-        error!(&func.sig.inputs.first(), "Cannot find this event.")
+        },
+        ty => Err(syn::Error::new_spanned(
+            ty,
+            r#"The first parameter of the function must be "event: [AnyEvent]"."#,
+        )),
+    }
+}
+
+/// 去掉类型外的括号
+fn strip_type(ty: &syn::Type) -> &syn::Type {
+    match ty {
+        syn::Type::Paren(paren) => strip_type(&paren.elem),
+        syn::Type::Group(group) => strip_type(&group.elem),
+        ty => ty,
+    }
+}
+
+/// listener支持的返回值: `()`、`EventResult`、`i32`、`Result<T, E>`（`T`也需要是支持的返回值）
+fn is_listener_return_type(ty: &syn::Type) -> bool {
+    match ty {
+        syn::Type::Tuple(tuple) => tuple.elems.is_empty(),
+        syn::Type::Paren(paren) => is_listener_return_type(&paren.elem),
+        syn::Type::Path(path) if path.qself.is_none() => {
+            let segment = match path.path.segments.last() {
+                Some(segment) => segment,
+                None => return false,
+            };
+            match &segment.arguments {
+                syn::PathArguments::None => {
+                    segment.ident == "EventResult" || path.path.is_ident("i32")
+                },
+                // 包括`io::Result<()>`这类别名
+                syn::PathArguments::AngleBracketed(args) if segment.ident == "Result" => {
+                    args.args.first().is_some_and(|arg| match arg {
+                        syn::GenericArgument::Type(ty) => is_listener_return_type(ty),
+                        _ => false,
+                    })
+                },
+                _ => false,
+            }
+        },
+        _ => false,
     }
 }
 
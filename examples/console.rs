@@ -0,0 +1,27 @@
+//! 在终端中和一个复读机插件对话
+//!
+//! `cargo run --example console --features testing`
+
+use coolq_sdk_rust::{prelude::*, testing::console::Console};
+
+#[listener]
+fn private_msg(event: PrivateMessageEvent) {
+    event.reply(&event.get_message().msg).ok();
+}
+
+#[listener]
+fn group_msg(event: GroupMessageEvent) -> EventResult {
+    if event.get_message().msg.starts_with("复读") {
+        event.reply_at(event.get_message().msg["复读".len()..].trim()).ok();
+        EventResult::Block
+    } else {
+        EventResult::Ignore
+    }
+}
+
+fn main() {
+    Console::new()
+        .on_private(on_private_msg_medium)
+        .on_group(on_group_msg_medium)
+        .run();
+}
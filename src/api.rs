@@ -95,15 +95,36 @@ macro_rules! gen_api_func {
     };
 
     ($(#[$doc: meta])* $cq_func: ident, $func: ident; $($arg: ident: $t: ty),* => $result_t: ty) => {
-        static $cq_func: OnceCell<extern "stdcall" fn(i32, $($t),*) -> $result_t> = OnceCell::new();
+        static $cq_func: OnceCell<extern "system" fn(i32, $($t),*) -> $result_t> = OnceCell::new();
 
         $(#[$doc])*
         pub fn $func($($arg: impl Into<Convert<$t>>),*) -> Result<Convert<$result_t>> {
-            unsafe {
-                let lib = libloading::Library::new("CQP.dll").unwrap();
-                Result::r_from(lib.get::<extern "stdcall" fn(i32, $($t),*) -> $result_t>(stringify!($cq_func).as_bytes()).unwrap()(AUTH_CODE.get().expect("auth code not found.").clone(), $($arg.into().into()),*))
+            $(let $arg: $t = $arg.into().into();)*
+            fn raw_call($($arg: $t),*) -> $result_t {
+                #[cfg(feature = "testing")]
+                {
+                    if crate::testing::is_active() {
+                        return crate::testing::call::<$result_t>(
+                            stringify!($func),
+                            vec![$(crate::testing::MockArg::to_value(&$arg)),*],
+                        );
+                    }
+                }
+                unsafe {
+                    let lib = libloading::Library::new("CQP.dll").unwrap();
+                    lib.get::<extern "system" fn(i32, $($t),*) -> $result_t>(stringify!($cq_func).as_bytes()).unwrap()(AUTH_CODE.get().expect("auth code not found.").clone(), $($arg),*)
+                }
+                //($cq_func.get().expect("CQP.dll not init."))(AUTH_CODE.get().expect("auth code not found.").clone(), $($arg),*)
             }
-            //Result::r_from(($cq_func.get().expect("CQP.dll not init."))(AUTH_CODE.get().expect("auth code not found.").clone(), $($arg.into().into()),*).into())
+            let result = raw_call($($arg),*);
+            #[cfg(feature = "recorder")]
+            crate::recorder::record_call(
+                stringify!($func),
+                // 参数在调用期间有效，返回的字符串由酷q持有
+                || vec![$(unsafe { crate::events::ToRaw::to_raw($arg) }),*],
+                || unsafe { crate::events::ToRaw::to_raw(result) },
+            );
+            Result::r_from(result)
         }
     };
 }
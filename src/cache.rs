@@ -0,0 +1,246 @@
+//! 群、群员、陌生人信息和好友列表的缓存
+//!
+//! 事件中的[`Group`]和[`User`]等都是通过此处获取的，以减少api调用。
+//!
+//! 缓存会在ttl（默认60秒）后过期，也会在收到以下事件时失效:
+//!
+//! * [`GroupAdminEvent`]：对应群员
+//! * [`GroupMemberIncreaseEvent`]/[`GroupMemberDecreaseEvent`]：群信息和对应群员。机器人被踢出时为该群全部群员
+//! * [`GroupBanEvent`]：对应群员
+//! * [`FriendAddEvent`]：好友列表
+//!
+//! 设置ttl为0即可关闭缓存。过期的条目会在插入新条目时清理。
+//!
+//! 酷q返回的数据无法解析时返回`Error(0)`。
+//!
+//! # Examples
+//! ```
+//! use coolq_sdk_rust::cache;
+//! use std::time::Duration;
+//!
+//! cache::set_ttl(Duration::from_secs(300));
+//! let stats = cache::stats();
+//! println!("群员缓存命中率: {}", stats.members.hit_rate());
+//! ```
+//!
+//! [`GroupAdminEvent`]: crate::events::GroupAdminEvent
+//! [`GroupMemberIncreaseEvent`]: crate::events::GroupMemberIncreaseEvent
+//! [`GroupMemberDecreaseEvent`]: crate::events::GroupMemberDecreaseEvent
+//! [`GroupBanEvent`]: crate::events::GroupBanEvent
+//! [`FriendAddEvent`]: crate::events::FriendAddEvent
+
+use std::{
+    collections::HashMap,
+    convert::TryInto,
+    hash::Hash,
+    sync::{
+        atomic::{AtomicU64, AtomicUsize, Ordering},
+        RwLock,
+    },
+    time::{Duration, Instant},
+};
+
+use crate::{
+    api::{
+        get_friend_list, get_group_info, get_group_member_info_v2, get_stranger_info, Error, Result,
+    },
+    targets::{
+        group::{Group, GroupMember},
+        user::{Authority, FriendInfo, User},
+    },
+};
+
+/// 毫秒
+static TTL: AtomicU64 = AtomicU64::new(60_000);
+
+lazy_static! {
+    static ref Groups: TtlCache<i64, Group> = TtlCache::new();
+    static ref Members: TtlCache<(i64, i64), GroupMember> = TtlCache::new();
+    static ref Users: TtlCache<i64, User> = TtlCache::new();
+    static ref Friends: TtlCache<(), Vec<FriendInfo>> = TtlCache::new();
+}
+
+struct TtlCache<K, V> {
+    map: RwLock<HashMap<K, (Instant, V)>>,
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl<K: Hash + Eq, V: Clone> TtlCache<K, V> {
+    fn new() -> Self {
+        TtlCache {
+            map: RwLock::new(HashMap::new()),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    fn get_or_fetch(&self, key: K, fetch: impl FnOnce() -> Result<V>) -> Result<V> {
+        if let Some((time, v)) = self.map.read().expect("cannot read cache").get(&key) {
+            if time.elapsed() < ttl() {
+                self.hits.fetch_add(1, Ordering::Relaxed);
+                return Ok(v.clone());
+            }
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+        let v = fetch()?;
+        self.insert(key, v.clone());
+        Ok(v)
+    }
+
+    /// 插入时顺便清理已过期的条目
+    fn insert(&self, key: K, v: V) {
+        let ttl = ttl();
+        if ttl != Duration::from_millis(0) {
+            let mut map = self.map.write().expect("cannot write cache");
+            map.retain(|_, (time, _)| time.elapsed() < ttl);
+            map.insert(key, (Instant::now(), v));
+        }
+    }
+
+    fn remove(&self, key: &K) {
+        self.map.write().expect("cannot write cache").remove(key);
+    }
+
+    fn retain(&self, f: impl Fn(&K) -> bool) {
+        self.map
+            .write()
+            .expect("cannot write cache")
+            .retain(|k, _| f(k));
+    }
+
+    fn stats(&self) -> CacheStats {
+        CacheStats {
+            hits: self.hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            len: self
+                .map
+                .read()
+                .expect("cannot read cache")
+                .values()
+                .filter(|(time, _)| time.elapsed() < ttl())
+                .count(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CacheStats {
+    pub hits: usize,
+    pub misses: usize,
+    /// 当前未过期的缓存条目数
+    pub len: usize,
+}
+
+impl CacheStats {
+    pub fn hit_rate(&self) -> f64 {
+        if self.hits + self.misses == 0 {
+            0.0
+        } else {
+            self.hits as f64 / (self.hits + self.misses) as f64
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Stats {
+    pub groups: CacheStats,
+    pub members: CacheStats,
+    pub users: CacheStats,
+    pub friends: CacheStats,
+}
+
+pub fn stats() -> Stats {
+    Stats {
+        groups: Groups.stats(),
+        members: Members.stats(),
+        users: Users.stats(),
+        friends: Friends.stats(),
+    }
+}
+
+pub fn ttl() -> Duration {
+    Duration::from_millis(TTL.load(Ordering::Relaxed))
+}
+
+/// 设置缓存过期时间，为0则关闭缓存并清空已有缓存
+pub fn set_ttl(ttl: Duration) {
+    TTL.store(ttl.as_millis() as u64, Ordering::Relaxed);
+    if ttl == Duration::from_millis(0) {
+        clear();
+    }
+}
+
+pub fn get_group(group_id: i64) -> Result<Group> {
+    Groups.get_or_fetch(group_id, || {
+        get_group_info(group_id, false)?
+            .try_into()
+            .map_err(|_| Error(0))
+    })
+}
+
+pub fn get_group_member(group_id: i64, user_id: i64) -> Result<GroupMember> {
+    let mut gm = Members.get_or_fetch((group_id, user_id), || {
+        get_group_member_info_v2(group_id, user_id, false)?
+            .try_into()
+            .map_err(|_| Error(0))
+    })?;
+    // 主人和超级管理员列表可能在缓存之后发生变化
+    gm.authority = Authority::from_group_member(&gm);
+    Ok(gm)
+}
+
+/// 返回的User不包含权限信息
+pub fn get_user(user_id: i64) -> Result<User> {
+    Users.get_or_fetch(user_id, || {
+        get_stranger_info(user_id, false)?
+            .try_into()
+            .map_err(|_| Error(0))
+    })
+}
+
+pub fn get_friends() -> Result<Vec<FriendInfo>> {
+    Friends.get_or_fetch((), || {
+        get_friend_list(false)?.try_into().map_err(|_| Error(0))
+    })
+}
+
+pub(crate) fn put_group(group: Group) {
+    Groups.insert(group.group_id, group);
+}
+
+pub(crate) fn put_group_member(gm: GroupMember) {
+    Members.insert((gm.group_id, gm.user_id), gm);
+}
+
+pub(crate) fn put_user(user: User) {
+    Users.insert(user.user_id, user);
+}
+
+pub fn invalidate_group(group_id: i64) {
+    Groups.remove(&group_id);
+}
+
+pub fn invalidate_group_member(group_id: i64, user_id: i64) {
+    Members.remove(&(group_id, user_id));
+}
+
+/// 使该群全部群员的缓存失效
+pub fn invalidate_group_members(group_id: i64) {
+    Members.retain(|(g, _)| *g != group_id);
+}
+
+pub fn invalidate_user(user_id: i64) {
+    Users.remove(&user_id);
+}
+
+pub fn invalidate_friends() {
+    Friends.remove(&());
+}
+
+pub fn clear() {
+    Groups.retain(|_| false);
+    Members.retain(|_| false);
+    Users.retain(|_| false);
+    Friends.retain(|_| false);
+}
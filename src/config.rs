@@ -0,0 +1,358 @@
+//! 插件配置
+//!
+//! 从插件数据目录读取配置文件到自定义的结构体，扩展名为`.json`时按json解析，否则按toml解析。
+//! 文件不存在时会写入[`Default`]的值。
+//!
+//! 读取或[校验](Validate)失败时会通过[`add_log`]记录错误，重载失败时保留原来的配置。
+//!
+//! 调用[`watch`]之后，配置文件被修改时会自动重载，插件停用或酷q退出时停止检查。
+//! 开启cqrs_builder的`config` feature时会在app.json中添加[`RELOAD_MENU`]菜单，可以在酷q中手动重载全部配置:
+//! ```toml
+//! [build-dependencies]
+//! cqrs_builder = { version = "0.1", features = ["config"] }
+//! ```
+//!
+//! 需要开启`config` feature。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::config::{self, Validate};
+//! use serde::{Deserialize, Serialize};
+//!
+//! #[derive(Default, Serialize, Deserialize)]
+//! struct Settings {
+//!     admins: Vec<i64>,
+//!     greeting: String,
+//! }
+//!
+//! impl Validate for Settings {
+//!     fn validate(&self) -> Result<(), String> {
+//!         if self.admins.is_empty() {
+//!             return Err("admins不能为空".to_owned());
+//!         }
+//!         Ok(())
+//!     }
+//! }
+//!
+//! // 插件启用时读取`config.toml`，等价于`#[coolq_sdk_rust::main(config = "Settings")]`
+//! config::init::<Settings>("config.toml");
+//!
+//! let settings = config::get::<Settings>();
+//! println!("{}", settings.greeting);
+//! ```
+//!
+//! [`add_log`]: crate::api::add_log
+
+use std::{
+    any::{Any, TypeId},
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs::{metadata, read_to_string, write},
+    io,
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::{Duration, SystemTime},
+};
+
+use serde::{de::DeserializeOwned, Serialize};
+
+use crate::api::{add_log, get_app_directory, CQLogLevel};
+
+/// 重载全部配置的菜单函数名
+pub const RELOAD_MENU: &str = "cqrs_reload_config";
+
+static WATCHING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref Configs: RwLock<HashMap<TypeId, Arc<dyn Reload>>> = RwLock::new(HashMap::new());
+    static ref Watcher: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Toml(toml::de::Error),
+    TomlSer(toml::ser::Error),
+    Json(serde_json::Error),
+    /// [`Validate::validate`]返回的错误
+    Invalid(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "{}", err),
+            Error::Toml(err) => write!(f, "{}", err),
+            Error::TomlSer(err) => write!(f, "{}", err),
+            Error::Json(err) => write!(f, "{}", err),
+            Error::Invalid(err) => write!(f, "校验失败: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+/// 校验配置
+pub trait Validate {
+    fn validate(&self) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+trait Reload: Send + Sync {
+    fn reload(&self) -> Result<(), Error>;
+
+    /// 文件被修改则重载
+    fn check(&self);
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync>;
+}
+
+type ChangeListener<T> = Box<dyn Fn(&T) + Send + Sync>;
+
+pub struct Config<T> {
+    path: PathBuf,
+    value: RwLock<Arc<T>>,
+    modified: Mutex<Option<SystemTime>>,
+    listeners: RwLock<Vec<ChangeListener<T>>>,
+}
+
+impl<T> Config<T>
+where
+    T: Serialize + DeserializeOwned + Default + Validate + Send + Sync + 'static,
+{
+    /// 读取配置文件，相对路径相对于插件数据目录
+    ///
+    /// 读取成功后可以通过[`get`]获取。
+    pub fn load(path: impl AsRef<Path>) -> Result<Arc<Config<T>>, Error> {
+        let path = resolve(path.as_ref())?;
+        if !path.exists() {
+            write(&path, serialize(&path, &T::default())?)?;
+        }
+        let (value, modified) = read(&path).map_err(|err| log_error(&path, err))?;
+        let config = Arc::new(Config {
+            path,
+            value: RwLock::new(Arc::new(value)),
+            modified: Mutex::new(modified),
+            listeners: RwLock::new(Vec::new()),
+        });
+        Configs
+            .write()
+            .expect("cannot write Configs")
+            .insert(TypeId::of::<T>(), config.clone());
+        Ok(config)
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn get(&self) -> Arc<T> {
+        self.value.read().expect("cannot read config").clone()
+    }
+
+    /// 重载成功后调用
+    pub fn on_reload(&self, f: impl Fn(&T) + Send + Sync + 'static) {
+        self.listeners
+            .write()
+            .expect("cannot write config listeners")
+            .push(Box::new(f));
+    }
+}
+
+impl<T> Reload for Config<T>
+where
+    T: Serialize + DeserializeOwned + Default + Validate + Send + Sync + 'static,
+{
+    fn reload(&self) -> Result<(), Error> {
+        let (value, modified) = read::<T>(&self.path).map_err(|err| log_error(&self.path, err))?;
+        let value = Arc::new(value);
+        *self.value.write().expect("cannot write config") = value.clone();
+        *self.modified.lock().expect("cannot lock config") = modified;
+        self.listeners
+            .read()
+            .expect("cannot read config listeners")
+            .iter()
+            .for_each(|f| f(&value));
+        let _ = add_log(
+            CQLogLevel::INFOSUCCESS,
+            "config",
+            format!("已重载{}", self.path.display()),
+        );
+        Ok(())
+    }
+
+    fn check(&self) {
+        let modified = metadata(&self.path).and_then(|m| m.modified()).ok();
+        if modified != *self.modified.lock().expect("cannot lock config") {
+            // 失败时也记录修改时间，避免每次检查都重复记录错误
+            if self.reload().is_err() {
+                *self.modified.lock().expect("cannot lock config") = modified;
+            }
+        }
+    }
+
+    fn as_any(self: Arc<Self>) -> Arc<dyn Any + Send + Sync> {
+        self
+    }
+}
+
+fn resolve(path: &Path) -> Result<PathBuf, Error> {
+    if path.is_absolute() {
+        return Ok(path.to_owned());
+    }
+    let app_dir = get_app_directory()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+        .to::<String>();
+    Ok(PathBuf::from(app_dir).join(path))
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "json")
+}
+
+fn serialize<T: Serialize>(path: &Path, value: &T) -> Result<String, Error> {
+    if is_json(path) {
+        serde_json::to_string_pretty(value).map_err(Error::Json)
+    } else {
+        toml::to_string_pretty(value).map_err(Error::TomlSer)
+    }
+}
+
+fn read<T: DeserializeOwned + Validate>(path: &Path) -> Result<(T, Option<SystemTime>), Error> {
+    let modified = metadata(path).and_then(|m| m.modified()).ok();
+    let content = read_to_string(path)?;
+    let value: T = if is_json(path) {
+        serde_json::from_str(&content).map_err(Error::Json)?
+    } else {
+        toml::from_str(&content).map_err(Error::Toml)?
+    };
+    value.validate().map_err(Error::Invalid)?;
+    Ok((value, modified))
+}
+
+fn log_error(path: &Path, err: Error) -> Error {
+    let _ = add_log(
+        CQLogLevel::ERROR,
+        "config",
+        format!("读取{}失败: {}", path.display(), err),
+    );
+    err
+}
+
+/// 读取配置文件，失败时使用默认值，并开始[`watch`]
+///
+/// 由`#[coolq_sdk_rust::main(config = "...")]`调用。
+pub fn init<T>(path: impl AsRef<Path>)
+where
+    T: Serialize + DeserializeOwned + Default + Validate + Send + Sync + 'static,
+{
+    if Config::<T>::load(path.as_ref()).is_err() {
+        if let Ok(path) = resolve(path.as_ref()) {
+            let modified = metadata(&path).and_then(|m| m.modified()).ok();
+            Configs.write().expect("cannot write Configs").insert(
+                TypeId::of::<T>(),
+                Arc::new(Config {
+                    path,
+                    value: RwLock::new(Arc::new(T::default())),
+                    modified: Mutex::new(modified),
+                    listeners: RwLock::new(Vec::new()),
+                }) as Arc<Config<T>>,
+            );
+        }
+    }
+    watch(Duration::from_secs(3));
+}
+
+/// 获取已读取的配置
+///
+/// # Panics
+/// 没有读取过该类型的配置
+pub fn config<T>() -> Arc<Config<T>>
+where
+    T: Serialize + DeserializeOwned + Default + Validate + Send + Sync + 'static,
+{
+    Configs
+        .read()
+        .expect("cannot read Configs")
+        .get(&TypeId::of::<T>())
+        .cloned()
+        .expect("config not loaded")
+        .as_any()
+        .downcast()
+        .unwrap_or_else(|_| unreachable!())
+}
+
+/// 获取已读取的配置的值，等价于`config::<T>().get()`
+pub fn get<T>() -> Arc<T>
+where
+    T: Serialize + DeserializeOwned + Default + Validate + Send + Sync + 'static,
+{
+    config::<T>().get()
+}
+
+/// 重载全部配置，返回失败的个数
+pub fn reload_all() -> usize {
+    let configs = Configs
+        .read()
+        .expect("cannot read Configs")
+        .values()
+        .cloned()
+        .collect::<Vec<_>>();
+    configs
+        .iter()
+        .filter(|config| config.reload().is_err())
+        .count()
+}
+
+/// 每隔`interval`检查一次配置文件是否被修改，重复调用无效
+pub fn watch(interval: Duration) {
+    if WATCHING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    let watcher = thread::Builder::new()
+        .name("cqrs-config-watcher".to_owned())
+        .spawn(move || loop {
+            thread::park_timeout(interval);
+            if !WATCHING.load(Ordering::SeqCst) {
+                break;
+            }
+            let configs = Configs
+                .read()
+                .expect("cannot read Configs")
+                .values()
+                .cloned()
+                .collect::<Vec<_>>();
+            configs.iter().for_each(|config| config.check());
+        })
+        .expect("cannot spawn config watcher");
+    *Watcher.lock().expect("cannot lock Watcher") = Some(watcher);
+}
+
+/// 停止检查配置文件，插件停用或酷q退出时会自动调用
+pub fn unwatch() {
+    if !WATCHING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    if let Some(watcher) = Watcher.lock().expect("cannot lock Watcher").take() {
+        watcher.thread().unpark();
+        let _ = watcher.join();
+    }
+}
+
+#[doc(hidden)]
+#[export_name = "cqrs_reload_config"]
+pub extern "system" fn reload_menu() -> i32 {
+    reload_all();
+    0
+}
@@ -2,16 +2,20 @@ use std::os::raw::c_char;
 
 use crate::{
     api::{set_friend_add_request, Convert, Flag},
-    targets::user::User,
+    targets::{lazy::Lazy, user::User},
 };
 
+sub_type!(AddFriendRequestType {
+    Request = 1
+});
+
 #[derive(Debug, Clone)]
 pub struct AddFriendRequestEvent {
-    pub sub_type: i32,
+    pub sub_type: AddFriendRequestType,
     pub send_time: i32,
     pub msg: String,
     pub flag: Flag,
-    pub user: User,
+    pub user: Lazy<User>,
 }
 
 impl AddFriendRequestEvent {
@@ -19,11 +23,11 @@ impl AddFriendRequestEvent {
         sub_type: i32, send_time: i32, user_id: i64, msg: *const c_char, flag: *const c_char,
     ) -> Self {
         AddFriendRequestEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
             msg: Convert::from(msg).into(),
             flag: Convert::from(flag).into(),
-            user: User::new(user_id),
+            user: Lazy::new(user_id),
         }
     }
 
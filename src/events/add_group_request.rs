@@ -2,17 +2,24 @@ use std::os::raw::c_char;
 
 use crate::{
     api::{set_group_add_request_v2, Convert, Flag},
-    targets::{group::Group, user::User},
+    targets::{group::Group, lazy::Lazy, user::User},
 };
 
+sub_type!(AddGroupRequestType {
+    /// 用户申请入群
+    Application = 1,
+    /// 机器人被邀请入群
+    Invite = 2
+});
+
 #[derive(Debug, Clone)]
 pub struct AddGroupRequestEvent {
-    pub sub_type: i32,
+    pub sub_type: AddGroupRequestType,
     pub send_time: i32,
     pub msg: String,
     pub flag: Flag,
-    pub group: Group,
-    pub user: User,
+    pub group: Lazy<Group>,
+    pub user: Lazy<User>,
 }
 
 impl AddGroupRequestEvent {
@@ -21,27 +28,27 @@ impl AddGroupRequestEvent {
         flag: *const c_char,
     ) -> Self {
         AddGroupRequestEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
             msg: Convert::from(msg).into(),
             flag: Convert::from(flag).into(),
-            group: Group::new(group_id),
-            user: User::new(user_id),
+            group: Lazy::new(group_id),
+            user: Lazy::new(user_id),
         }
     }
 
     /// 收到入群邀请
     pub fn is_invite(&self) -> bool {
-        self.sub_type == 2
+        self.sub_type == AddGroupRequestType::Invite
     }
 
     /// 用户申请入群
     pub fn is_application(&self) -> bool {
-        self.sub_type == 1
+        self.sub_type == AddGroupRequestType::Application
     }
 
     /// `reason`: 拒绝理由
     pub fn handle(&self, approve: bool, reason: &str) -> crate::api::Result<Convert<i32>> {
-        set_group_add_request_v2(self.flag.clone(), self.sub_type, approve, reason)
+        set_group_add_request_v2(self.flag.clone(), i32::from(self.sub_type), approve, reason)
     }
 }
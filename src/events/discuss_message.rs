@@ -1,28 +1,65 @@
-//! qq已经放弃讨论组，所以讨论组方面的东西就不写了。
-
 use std::os::raw::c_char;
 
+use crate::{
+    api::Convert,
+    targets::{
+        discuss::Discuss,
+        lazy::Lazy,
+        message::{Message, SendMessage},
+        user::User,
+    },
+};
+
+sub_type!(DiscussMessageType {
+    Normal = 1
+});
+
 #[derive(Debug, Clone)]
 pub struct DiscussMessageEvent {
-    pub sub_type: i32,
+    pub sub_type: DiscussMessageType,
+    pub msg: Message,
+    pub font: i32,
+    pub discuss: Discuss,
+    pub user: Lazy<User>,
+    #[deprecated(note = "使用`msg.get_msg_id()`")]
     pub msg_id: i32,
+    #[deprecated(note = "使用`discuss.discuss_id`")]
     pub discuss_id: i64,
+    #[deprecated(note = "使用`user.id()`")]
     pub user_id: i64,
-    pub msg: String,
-    pub font: i32,
 }
 
 impl DiscussMessageEvent {
+    #[allow(deprecated)]
     pub fn new(
         sub_type: i32, msg_id: i32, discuss_id: i64, user_id: i64, msg: *const c_char, font: i32,
     ) -> Self {
         DiscussMessageEvent {
-            sub_type,
+            sub_type: sub_type.into(),
+            msg: Message::new(msg, msg_id),
+            font,
+            discuss: Discuss::new(discuss_id),
+            user: Lazy::new(user_id),
             msg_id,
             discuss_id,
             user_id,
-            msg: "".to_string(),
-            font,
         }
     }
+
+    pub fn get_message(&self) -> &Message {
+        &self.msg
+    }
+
+    pub fn reply(&self, msg: impl ToString) -> crate::api::Result<i32> {
+        self.discuss.send_message(msg)
+    }
+
+    pub fn reply_at(&self, msg: impl ToString) -> crate::api::Result<i32> {
+        self.discuss.at(self.user.id(), msg)
+    }
+
+    /// 退出该讨论组
+    pub fn leave(&self) -> crate::api::Result<Convert<i32>> {
+        self.discuss.leave()
+    }
 }
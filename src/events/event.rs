@@ -0,0 +1,222 @@
+use std::{
+    convert::TryFrom,
+    sync::{Arc, RwLock},
+};
+
+use crate::{
+    events::*,
+    middleware,
+    panic_guard::{self, EventContext},
+    targets::message::Message,
+};
+
+type CatchAllListener = Arc<dyn Fn(&Event) + Send + Sync>;
+
+lazy_static! {
+    static ref CatchAllListeners: RwLock<Vec<CatchAllListener>> = RwLock::new(Vec::new());
+}
+
+macro_rules! gen_event_enum {
+    ($($variant: ident($event: ident)),*) => {
+        /// 包含全部事件的枚举
+        #[derive(Debug, Clone)]
+        pub enum Event {
+            $($variant($event)),*
+        }
+
+        #[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+        pub enum EventKind {
+            $($variant),*
+        }
+
+        impl Event {
+            pub fn kind(&self) -> EventKind {
+                match self {
+                    $(Event::$variant(_) => EventKind::$variant),*
+                }
+            }
+        }
+
+        $(
+            impl From<$event> for Event {
+                fn from(event: $event) -> Self {
+                    Event::$variant(event)
+                }
+            }
+
+            impl TryFrom<Event> for $event {
+                type Error = Event;
+
+                fn try_from(event: Event) -> Result<Self, Self::Error> {
+                    match event {
+                        Event::$variant(event) => Ok(event),
+                        event => Err(event),
+                    }
+                }
+            }
+        )*
+    };
+}
+
+gen_event_enum!(
+    Start(StartEvent),
+    Exit(ExitEvent),
+    Disable(DisableEvent),
+    PrivateMessage(PrivateMessageEvent),
+    GroupMessage(GroupMessageEvent),
+    DiscussMessage(DiscussMessageEvent),
+    GroupUpload(GroupUploadEvent),
+    GroupAdmin(GroupAdminEvent),
+    GroupMemberDecrease(GroupMemberDecreaseEvent),
+    GroupMemberIncrease(GroupMemberIncreaseEvent),
+    GroupBan(GroupBanEvent),
+    FriendAdd(FriendAddEvent),
+    AddFriendRequest(AddFriendRequestEvent),
+    AddGroupRequest(AddGroupRequestEvent)
+);
+
+impl Event {
+    /// 事件发生的时间戳，消息事件和酷q生命周期事件没有该信息
+    pub fn time(&self) -> Option<i32> {
+        match self {
+            Event::GroupUpload(e) => Some(e.send_time),
+            Event::GroupAdmin(e) => Some(e.send_time),
+            Event::GroupMemberDecrease(e) => Some(e.send_time),
+            Event::GroupMemberIncrease(e) => Some(e.send_time),
+            Event::GroupBan(e) => Some(e.send_time),
+            Event::FriendAdd(e) => Some(e.send_time),
+            Event::AddFriendRequest(e) => Some(e.send_time),
+            Event::AddGroupRequest(e) => Some(e.send_time),
+            _ => None,
+        }
+    }
+
+    pub fn group_id(&self) -> Option<i64> {
+        match self {
+            Event::GroupMessage(e) => Some(e.group.id()),
+            Event::GroupUpload(e) => Some(e.group.id()),
+            Event::GroupAdmin(e) => Some(e.group.id()),
+            Event::GroupMemberDecrease(e) => Some(e.group.id()),
+            Event::GroupMemberIncrease(e) => Some(e.group.id()),
+            Event::GroupBan(e) => Some(e.group.id()),
+            Event::AddGroupRequest(e) => Some(e.group.id()),
+            _ => None,
+        }
+    }
+
+    pub fn discuss_id(&self) -> Option<i64> {
+        match self {
+            Event::DiscussMessage(e) => Some(e.discuss.discuss_id),
+            _ => None,
+        }
+    }
+
+    /// 事件主体的qq号
+    ///
+    /// 群成员变动和禁言事件为被操作者，全体禁言时为None
+    pub fn user_id(&self) -> Option<i64> {
+        match self {
+            Event::PrivateMessage(e) => Some(e.user.id()),
+            Event::GroupMessage(e) => Some(e.user.id()),
+            Event::DiscussMessage(e) => Some(e.user.id()),
+            Event::GroupUpload(e) => Some(e.user.id()),
+            Event::GroupAdmin(e) => Some(e.user.id()),
+            Event::GroupMemberDecrease(e) => Some(e.being_operate_user.id()),
+            Event::GroupMemberIncrease(e) => Some(e.being_operate_user.id()),
+            Event::GroupBan(e) if !e.is_whole_ban() => Some(e.being_operate_user.id()),
+            Event::FriendAdd(e) => Some(e.user.id()),
+            Event::AddFriendRequest(e) => Some(e.user.id()),
+            Event::AddGroupRequest(e) => Some(e.user.id()),
+            _ => None,
+        }
+    }
+
+    pub fn message(&self) -> Option<&Message> {
+        match self {
+            Event::PrivateMessage(e) => Some(&e.msg),
+            Event::GroupMessage(e) => Some(&e.msg),
+            Event::DiscussMessage(e) => Some(&e.msg),
+            _ => None,
+        }
+    }
+}
+
+/// 注册一个接收全部事件的回调
+///
+/// 每个酷q事件只会调用一次，没有listener的事件也会传到这里。
+/// 这需要app.json中有cqrs_builder为每个事件添加的最高优先级的`catch_all_*`函数，
+/// 源码中调用了`listen_all`时才会添加，关闭了默认事件或者在依赖的库中调用时需要自己添加。
+///
+/// 回调在中优先级以下的listener和[模块](crate::module)之前调用，
+/// 与`highest`的listener的先后顺序由酷q决定。
+///
+/// # Examples
+/// ```no_run
+/// use coolq_sdk_rust::{
+///     api::{self, CQLogLevel},
+///     events,
+/// };
+///
+/// // 一般在`#[coolq_sdk_rust::main]`中注册
+/// events::listen_all(|event| {
+///     api::add_log(CQLogLevel::DEBUG, "audit", format!("{:?}", event.kind())).ok();
+/// });
+/// ```
+pub fn listen_all(f: impl Fn(&Event) + Send + Sync + 'static) {
+    CatchAllListeners
+        .write()
+        .expect("cannot write CatchAllListeners")
+        .push(Arc::new(f));
+}
+
+/// 调用全部事件回调，由`#[coolq_sdk_rust::main]`导出的`catch_all_*`函数调用
+///
+/// 回调不能拦截事件，总是返回0。
+#[doc(hidden)]
+pub fn dispatch_all(event: impl Into<Event>) -> i32 {
+    // 复制一份，回调中可以调用listen_all
+    let listeners = CatchAllListeners
+        .read()
+        .expect("cannot read CatchAllListeners")
+        .clone();
+    if listeners.is_empty() {
+        return 0;
+    }
+    let event = event.into();
+    let context = EventContext {
+        kind: event.kind(),
+        group_id: event.group_id(),
+        user_id: event.user_id(),
+    };
+    panic_guard::guard(context, || listeners.iter().for_each(|f| f(&event)));
+    0
+}
+
+/// listener的统一入口，由[`listener`](cqrs_macro::listener)生成的函数调用
+///
+/// 依次调用[中间件](crate::middleware)和listener。
+/// 期间的panic会被[捕获](crate::panic_guard)，此时不拦截事件
+#[doc(hidden)]
+pub fn dispatch<E: Into<Event> + TryFrom<Event>>(
+    event: E, listener: impl FnOnce(E) -> EventResult,
+) -> i32 {
+    let event = event.into();
+    let context = EventContext {
+        kind: event.kind(),
+        group_id: event.group_id(),
+        user_id: event.user_id(),
+    };
+    panic_guard::guard(context, || {
+        if middleware::is_empty() {
+            return listener(E::try_from(event).unwrap_or_else(|_| unreachable!()));
+        }
+        middleware::run(event, |event| {
+            let kind = event.kind();
+            listener(E::try_from(event).unwrap_or_else(|_| {
+                panic!("middleware must not change the event kind ({:?})", kind)
+            }))
+        })
+    })
+    .unwrap_or_default()
+    .into()
+}
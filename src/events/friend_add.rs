@@ -1,18 +1,26 @@
-use crate::targets::user::User;
+use crate::{
+    cache,
+    targets::{lazy::Lazy, user::User},
+};
+
+sub_type!(FriendAddType {
+    Added = 1
+});
 
 #[derive(Debug, Clone)]
 pub struct FriendAddEvent {
-    pub sub_type: i32,
+    pub sub_type: FriendAddType,
     pub send_time: i32,
-    pub user: User,
+    pub user: Lazy<User>,
 }
 
 impl FriendAddEvent {
     pub fn new(sub_type: i32, send_time: i32, user_id: i64) -> Self {
+        cache::invalidate_friends();
         FriendAddEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
-            user: User::new(user_id),
+            user: Lazy::new(user_id),
         }
     }
 }
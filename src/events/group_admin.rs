@@ -1,28 +1,39 @@
-use crate::targets::{group::Group, user::User};
+use crate::{
+    cache,
+    targets::{group::Group, lazy::Lazy, user::User},
+};
+
+sub_type!(GroupAdminType {
+    /// 被取消管理员
+    Unset = 1,
+    /// 被设置为管理员
+    Set = 2
+});
 
 #[derive(Debug, Clone)]
 pub struct GroupAdminEvent {
-    pub sub_type: i32,
+    pub sub_type: GroupAdminType,
     pub send_time: i32,
-    pub group: Group,
-    pub user: User,
+    pub group: Lazy<Group>,
+    pub user: Lazy<User>,
 }
 
 impl GroupAdminEvent {
     pub fn new(sub_type: i32, send_time: i32, group_id: i64, user_id: i64) -> Self {
+        cache::invalidate_group_member(group_id, user_id);
         GroupAdminEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
-            group: Group::new(group_id),
-            user: User::new(user_id),
+            group: Lazy::new(group_id),
+            user: Lazy::in_group(user_id, group_id),
         }
     }
 
     pub fn is_add(&self) -> bool {
-        self.sub_type == 2
+        self.sub_type == GroupAdminType::Set
     }
 
     pub fn is_remove(&self) -> bool {
-        self.sub_type == 1
+        self.sub_type == GroupAdminType::Unset
     }
 }
@@ -1,17 +1,26 @@
 use crate::{
     api,
-    api::Error,
-    targets::{group::Group, user::User},
+    api::{set_group_ban, set_group_whole_ban, Error},
+    cache,
+    targets::{group::Group, lazy::Lazy, user::User},
 };
 
+sub_type!(GroupBanType {
+    /// 解除禁言
+    Unban = 1,
+    /// 禁言
+    Ban = 2
+});
+
 #[derive(Debug, Clone)]
 pub struct GroupBanEvent {
-    pub sub_type: i32,
+    pub sub_type: GroupBanType,
     pub send_time: i32,
-    pub operate_user: User,
-    pub being_operate_user: User,
+    pub operate_user: Lazy<User>,
+    /// 全体禁言时为0，不要访问其信息
+    pub being_operate_user: Lazy<User>,
     pub time: i64,
-    pub group: Group,
+    pub group: Lazy<Group>,
 }
 
 impl GroupBanEvent {
@@ -19,18 +28,19 @@ impl GroupBanEvent {
         sub_type: i32, send_time: i32, group_id: i64, operate_user_id: i64,
         being_operate_user_id: i64, time: i64,
     ) -> Self {
+        cache::invalidate_group_member(group_id, being_operate_user_id);
         GroupBanEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
-            operate_user: User::new(operate_user_id),
-            being_operate_user: User::new(being_operate_user_id),
+            operate_user: Lazy::in_group(operate_user_id, group_id),
+            being_operate_user: Lazy::in_group(being_operate_user_id, group_id),
             time,
-            group: Group::new(group_id),
+            group: Lazy::new(group_id),
         }
     }
 
     pub fn is_whole_ban(&self) -> bool {
-        self.being_operate_user.user_id == 0
+        self.being_operate_user.id() == 0
     }
 
     /// 撤销禁言
@@ -39,9 +49,9 @@ impl GroupBanEvent {
     pub fn revoke(&self) -> crate::api::Result<api::Convert<i32>> {
         if self.is_ban() {
             if self.is_whole_ban() {
-                self.group.set_whole_ban(false)
+                set_group_whole_ban(self.group.id(), false)
             } else {
-                self.group.set_ban(self.being_operate_user.user_id, 0)
+                set_group_ban(self.group.id(), self.being_operate_user.id(), 0)
             }
         } else {
             Err(Error(0))
@@ -49,10 +59,10 @@ impl GroupBanEvent {
     }
 
     pub fn is_unban(&self) -> bool {
-        self.sub_type == 1
+        self.sub_type == GroupBanType::Unban
     }
 
     pub fn is_ban(&self) -> bool {
-        self.sub_type == 2
+        self.sub_type == GroupBanType::Ban
     }
 }
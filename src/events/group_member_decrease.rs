@@ -1,12 +1,24 @@
-use crate::targets::{group::Group, user::User};
+use crate::{
+    cache,
+    targets::{group::Group, lazy::Lazy, user::User},
+};
+
+sub_type!(GroupMemberDecreaseType {
+    /// 主动退出
+    Quit = 1,
+    /// 被踢出
+    Kick = 2,
+    /// 机器人被踢出
+    KickMe = 3
+});
 
 #[derive(Debug, Clone)]
 pub struct GroupMemberDecreaseEvent {
-    pub sub_type: i32,
+    pub sub_type: GroupMemberDecreaseType,
     pub send_time: i32,
-    pub operate_user: User,
-    pub being_operate_user: User,
-    pub group: Group,
+    pub operate_user: Lazy<User>,
+    pub being_operate_user: Lazy<User>,
+    pub group: Lazy<Group>,
 }
 
 impl GroupMemberDecreaseEvent {
@@ -14,26 +26,32 @@ impl GroupMemberDecreaseEvent {
         sub_type: i32, send_time: i32, group_id: i64, operate_user_id: i64,
         being_operate_user_id: i64,
     ) -> Self {
+        let sub_type = GroupMemberDecreaseType::from(sub_type);
+        cache::invalidate_group(group_id);
+        cache::invalidate_group_member(group_id, being_operate_user_id);
+        if let GroupMemberDecreaseType::KickMe = sub_type {
+            cache::invalidate_group_members(group_id);
+        }
         GroupMemberDecreaseEvent {
             sub_type,
             send_time,
-            operate_user: User::new(operate_user_id),
-            being_operate_user: User::new(being_operate_user_id),
-            group: Group::new(group_id),
+            operate_user: Lazy::in_group(operate_user_id, group_id),
+            being_operate_user: Lazy::new(being_operate_user_id),
+            group: Lazy::new(group_id),
         }
     }
 
     /// 主动退出
     pub fn is_quit(&self) -> bool {
-        self.sub_type == 1
+        self.sub_type == GroupMemberDecreaseType::Quit
     }
 
     /// 被踢出
     pub fn is_kick(&self) -> bool {
-        self.sub_type == 2 || self.sub_type == 3
+        self.sub_type == GroupMemberDecreaseType::Kick || self.is_kick_me()
     }
 
     pub fn is_kick_me(&self) -> bool {
-        self.sub_type == 3
+        self.sub_type == GroupMemberDecreaseType::KickMe
     }
 }
@@ -1,12 +1,22 @@
-use crate::targets::{group::Group, user::User};
+use crate::{
+    cache,
+    targets::{group::Group, lazy::Lazy, user::User},
+};
+
+sub_type!(GroupMemberIncreaseType {
+    /// 管理员同意入群
+    Approve = 1,
+    /// 被邀请入群
+    Invite = 2
+});
 
 #[derive(Debug, Clone)]
 pub struct GroupMemberIncreaseEvent {
-    pub sub_type: i32,
+    pub sub_type: GroupMemberIncreaseType,
     pub send_time: i32,
-    pub operate_user: User,
-    pub being_operate_user: User,
-    pub group: Group,
+    pub operate_user: Lazy<User>,
+    pub being_operate_user: Lazy<User>,
+    pub group: Lazy<Group>,
 }
 
 impl GroupMemberIncreaseEvent {
@@ -14,17 +24,19 @@ impl GroupMemberIncreaseEvent {
         sub_type: i32, send_time: i32, group_id: i64, operate_user_id: i64,
         being_operate_user_id: i64,
     ) -> Self {
+        cache::invalidate_group(group_id);
+        cache::invalidate_group_member(group_id, being_operate_user_id);
         GroupMemberIncreaseEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
-            operate_user: User::new(operate_user_id),
-            being_operate_user: User::new(being_operate_user_id),
-            group: Group::new(group_id),
+            operate_user: Lazy::in_group(operate_user_id, group_id),
+            being_operate_user: Lazy::in_group(being_operate_user_id, group_id),
+            group: Lazy::new(group_id),
         }
     }
 
     /// 被邀请入群
     pub fn is_invite(&self) -> bool {
-        self.sub_type == 2
+        self.sub_type == GroupMemberIncreaseType::Invite
     }
 }
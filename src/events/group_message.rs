@@ -5,21 +5,27 @@ use crate::{
     targets::{
         Anonymous,
         group::Group,
+        lazy::Lazy,
         message::{Message, SendMessage},
         user::User,
     },
 };
-use crate::api::get_group_member_info_v2;
-use crate::targets::group::GroupMember;
+
+sub_type!(GroupMessageType {
+    Normal = 1,
+    Anonymous = 2,
+    /// 系统消息
+    System = 3
+});
 
 #[derive(Debug, Clone)]
 pub struct GroupMessageEvent {
-    pub sub_type: i32,
+    pub sub_type: GroupMessageType,
     pub anonymous_flag: Flag,
     pub msg: Message,
     pub font: i32,
-    pub group: Group,
-    pub user: User,
+    pub group: Lazy<Group>,
+    pub user: Lazy<User>,
 }
 
 impl GroupMessageEvent {
@@ -28,18 +34,12 @@ impl GroupMessageEvent {
         msg: *const c_char, font: i32,
     ) -> Self {
         GroupMessageEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             anonymous_flag: Convert::from(anonymous_flag).into(),
             msg: Message::new(msg, msg_id),
             font,
-            group: Group::new(group_id),
-            user: {
-                let mut user = User::new(user_id);
-                if let Ok(gm) = get_group_member_info_v2(group_id, user_id, false) {
-                    user.set_authority(gm.try_to::<GroupMember>().unwrap().authority);
-                }
-                user
-            },
+            group: Lazy::new(group_id),
+            user: Lazy::in_group(user_id, group_id),
         }
     }
 
@@ -53,7 +53,7 @@ impl GroupMessageEvent {
 
     pub fn get_anonymous(&self) -> std::io::Result<Anonymous> {
         if self.is_anonymous() {
-            Anonymous::decode(self.anonymous_flag.as_bytes(), self.group.group_id)
+            Anonymous::decode(self.anonymous_flag.as_bytes(), self.group.id())
         } else {
             Ok(Anonymous::default())
         }
@@ -64,6 +64,6 @@ impl GroupMessageEvent {
     }
 
     pub fn reply_at(&self, msg: impl ToString) -> crate::api::Result<i32> {
-        self.group.at(self.user.user_id, msg)
+        self.group.at(self.user.id(), msg)
     }
 }
@@ -1,25 +1,68 @@
-use crate::{api::Convert, targets::File};
 use std::{convert::TryInto, os::raw::c_char};
 
+use crate::{
+    api::Convert,
+    targets::{
+        group::Group,
+        lazy::Lazy,
+        message::{MessageSegment, SendMessage},
+        user::User,
+        File,
+    },
+};
+
+sub_type!(GroupUploadType {
+    Upload = 1
+});
+
 #[derive(Debug, Clone)]
 pub struct GroupUploadEvent {
-    pub sub_type: i32,
+    pub sub_type: GroupUploadType,
     pub send_time: i32,
+    pub group: Lazy<Group>,
+    pub user: Lazy<User>,
+    /// 解析失败时为默认值
+    pub file: File,
+    #[deprecated(note = "使用`group.id()`")]
     pub group_id: i64,
+    #[deprecated(note = "使用`user.id()`")]
     pub user_id: i64,
-    pub file: File,
 }
 
 impl GroupUploadEvent {
+    #[allow(deprecated)]
     pub fn new(
         sub_type: i32, send_time: i32, group_id: i64, user_id: i64, file: *const c_char,
     ) -> Self {
         GroupUploadEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             send_time,
+            group: Lazy::new(group_id),
+            user: Lazy::in_group(user_id, group_id),
+            file: Convert::from(file).try_into().unwrap_or_default(),
             group_id,
             user_id,
-            file: Convert::from(file).try_into().unwrap(),
         }
     }
+
+    pub fn reply(&self, msg: impl ToString) -> crate::api::Result<i32> {
+        self.group.send_message(msg)
+    }
+
+    pub fn reply_at(&self, msg: impl ToString) -> crate::api::Result<i32> {
+        self.group.at(self.user.id(), msg)
+    }
+
+    /// 在群里回复，并附上文件名和大小
+    ///
+    /// 如: `@上传者 已归档\n[文件] xxx.zip (1.5 MB)`
+    pub fn reply_file(&self, msg: impl ToString) -> crate::api::Result<i32> {
+        self.group.at(
+            self.user.id(),
+            MessageSegment::new()
+                .add(msg)
+                .newline()
+                .add(format!("[文件] {} ({})", self.file.name, self.file.human_size())),
+        )
+    }
 }
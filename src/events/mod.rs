@@ -1,6 +1,50 @@
+/// 生成事件子类型的枚举，未知的值保存在`Unknown`中
+///
+/// `deprecated`中的是旧版本的变体，只为了兼容而保留，不会再出现，转换为i32时为0。
+macro_rules! sub_type {
+    ($(#[$doc: meta])* $name: ident {
+        $($(#[$vdoc: meta])* $variant: ident = $value: literal),*
+    } $(deprecated { $($old: ident),* })?) => {
+        $(#[$doc])*
+        #[derive(Debug, Clone, Copy, Eq, PartialEq)]
+        pub enum $name {
+            $($(#[$vdoc])* $variant,)*
+            $($(
+                #[deprecated(note = "不会再出现，未知的子类型请使用`Unknown`")]
+                $old,
+            )*)?
+            /// 未知的子类型，保留原始值
+            Unknown(i32),
+        }
+
+        impl From<i32> for $name {
+            fn from(i: i32) -> Self {
+                match i {
+                    $($value => $name::$variant,)*
+                    i => $name::Unknown(i),
+                }
+            }
+        }
+
+        impl From<$name> for i32 {
+            fn from(t: $name) -> i32 {
+                match t {
+                    $($name::$variant => $value,)*
+                    $($(
+                        #[allow(deprecated)]
+                        $name::$old => 0,
+                    )*)?
+                    $name::Unknown(i) => i,
+                }
+            }
+        }
+    };
+}
+
 mod add_friend_request;
 mod add_group_request;
 mod discuss_message;
+mod event;
 mod friend_add;
 mod group_admin;
 mod group_ban;
@@ -9,10 +53,13 @@ mod group_member_increase;
 mod group_message;
 mod group_upload;
 mod private_message;
+mod raw;
+mod result;
 
 pub use add_friend_request::*;
 pub use add_group_request::*;
 pub use discuss_message::*;
+pub use event::*;
 pub use friend_add::*;
 pub use group_admin::*;
 pub use group_ban::*;
@@ -21,6 +68,8 @@ pub use group_member_increase::*;
 pub use group_message::*;
 pub use group_upload::*;
 pub use private_message::*;
+pub use raw::*;
+pub use result::*;
 
 macro_rules! impl_new {
     ($($name:ident),*) => {
@@ -35,10 +84,13 @@ macro_rules! impl_new {
 }
 
 #[allow(dead_code)]
+#[derive(Debug, Clone)]
 pub struct StartEvent;
 #[allow(dead_code)]
+#[derive(Debug, Clone)]
 pub struct ExitEvent;
 #[allow(dead_code)]
+#[derive(Debug, Clone)]
 pub struct DisableEvent;
 
 impl_new!(StartEvent, ExitEvent, DisableEvent);
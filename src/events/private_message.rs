@@ -1,44 +1,38 @@
 use std::os::raw::c_char;
 
 use crate::targets::{
+    lazy::Lazy,
     message::{Message, SendMessage},
     user::User,
 };
 
-#[derive(Debug, Clone)]
-pub enum PrivateMessageType {
-    Friend,
-    Group,
-    Discuss,
-    Other,
-}
+sub_type!(PrivateMessageType {
+    /// 来自在线状态
+    OnlineState = 1,
+    /// 来自群临时会话
+    Group = 2,
+    /// 来自讨论组临时会话
+    Discuss = 3,
+    Friend = 11
+} deprecated {
+    Other
+});
 
-impl From<i32> for PrivateMessageType {
-    fn from(i: i32) -> Self {
-        match i {
-            11 => PrivateMessageType::Friend,
-            2 => PrivateMessageType::Group,
-            3 => PrivateMessageType::Discuss,
-            _ => PrivateMessageType::Other,
-        }
-    }
-}
-
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct PrivateMessageEvent {
-    pub sub_type: i32,
+    pub sub_type: PrivateMessageType,
     pub msg: Message,
     pub font: i32,
-    pub user: User,
+    pub user: Lazy<User>,
 }
 
 impl PrivateMessageEvent {
     pub fn new(sub_type: i32, msg_id: i32, user_id: i64, msg: *const c_char, font: i32) -> Self {
         PrivateMessageEvent {
-            sub_type,
+            sub_type: sub_type.into(),
             msg: Message::new(msg, msg_id),
             font,
-            user: User::new(user_id),
+            user: Lazy::new(user_id),
         }
     }
 
@@ -51,6 +45,6 @@ impl PrivateMessageEvent {
     }
 
     pub fn get_sub_type(&self) -> PrivateMessageType {
-        PrivateMessageType::from(self.sub_type)
+        self.sub_type
     }
 }
@@ -0,0 +1,118 @@
+use std::{ffi::CStr, fmt::Debug, os::raw::c_char};
+
+use crate::iconv::IconvDecodable;
+
+/// 酷q传给listener的参数，或api的参数和返回值，字符串已转换为utf8
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "recorder",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(untagged)
+)]
+pub enum RawValue {
+    /// 空指针
+    Null,
+    Int(i64),
+    Bool(bool),
+    Str(String),
+}
+
+impl RawValue {
+    pub fn as_int(&self) -> Option<i64> {
+        match self {
+            RawValue::Int(i) => Some(*i),
+            RawValue::Bool(b) => Some(*b as i64),
+            _ => None,
+        }
+    }
+
+    /// 空指针视为空字符串
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            RawValue::Str(s) => Some(s),
+            RawValue::Null => Some(""),
+            _ => None,
+        }
+    }
+}
+
+impl From<i32> for RawValue {
+    fn from(i: i32) -> Self {
+        RawValue::Int(i as i64)
+    }
+}
+
+impl From<i64> for RawValue {
+    fn from(i: i64) -> Self {
+        RawValue::Int(i)
+    }
+}
+
+impl From<bool> for RawValue {
+    fn from(b: bool) -> Self {
+        RawValue::Bool(b)
+    }
+}
+
+impl RawValue {
+    /// 由宏生成的代码调用，读取酷q传入的字符串
+    ///
+    /// # Safety
+    /// `ptr`必须为空指针，或指向有效的以`\0`结尾的字符串
+    #[doc(hidden)]
+    pub unsafe fn from_ptr(ptr: *const c_char) -> Self {
+        if ptr.is_null() {
+            return RawValue::Null;
+        }
+        let bytes = CStr::from_ptr(ptr).to_bytes();
+        RawValue::Str(
+            bytes
+                .decode_with_encoding("GB18030")
+                .unwrap_or_else(|| String::from_utf8_lossy(bytes).into_owned()),
+        )
+    }
+}
+
+/// api的参数和返回值转换为[`RawValue`]
+#[cfg(feature = "recorder")]
+pub(crate) trait ToRaw {
+    /// # Safety
+    /// 指针必须为空指针，或指向有效的以`\0`结尾的字符串
+    unsafe fn to_raw(self) -> RawValue;
+}
+
+#[cfg(feature = "recorder")]
+macro_rules! to_raw {
+    ($($t: ty),*) => {
+        $(
+            impl ToRaw for $t {
+                unsafe fn to_raw(self) -> RawValue {
+                    RawValue::from(self)
+                }
+            }
+        )*
+    };
+}
+
+#[cfg(feature = "recorder")]
+to_raw!(i32, i64, bool);
+
+#[cfg(feature = "recorder")]
+impl ToRaw for *const c_char {
+    unsafe fn to_raw(self) -> RawValue {
+        RawValue::from_ptr(self)
+    }
+}
+
+/// 由`#[listener]`生成的函数调用，开启`recorder` feature并开始录制时记录事件
+///
+/// `exported`为导出的函数名，用于区分同一次分发中的各个优先级
+#[doc(hidden)]
+pub fn record<E: Debug>(
+    function: &'static str, exported: &'static str, args: impl FnOnce() -> Vec<RawValue>,
+    event: E,
+) -> E {
+    #[cfg(feature = "recorder")]
+    crate::recorder::record_event(function, exported, args, &event);
+    event
+}
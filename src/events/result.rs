@@ -0,0 +1,94 @@
+use std::fmt::Display;
+
+use crate::api::{add_log, CQLogLevel};
+
+/// listener的返回值，决定是否拦截事件
+///
+/// # Examples
+/// ```no_run
+/// use coolq_sdk_rust::prelude::*;
+///
+/// #[listener]
+/// fn block_spam(event: GroupMessageEvent) -> EventResult {
+///     if event.get_message().raw_msg.contains("广告") {
+///         EventResult::Block
+///     } else {
+///         EventResult::Ignore
+///     }
+/// }
+///
+/// // 返回Err时会记录错误，并且不拦截事件
+/// #[listener]
+/// fn may_fail(event: PrivateMessageEvent) -> Result<EventResult, String> {
+///     event.reply("hello").map_err(|err| format!("{:?}", err))?;
+///     Ok(EventResult::Block)
+/// }
+/// ```
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Default)]
+pub enum EventResult {
+    /// 忽略事件，交给其他插件处理
+    #[default]
+    Ignore,
+    /// 拦截事件，优先级更低的插件将不会收到该事件
+    Block,
+}
+
+impl From<EventResult> for i32 {
+    fn from(result: EventResult) -> i32 {
+        match result {
+            EventResult::Ignore => 0,
+            EventResult::Block => 1,
+        }
+    }
+}
+
+impl From<i32> for EventResult {
+    fn from(i: i32) -> Self {
+        if i == 0 {
+            EventResult::Ignore
+        } else {
+            EventResult::Block
+        }
+    }
+}
+
+/// listener可以使用的返回值类型
+///
+/// `()`、[`EventResult`]、`i32`（兼容旧的写法，非0为拦截），以及`Result<T, E>`（T为以上类型）。
+pub trait IntoEventResult {
+    fn into_event_result(self) -> EventResult;
+}
+
+impl IntoEventResult for () {
+    fn into_event_result(self) -> EventResult {
+        EventResult::Ignore
+    }
+}
+
+impl IntoEventResult for EventResult {
+    fn into_event_result(self) -> EventResult {
+        self
+    }
+}
+
+impl IntoEventResult for i32 {
+    fn into_event_result(self) -> EventResult {
+        EventResult::from(self)
+    }
+}
+
+impl<T: IntoEventResult, E: Display> IntoEventResult for Result<T, E> {
+    fn into_event_result(self) -> EventResult {
+        match self {
+            Ok(result) => result.into_event_result(),
+            Err(err) => {
+                let _ = add_log(
+                    CQLogLevel::ERROR,
+                    "listener",
+                    format!("listener returned an error: {}", err),
+                );
+                EventResult::Ignore
+            },
+        }
+    }
+}
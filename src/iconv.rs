@@ -309,6 +309,13 @@ impl<W: Write> Write for IconvWriter<W> {
 
 // TODO: use Result<> instead of Option<> to indicate Error
 pub fn convert_bytes(inbuf: &[u8], from: &str, to: &str) -> Option<Vec<u8>> {
+    // 模拟时没有libiconv.dll，字符串全部使用utf8
+    #[cfg(feature = "testing")]
+    {
+        if crate::testing::is_active() {
+            return Some(inbuf.to_vec());
+        }
+    }
     let converter = Converter::new(from, to);
     let mut outbuf_size = inbuf.len() * 2;
     let mut total_nread = 0;
@@ -17,6 +17,15 @@
 //! * `enhanced-cqcode`: 开启 [增强cq码(图片)][enhanced-cqcode]
 //! * `async-listener`: 开启async事件回调函数
 //! * `tokio-threaded`: 开启tokio的rt-threaded feature。
+//! * `logger`: 开启[`log`](crate::logger::CQLogger)的实现
+//! * `tracing-logger`: 开启[`tracing`](crate::logger::CQLayer)的Layer
+//! * `scheduler`: 开启[定时任务](crate::scheduler)
+//! * `storage`: 开启[数据存储](crate::storage)
+//! * `config`: 开启[配置文件](crate::config)
+//! * `testing`: 开启[模拟测试](crate::testing)
+//! * `recorder`: 开启[事件录制](crate::recorder)，同时开启`testing`时可以[回放](crate::testing::replay)
+//! * `onebot`: 开启[OneBot v11](crate::onebot)的HTTP和WebSocket服务
+//! * `backtrace`: [panic](crate::panic_guard)时记录backtrace，需要Rust 1.65以上
 //!
 //! [enhanced-cqcode]: crate::targets::cqcode::CQImage
 //!
@@ -29,7 +38,7 @@
 //! coolq-sdk-rust = "0.1"
 //!
 //! [build-dependencies]
-//! cqrs_builder = { version = "0.1", features = ["full-priority"] }
+//! cqrs_builder = "0.1"
 //!
 //! [lib]
 //! crate-type = ["cdylib"]
@@ -51,12 +60,9 @@
 //!         .finish();
 //! }
 //! ```
-//! 目前还支持一个`feature`:
+//! [cqrs_builder](https://docs.rs/cqrs_builder)会扫描`src`下的源码，只为定义了的listener按其 [**优先级**](https://docs.cqp.im/dev/v9/app.json/event/#priority) 生成事件，
+//! 同一事件同一优先级定义了多个listener时会编译失败。
 //!
-//! * `full-priority` :
-//!
-//! > 启用该功能之后，[cqrs_builder](https://docs.rs/cqrs_builder)会生成支持全部 [**优先级**](https://docs.cqp.im/dev/v9/app.json/event/#priority) 的app.json
-//! >
 //! > 更多信息可以在[AppJson](https://docs.rs/cqrs_builder/latest/cqrs_builder/struct.AppJson.html)找到。
 //!
 //!
@@ -72,7 +78,7 @@
 //! }
 //!
 //! // `priority`可选填，默认中优先级。
-//! // 开启`full-priority`功能之后，`priority`才会生效。否则除medium外的回调函数将不会被酷q调用
+//! // cqrs_builder会扫描源码，按listener的优先级生成app.json中的事件
 //! #[listener(priority = "high")]
 //! fn this_is_private_msg(event: PrivateMessageEvent) {
 //!     event.reply("hello");
@@ -106,31 +112,65 @@
 //!         event.reply_at(format!("信息含有以下cq码: {:?}", msg).no_cq_code());
 //!     }
 //! }
+//!
+//! // 菜单，cqrs_builder会自动添加到app.json中
+//! #[menu(name = "设置")]
+//! fn settings() {
+//!     api::add_log(CQLogLevel::INFO, "menu", "打开设置").ok();
+//! }
 //! ```
 
 #[macro_use]
 extern crate lazy_static;
 
-use std::panic::set_hook;
-
 #[doc(hidden)]
 pub use cqrs_macro::main;
 
-use crate::api::set_fatal;
-
 mod iconv;
 
 pub mod api;
+pub mod cache;
+#[cfg(feature = "config")]
+#[cfg_attr(docsrs, doc(cfg(feature = "config")))]
+pub mod config;
 pub mod events;
+#[cfg(any(feature = "logger", feature = "tracing-logger"))]
+#[cfg_attr(docsrs, doc(cfg(any(feature = "logger", feature = "tracing-logger"))))]
+pub mod logger;
+pub mod middleware;
+pub mod module;
+#[cfg(feature = "onebot")]
+#[cfg_attr(docsrs, doc(cfg(feature = "onebot")))]
+pub mod onebot;
+pub mod panic_guard;
+pub mod permission;
+#[cfg(feature = "recorder")]
+#[cfg_attr(docsrs, doc(cfg(feature = "recorder")))]
+pub mod recorder;
+#[cfg(feature = "scheduler")]
+#[cfg_attr(docsrs, doc(cfg(feature = "scheduler")))]
+pub mod scheduler;
+pub mod status;
+#[cfg(feature = "storage")]
+#[cfg_attr(docsrs, doc(cfg(feature = "storage")))]
+pub mod storage;
 pub mod targets;
+#[cfg(feature = "testing")]
+#[cfg_attr(docsrs, doc(cfg(feature = "testing")))]
+pub mod testing;
 
 pub mod prelude {
     pub use crate::{
         api::{self, Convert, CQLogLevel},
         events::*,
-        targets::{cqcode::*, group::Group, message::*, user::User, Anonymous, File},
+        targets::{
+            cqcode::*, discuss::Discuss, group::Group, message::*, user::User, Anonymous, File,
+            FileType,
+        },
     };
     pub use cqrs_macro::listener;
+    pub use cqrs_macro::menu;
+    pub use cqrs_macro::status;
     pub use cqrs_macro::block_on;
 }
 
@@ -153,13 +193,31 @@ pub fn block_on<F: Future>(f: F) -> F::Output {
 
 pub const APIVER: usize = 9;
 
+/// 插件启用，在`main`函数之后调用
+#[doc(hidden)]
+pub fn enable() {
+    module::enable();
+    #[cfg(feature = "scheduler")]
+    scheduler::start();
+}
+
+/// 插件停用或酷q退出
+#[doc(hidden)]
+pub fn disable() {
+    module::disable();
+    middleware::clear();
+    #[cfg(feature = "scheduler")]
+    scheduler::stop();
+    #[cfg(feature = "config")]
+    config::unwatch();
+    #[cfg(feature = "recorder")]
+    recorder::stop();
+}
+
 #[doc(hidden)]
 #[export_name = "Initialize"]
-pub unsafe extern "stdcall" fn initialize(auth_code: i32) -> i32 {
-    set_hook(Box::new(|info| {
-        // 在 mirai-native 上会返回 0 而被当成错误
-        let _ = set_fatal(info.to_string());
-    }));
+pub unsafe extern "system" fn initialize(auth_code: i32) -> i32 {
+    panic_guard::install_hook();
     api::init(auth_code);
     0
 }
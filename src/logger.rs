@@ -0,0 +1,373 @@
+//! 把[`log`]和[`tracing`]的日志输出到酷q
+//!
+//! 需要开启`logger`（log）或`tracing-logger`（tracing）feature。
+//!
+//! 日志等级对应关系:
+//!
+//! | log/tracing | 酷q |
+//! | --- | --- |
+//! | Error | ERROR |
+//! | Warn | WARNING |
+//! | Info | INFO |
+//! | Debug, Trace | DEBUG |
+//!
+//! Info等级的日志target为[`RECV`]、[`SEND`]、[`SUCCESS`]时，分别对应酷q的INFORECV、INFOSEND、INFOSUCCESS。
+//!
+//! 日志的tag为产生日志的模块名（模块路径的最后一段），tracing的字段会以`key=value`的形式附加在消息后面。
+//!
+//! 可以通过[`FileSink`]同时把日志写入插件数据目录下的`logs`文件夹，文件超过大小后会自动轮转。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::logger::{CQLogger, FileSink, RECV};
+//!
+//! // 在`#[coolq_sdk_rust::main]`中初始化
+//! CQLogger::new()
+//!     .level(log::LevelFilter::Debug)
+//!     .file(FileSink::new("plugin").unwrap().max_size(1024 * 1024).keep(3))
+//!     .init()
+//!     .unwrap();
+//!
+//! log::info!("插件已启用");
+//! log::info!(target: RECV, "收到消息");
+//! ```
+//!
+//! [`log`]: https://docs.rs/log
+//! [`tracing`]: https://docs.rs/tracing
+
+use std::{
+    fs::{create_dir_all, rename, File, OpenOptions},
+    io::{Result as IOResult, Write},
+    path::PathBuf,
+    sync::Mutex,
+};
+
+use crate::api::{add_log, get_app_directory, CQLogLevel};
+
+/// 酷q的INFORECV（信息(接收)）
+pub const RECV: &str = "recv";
+/// 酷q的INFOSEND（信息(发送)）
+pub const SEND: &str = "send";
+/// 酷q的INFOSUCCESS（信息(成功)）
+pub const SUCCESS: &str = "success";
+
+/// 按大小轮转的日志文件
+///
+/// 写入`{目录}/{name}.log`，超过`max_size`后依次重命名为`{name}.1.log`、`{name}.2.log`...，最多保留`keep`个旧文件。
+/// `keep`为0时直接清空当前文件。
+pub struct FileSink {
+    dir: PathBuf,
+    name: String,
+    max_size: u64,
+    keep: usize,
+    file: Mutex<Option<(File, u64)>>,
+}
+
+impl FileSink {
+    /// 写入插件数据目录下的`logs`文件夹，默认单个文件10MB，保留5个旧文件
+    pub fn new(name: &str) -> IOResult<FileSink> {
+        let app_dir = get_app_directory()
+            .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?
+            .to::<String>();
+        FileSink::with_dir(PathBuf::from(app_dir).join("logs"), name)
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>, name: &str) -> IOResult<FileSink> {
+        let dir = dir.into();
+        create_dir_all(&dir)?;
+        Ok(FileSink {
+            dir,
+            name: name.to_owned(),
+            max_size: 10 * 1024 * 1024,
+            keep: 5,
+            file: Mutex::new(None),
+        })
+    }
+
+    /// 单个文件的最大字节数
+    pub fn max_size(mut self, max_size: u64) -> Self {
+        self.max_size = max_size;
+        self
+    }
+
+    /// 保留的旧文件个数
+    pub fn keep(mut self, keep: usize) -> Self {
+        self.keep = keep;
+        self
+    }
+
+    fn path(&self, index: usize) -> PathBuf {
+        if index == 0 {
+            self.dir.join(format!("{}.log", self.name))
+        } else {
+            self.dir.join(format!("{}.{}.log", self.name, index))
+        }
+    }
+
+    fn rotate(&self) -> IOResult<()> {
+        if self.keep == 0 {
+            return File::create(self.path(0)).map(|_| ());
+        }
+        for i in (0..self.keep).rev() {
+            let from = self.path(i);
+            if from.exists() {
+                rename(from, self.path(i + 1))?;
+            }
+        }
+        Ok(())
+    }
+
+    pub fn write(&self, level: &str, tag: &str, msg: &str) -> IOResult<()> {
+        let line = format!(
+            "{} [{}] {}: {}\n",
+            chrono::Local::now().format("%Y-%m-%d %H:%M:%S%.3f"),
+            level,
+            tag,
+            msg
+        );
+        let mut file = self.file.lock().expect("cannot lock log file");
+        if let Some((_, size)) = &*file {
+            if size + line.len() as u64 > self.max_size {
+                *file = None;
+                self.rotate()?;
+            }
+        }
+        if file.is_none() {
+            let f = OpenOptions::new()
+                .create(true)
+                .append(true)
+                .open(self.path(0))?;
+            let size = f.metadata()?.len();
+            *file = Some((f, size));
+        }
+        let (f, size) = file.as_mut().unwrap();
+        f.write_all(line.as_bytes())?;
+        *size += line.len() as u64;
+        Ok(())
+    }
+}
+
+fn level_name(level: &CQLogLevel) -> &'static str {
+    match level {
+        CQLogLevel::DEBUG => "DEBUG",
+        CQLogLevel::INFO => "INFO",
+        CQLogLevel::INFOSUCCESS => "INFOSUCCESS",
+        CQLogLevel::INFORECV => "INFORECV",
+        CQLogLevel::INFOSEND => "INFOSEND",
+        CQLogLevel::WARNING => "WARNING",
+        CQLogLevel::ERROR => "ERROR",
+        CQLogLevel::FATAL => "FATAL",
+    }
+}
+
+fn info_level(target: &str) -> CQLogLevel {
+    match target {
+        RECV => CQLogLevel::INFORECV,
+        SEND => CQLogLevel::INFOSEND,
+        SUCCESS => CQLogLevel::INFOSUCCESS,
+        _ => CQLogLevel::INFO,
+    }
+}
+
+/// 模块路径的最后一段
+fn tag_of<'a>(module_path: Option<&'a str>, target: &'a str) -> &'a str {
+    let path = module_path.unwrap_or(target);
+    path.rsplit("::").next().unwrap_or(path)
+}
+
+fn output(file: &Option<FileSink>, level: CQLogLevel, tag: &str, msg: &str) {
+    if let Some(file) = file {
+        let _ = file.write(level_name(&level), tag, msg);
+    }
+    let _ = add_log(level, tag, msg);
+}
+
+#[cfg(feature = "logger")]
+#[cfg_attr(docsrs, doc(cfg(feature = "logger")))]
+pub use self::log_impl::CQLogger;
+
+#[cfg(feature = "logger")]
+mod log_impl {
+    use log::{Level, LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+    use super::{info_level, output, tag_of, FileSink};
+    use crate::api::CQLogLevel;
+
+    /// [`log`](https://docs.rs/log)的实现
+    pub struct CQLogger {
+        level: LevelFilter,
+        file: Option<FileSink>,
+    }
+
+    impl CQLogger {
+        pub fn new() -> CQLogger {
+            CQLogger {
+                level: LevelFilter::Info,
+                file: None,
+            }
+        }
+
+        pub fn level(mut self, level: LevelFilter) -> Self {
+            self.level = level;
+            self
+        }
+
+        pub fn file(mut self, file: FileSink) -> Self {
+            self.file = Some(file);
+            self
+        }
+
+        /// 设置为全局logger
+        pub fn init(self) -> Result<(), SetLoggerError> {
+            log::set_max_level(self.level);
+            log::set_boxed_logger(Box::new(self))
+        }
+    }
+
+    impl Default for CQLogger {
+        fn default() -> Self {
+            CQLogger::new()
+        }
+    }
+
+    impl Log for CQLogger {
+        fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+            metadata.level() <= self.level
+        }
+
+        fn log(&self, record: &Record<'_>) {
+            if !self.enabled(record.metadata()) {
+                return;
+            }
+            let level = match record.level() {
+                Level::Error => CQLogLevel::ERROR,
+                Level::Warn => CQLogLevel::WARNING,
+                Level::Info => info_level(record.target()),
+                Level::Debug | Level::Trace => CQLogLevel::DEBUG,
+            };
+            output(
+                &self.file,
+                level,
+                tag_of(record.module_path(), record.target()),
+                &record.args().to_string(),
+            );
+        }
+
+        fn flush(&self) {}
+    }
+}
+
+#[cfg(feature = "tracing-logger")]
+#[cfg_attr(docsrs, doc(cfg(feature = "tracing-logger")))]
+pub use self::tracing_impl::CQLayer;
+
+#[cfg(feature = "tracing-logger")]
+mod tracing_impl {
+    use std::fmt::{Debug, Write};
+
+    use tracing::{
+        field::{Field, Visit},
+        level_filters::LevelFilter,
+        Event, Level, Subscriber,
+    };
+    use tracing_subscriber::{
+        layer::{Context, Layer, SubscriberExt},
+        registry::Registry,
+    };
+
+    use super::{info_level, output, tag_of, FileSink};
+    use crate::api::CQLogLevel;
+
+    /// [`tracing`](https://docs.rs/tracing)的Layer
+    ///
+    /// # Examples
+    /// ```no_run
+    /// use coolq_sdk_rust::logger::CQLayer;
+    /// use tracing::level_filters::LevelFilter;
+    ///
+    /// CQLayer::new().level(LevelFilter::DEBUG).init().unwrap();
+    /// tracing::info!(user_id = 12345, "收到消息");
+    /// ```
+    pub struct CQLayer {
+        level: LevelFilter,
+        file: Option<FileSink>,
+    }
+
+    impl CQLayer {
+        /// 默认只输出Info及以上等级的日志
+        pub fn new() -> CQLayer {
+            CQLayer {
+                level: LevelFilter::INFO,
+                file: None,
+            }
+        }
+
+        pub fn level(mut self, level: LevelFilter) -> Self {
+            self.level = level;
+            self
+        }
+
+        pub fn file(mut self, file: FileSink) -> Self {
+            self.file = Some(file);
+            self
+        }
+
+        /// 以该Layer作为全局subscriber
+        pub fn init(self) -> Result<(), tracing::dispatcher::SetGlobalDefaultError> {
+            tracing::subscriber::set_global_default(Registry::default().with(self))
+        }
+    }
+
+    impl Default for CQLayer {
+        fn default() -> Self {
+            CQLayer::new()
+        }
+    }
+
+    #[derive(Default)]
+    struct FieldVisitor {
+        message: String,
+        fields: String,
+    }
+
+    impl Visit for FieldVisitor {
+        fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+            if field.name() == "message" {
+                let _ = write!(self.message, "{:?}", value);
+            } else {
+                let _ = write!(self.fields, " {}={:?}", field.name(), value);
+            }
+        }
+
+        fn record_str(&mut self, field: &Field, value: &str) {
+            if field.name() == "message" {
+                self.message.push_str(value);
+            } else {
+                let _ = write!(self.fields, " {}={}", field.name(), value);
+            }
+        }
+    }
+
+    impl<S: Subscriber> Layer<S> for CQLayer {
+        fn on_event(&self, event: &Event<'_>, _: Context<'_, S>) {
+            let meta = event.metadata();
+            if self.level < *meta.level() {
+                return;
+            }
+            let level = match *meta.level() {
+                Level::ERROR => CQLogLevel::ERROR,
+                Level::WARN => CQLogLevel::WARNING,
+                Level::INFO => info_level(meta.target()),
+                _ => CQLogLevel::DEBUG,
+            };
+            let mut visitor = FieldVisitor::default();
+            event.record(&mut visitor);
+            output(
+                &self.file,
+                level,
+                tag_of(meta.module_path(), meta.target()),
+                &format!("{}{}", visitor.message, visitor.fields),
+            );
+        }
+    }
+}
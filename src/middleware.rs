@@ -0,0 +1,116 @@
+//! 中间件
+//!
+//! 中间件包裹在每一次listener调用的外面，可以用来做日志、计时、黑名单过滤等。
+//! 中间件按注册顺序调用，调用[`Next::run`]进入下一个中间件，最后进入listener；不调用则拦截该listener。
+//!
+//! 可以在`#[coolq_sdk_rust::main(middleware(A, B))]`中注册（`A`、`B`需要是可以直接作为表达式的值，如unit struct），
+//! 也可以在运行时调用[`register`]注册。插件停用时会清空已注册的中间件，启用时`main`会重新注册。
+//!
+//! `async fn`的listener会被放到`ASYNC_RUNTIME`中运行，listener函数启动任务后立即返回`EventResult::Ignore`。
+//! 因此中间件得到的返回值总是`Ignore`，计时也只包括启动任务的时间，而不是listener实际的运行时间。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::{
+//!     api::{self, CQLogLevel},
+//!     events::{Event, EventResult},
+//!     middleware::{Middleware, Next},
+//! };
+//! use std::time::Instant;
+//!
+//! struct Timing;
+//!
+//! impl Middleware for Timing {
+//!     fn handle(&self, event: Event, next: Next<'_>) -> EventResult {
+//!         let kind = event.kind();
+//!         let start = Instant::now();
+//!         let result = next.run(event);
+//!         api::add_log(CQLogLevel::DEBUG, "timing", format!("{:?}: {:?}", kind, start.elapsed())).ok();
+//!         result
+//!     }
+//! }
+//!
+//! // 黑名单
+//! coolq_sdk_rust::middleware::register(|event: Event, next: Next<'_>| {
+//!     if event.user_id() == Some(12345) {
+//!         EventResult::Ignore
+//!     } else {
+//!         next.run(event)
+//!     }
+//! });
+//! ```
+
+use std::sync::{Arc, RwLock};
+
+use crate::events::{Event, EventResult};
+
+lazy_static! {
+    static ref Middlewares: RwLock<Vec<Arc<dyn Middleware>>> = RwLock::new(Vec::new());
+}
+
+pub trait Middleware: Send + Sync + 'static {
+    /// `event`为本次事件，返回值为listener的返回值（是否拦截事件）
+    fn handle(&self, event: Event, next: Next<'_>) -> EventResult;
+}
+
+impl<F> Middleware for F
+where
+    F: Fn(Event, Next<'_>) -> EventResult + Send + Sync + 'static,
+{
+    fn handle(&self, event: Event, next: Next<'_>) -> EventResult {
+        self(event, next)
+    }
+}
+
+/// 剩余的中间件和listener
+pub struct Next<'a> {
+    chain: &'a [Arc<dyn Middleware>],
+    listener: Box<dyn FnOnce(Event) -> EventResult + 'a>,
+}
+
+impl<'a> Next<'a> {
+    pub fn run(self, event: Event) -> EventResult {
+        match self.chain.split_first() {
+            Some((middleware, chain)) => middleware.handle(event, Next {
+                chain,
+                listener: self.listener,
+            }),
+            None => (self.listener)(event),
+        }
+    }
+}
+
+pub fn register(middleware: impl Middleware) {
+    Middlewares
+        .write()
+        .expect("cannot write Middlewares")
+        .push(Arc::new(middleware));
+}
+
+/// 插件停用时清空，避免再次启用时重复注册
+pub(crate) fn clear() {
+    Middlewares
+        .write()
+        .expect("cannot write Middlewares")
+        .clear();
+}
+
+pub(crate) fn is_empty() -> bool {
+    Middlewares
+        .read()
+        .expect("cannot read Middlewares")
+        .is_empty()
+}
+
+pub(crate) fn run(event: Event, listener: impl FnOnce(Event) -> EventResult) -> EventResult {
+    // 复制一份，避免中间件或listener中注册中间件时死锁
+    let chain = Middlewares
+        .read()
+        .expect("cannot read Middlewares")
+        .clone();
+    Next {
+        chain: &chain,
+        listener: Box::new(listener),
+    }
+    .run(event)
+}
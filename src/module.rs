@@ -0,0 +1,114 @@
+//! 插件模块
+//!
+//! 可以把反广告、入群欢迎之类的功能写成库，在任意插件中复用。
+//! 库实现[`Module`]，插件在`#[coolq_sdk_rust::main(modules(AntiSpam, Welcome))]`中注册
+//! （同中间件一样，需要是可以直接作为表达式的值），`main`会为全部事件导出`module_on_*`函数，
+//! 把事件按注册顺序分发给每个模块，有模块拦截时后面的模块不会收到该事件。
+//!
+//! cqrs_builder会把这些函数以中优先级添加到app.json中。
+//! 模块中用到的api无法被扫描到，因此声明了模块时cqrs_builder会申请全部auth，不需要的可以在build.rs中`remove_auth`。
+//!
+//! # Examples
+//! ```no_run
+//! // 库
+//! use coolq_sdk_rust::{events::{Event, EventResult}, module::Module};
+//!
+//! pub struct AntiSpam;
+//!
+//! impl Module for AntiSpam {
+//!     fn name(&self) -> &str {
+//!         "anti-spam"
+//!     }
+//!
+//!     fn on_event(&self, event: &Event) -> EventResult {
+//!         match event {
+//!             Event::GroupMessage(event) if event.get_message().raw_msg.contains("广告") => {
+//!                 event.get_message().delete();
+//!                 EventResult::Block
+//!             },
+//!             _ => EventResult::Ignore,
+//!         }
+//!     }
+//! }
+//! ```
+//!
+//! ```ignore
+//! // 插件
+//! #[coolq_sdk_rust::main(modules(anti_spam::AntiSpam))]
+//! fn main() {}
+//! ```
+
+use std::sync::{
+    atomic::{AtomicBool, Ordering},
+    Arc, RwLock,
+};
+
+use crate::events::{Event, EventResult};
+
+lazy_static! {
+    static ref Modules: RwLock<Vec<Arc<dyn Module>>> = RwLock::new(Vec::new());
+}
+
+/// 插件停用后酷q退出时还会再调用一次`disable`
+static Enabled: AtomicBool = AtomicBool::new(false);
+
+pub trait Module: Send + Sync + 'static {
+    /// 模块名，用于日志
+    fn name(&self) -> &str;
+
+    /// 插件启用，注册的模块按顺序调用
+    fn on_enable(&self) {}
+
+    /// 插件停用或酷q退出
+    fn on_disable(&self) {}
+
+    /// 返回[`EventResult::Block`]时拦截该事件
+    fn on_event(&self, event: &Event) -> EventResult {
+        EventResult::Ignore
+    }
+}
+
+/// 注册模块
+///
+/// 只有在`main`中声明了模块时才会导出`module_on_*`函数，运行时注册的模块也依赖这些函数接收事件。
+/// 插件停用时会清空已注册的模块，启用时`main`会重新注册。
+pub fn register(module: impl Module) {
+    Modules
+        .write()
+        .expect("cannot write Modules")
+        .push(Arc::new(module));
+}
+
+/// 已注册的模块名
+pub fn names() -> Vec<String> {
+    modules().iter().map(|module| module.name().to_owned()).collect()
+}
+
+fn modules() -> Vec<Arc<dyn Module>> {
+    // 复制一份，避免模块中注册模块时死锁
+    Modules.read().expect("cannot read Modules").clone()
+}
+
+pub(crate) fn enable() {
+    Enabled.store(true, Ordering::SeqCst);
+    modules().iter().for_each(|module| module.on_enable());
+}
+
+pub(crate) fn disable() {
+    if !Enabled.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    modules().iter().for_each(|module| module.on_disable());
+    // 再次启用时`main`会重新注册
+    Modules.write().expect("cannot write Modules").clear();
+}
+
+/// 由`main`导出的`module_on_*`函数调用
+#[doc(hidden)]
+pub fn dispatch(event: Event) -> EventResult {
+    modules()
+        .iter()
+        .map(|module| module.on_event(&event))
+        .find(|result| *result == EventResult::Block)
+        .unwrap_or_default()
+}
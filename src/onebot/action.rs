@@ -0,0 +1,506 @@
+//! OneBot v11的api映射到[`api`](crate::api)
+
+use std::{
+    convert::TryInto,
+    io,
+    sync::{
+        mpsc::{self, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread,
+};
+
+use serde::Serialize;
+use serde_json::{json, Map, Value};
+
+use crate::{
+    api::{self, Convert},
+    targets::{
+        cqcode::CQStr,
+        group::{Group, GroupMember, GroupRole},
+        user::{FriendInfo, User, UserSex},
+    },
+};
+
+/// 调用`_async`结尾的api的线程数
+const ASYNC_WORKERS: usize = 4;
+/// 等待调用的`_async`的api数，超过时返回失败
+const ASYNC_QUEUE: usize = 64;
+
+lazy_static! {
+    static ref AsyncActions: Mutex<SyncSender<(String, Value)>> = {
+        let (sender, receiver) = mpsc::sync_channel(ASYNC_QUEUE);
+        let receiver = Arc::new(Mutex::new(receiver));
+        for _ in 0..ASYNC_WORKERS {
+            let receiver = receiver.clone();
+            thread::spawn(move || run_async_actions(&receiver));
+        }
+        Mutex::new(sender)
+    };
+}
+
+fn run_async_actions(receiver: &Mutex<Receiver<(String, Value)>>) {
+    loop {
+        let next = receiver.lock().expect("cannot lock AsyncActions").recv();
+        match next {
+            Ok((action, params)) => {
+                handle(&action, &params);
+            },
+            Err(_) => return,
+        }
+    }
+}
+
+/// api的返回值
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Response {
+    /// `ok`、`async`或`failed`
+    pub status: &'static str,
+    /// 0为成功，1为已异步处理，100为参数错误，102为酷q返回了无效的数据，
+    /// 201为等待异步处理的api过多，1404为不支持的api，负数为酷q的错误码
+    pub retcode: i32,
+    pub data: Value,
+    /// 参数错误的原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub msg: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub echo: Option<Value>,
+}
+
+impl Response {
+    fn ok(data: Value) -> Response {
+        Response {
+            status: "ok",
+            retcode: 0,
+            data,
+            msg: None,
+            echo: None,
+        }
+    }
+
+    fn failed(retcode: i32) -> Response {
+        Response {
+            status: "failed",
+            retcode,
+            data: Value::Null,
+            msg: None,
+            echo: None,
+        }
+    }
+
+    pub fn is_unsupported(&self) -> bool {
+        self.retcode == 1404
+    }
+}
+
+enum Failure {
+    BadParams(String),
+    InvalidData,
+    Api(api::Error),
+    Unsupported,
+}
+
+impl From<api::Error> for Failure {
+    fn from(err: api::Error) -> Self {
+        Failure::Api(err)
+    }
+}
+
+impl From<io::Error> for Failure {
+    fn from(_: io::Error) -> Self {
+        Failure::InvalidData
+    }
+}
+
+/// 调用api
+///
+/// `_async`结尾的api会放到后台的线程中依次调用，立即返回，`_rate_limited`结尾的api同普通的api。
+pub fn handle(action: &str, params: &Value) -> Response {
+    if let Some(action) = action.strip_suffix("_async") {
+        if !is_supported(action) {
+            return Response::failed(1404);
+        }
+        let queued = AsyncActions
+            .lock()
+            .expect("cannot lock AsyncActions")
+            .try_send((action.to_owned(), params.clone()));
+        if queued.is_err() {
+            return Response {
+                msg: Some("too many async actions".to_owned()),
+                ..Response::failed(201)
+            };
+        }
+        return Response {
+            status: "async",
+            retcode: 1,
+            data: Value::Null,
+            msg: None,
+            echo: None,
+        };
+    }
+    let action = action.strip_suffix("_rate_limited").unwrap_or(action);
+    match call(action, &Params(params)) {
+        Ok(data) => Response::ok(data),
+        Err(Failure::BadParams(msg)) => Response {
+            msg: Some(msg),
+            ..Response::failed(100)
+        },
+        Err(Failure::InvalidData) => Response::failed(102),
+        Err(Failure::Api(err)) if err.0 < 0 => Response::failed(err.0),
+        Err(Failure::Api(_)) => Response::failed(102),
+        Err(Failure::Unsupported) => Response::failed(1404),
+    }
+}
+
+/// 处理WebSocket收到的`{"action": .., "params": .., "echo": ..}`
+pub fn handle_json(text: &str) -> Response {
+    let request = match serde_json::from_str::<Value>(text) {
+        Ok(request) => request,
+        Err(_) => return Response::failed(100),
+    };
+    let mut response = match request["action"].as_str() {
+        Some(action) => handle(action, &request["params"]),
+        None => Response::failed(100),
+    };
+    response.echo = request.get("echo").cloned();
+    response
+}
+
+fn is_supported(action: &str) -> bool {
+    [
+        "send_private_msg",
+        "send_group_msg",
+        "send_discuss_msg",
+        "send_msg",
+        "delete_msg",
+        "send_like",
+        "set_group_kick",
+        "set_group_ban",
+        "set_group_anonymous_ban",
+        "set_group_whole_ban",
+        "set_group_admin",
+        "set_group_anonymous",
+        "set_group_card",
+        "set_group_leave",
+        "set_group_special_title",
+        "set_discuss_leave",
+        "set_friend_add_request",
+        "set_group_add_request",
+    ]
+    .contains(&action)
+}
+
+/// 参数，数字和布尔值也可以是字符串（HTTP的query）
+struct Params<'a>(&'a Value);
+
+impl Params<'_> {
+    fn get(&self, key: &str) -> Option<&Value> {
+        self.0.get(key).filter(|value| !value.is_null())
+    }
+
+    fn int(&self, key: &str) -> Result<i64, Failure> {
+        self.int_or_none(key)?
+            .ok_or_else(|| Failure::BadParams(format!("missing `{}`", key)))
+    }
+
+    fn int_or_none(&self, key: &str) -> Result<Option<i64>, Failure> {
+        let value = match self.get(key) {
+            Some(value) => value,
+            None => return Ok(None),
+        };
+        value
+            .as_i64()
+            .or_else(|| value.as_str().and_then(|s| s.parse().ok()))
+            .map(Some)
+            .ok_or_else(|| Failure::BadParams(format!("`{}` should be a number", key)))
+    }
+
+    fn int_or(&self, key: &str, default: i64) -> Result<i64, Failure> {
+        Ok(self.int_or_none(key)?.unwrap_or(default))
+    }
+
+    fn bool_or(&self, key: &str, default: bool) -> Result<bool, Failure> {
+        match self.get(key) {
+            None => Ok(default),
+            Some(Value::Bool(b)) => Ok(*b),
+            Some(Value::Number(n)) => Ok(n.as_i64() != Some(0)),
+            Some(Value::String(s)) if s == "true" || s == "1" => Ok(true),
+            Some(Value::String(s)) if s == "false" || s == "0" => Ok(false),
+            Some(_) => Err(Failure::BadParams(format!("`{}` should be a boolean", key))),
+        }
+    }
+
+    fn str_or(&self, key: &str, default: &str) -> Result<String, Failure> {
+        match self.get(key) {
+            None => Ok(default.to_owned()),
+            Some(Value::String(s)) => Ok(s.clone()),
+            Some(Value::Number(n)) => Ok(n.to_string()),
+            Some(_) => Err(Failure::BadParams(format!("`{}` should be a string", key))),
+        }
+    }
+
+    fn str(&self, key: &str) -> Result<String, Failure> {
+        match self.get(key) {
+            None => Err(Failure::BadParams(format!("missing `{}`", key))),
+            Some(_) => self.str_or(key, ""),
+        }
+    }
+
+    /// 字符串或消息段数组，转换为cq码
+    fn message(&self) -> Result<String, Failure> {
+        let message = match self.get("message") {
+            Some(Value::String(s)) if self.bool_or("auto_escape", false)? => s.no_cq_code(),
+            Some(Value::String(s)) => s.clone(),
+            Some(Value::Array(segments)) => segments.iter().map(segment).collect::<Option<_>>()
+                .ok_or_else(|| Failure::BadParams("invalid message segment".to_owned()))?,
+            Some(object @ Value::Object(_)) => segment(object)
+                .ok_or_else(|| Failure::BadParams("invalid message segment".to_owned()))?,
+            _ => return Err(Failure::BadParams("missing `message`".to_owned())),
+        };
+        Ok(message)
+    }
+}
+
+/// `{"type": "face", "data": {"id": "1"}}`转换为`[CQ:face,id=1]`
+fn segment(segment: &Value) -> Option<String> {
+    let kind = segment["type"].as_str()?;
+    let empty = Map::new();
+    let data = match &segment["data"] {
+        Value::Object(data) => data,
+        Value::Null => &empty,
+        _ => return None,
+    };
+    let value = |value: &Value| match value {
+        Value::String(s) => Some(s.no_cq_code()),
+        Value::Number(n) => Some(n.to_string()),
+        Value::Bool(b) => Some(b.to_string()),
+        _ => None,
+    };
+    if kind == "text" {
+        return data.get("text").and_then(value);
+    }
+    let mut code = format!("[CQ:{}", kind);
+    for (key, v) in data {
+        code.push_str(&format!(",{}={}", key, value(v)?));
+    }
+    code.push(']');
+    Some(code)
+}
+
+fn call(action: &str, p: &Params) -> Result<Value, Failure> {
+    Ok(match action {
+        "send_private_msg" => message_id(api::send_private_msg(p.int("user_id")?, p.message()?)?),
+        "send_group_msg" => message_id(api::send_group_msg(p.int("group_id")?, p.message()?)?),
+        "send_discuss_msg" => {
+            message_id(api::send_discuss_msg(p.int("discuss_id")?, p.message()?)?)
+        },
+        "send_msg" => {
+            let message_type = match p.str_or("message_type", "")?.as_str() {
+                "" if p.get("group_id").is_some() => "group".to_owned(),
+                "" if p.get("discuss_id").is_some() => "discuss".to_owned(),
+                "" => "private".to_owned(),
+                message_type => message_type.to_owned(),
+            };
+            return call(&format!("send_{}_msg", message_type), p);
+        },
+        "delete_msg" => ok(api::delete_msg(p.int("message_id")? as i32)?),
+        "send_like" => ok(api::send_like_v2(p.int("user_id")?, p.int_or("times", 1)? as i32)?),
+        "set_group_kick" => ok(api::set_group_kick(
+            p.int("group_id")?,
+            p.int("user_id")?,
+            p.bool_or("reject_add_request", false)?,
+        )?),
+        "set_group_ban" => ok(api::set_group_ban(
+            p.int("group_id")?,
+            p.int("user_id")?,
+            p.int_or("duration", 30 * 60)?,
+        )?),
+        "set_group_anonymous_ban" => {
+            let flag = match p.get("anonymous") {
+                Some(anonymous) => Params(anonymous).str("flag")?,
+                None => p
+                    .str("anonymous_flag")
+                    .or_else(|_| p.str("flag"))?,
+            };
+            ok(api::set_group_anonymous_ban(
+                p.int("group_id")?,
+                flag,
+                p.int_or("duration", 30 * 60)?,
+            )?)
+        },
+        "set_group_whole_ban" => ok(api::set_group_whole_ban(
+            p.int("group_id")?,
+            p.bool_or("enable", true)?,
+        )?),
+        "set_group_admin" => ok(api::set_group_admin(
+            p.int("group_id")?,
+            p.int("user_id")?,
+            p.bool_or("enable", true)?,
+        )?),
+        "set_group_anonymous" => ok(api::set_group_anonymous(
+            p.int("group_id")?,
+            p.bool_or("enable", true)?,
+        )?),
+        "set_group_card" => ok(api::set_group_card(
+            p.int("group_id")?,
+            p.int("user_id")?,
+            p.str_or("card", "")?,
+        )?),
+        "set_group_leave" => ok(api::set_group_leave(
+            p.int("group_id")?,
+            p.bool_or("is_dismiss", false)?,
+        )?),
+        "set_group_special_title" => ok(api::set_group_special_title(
+            p.int("group_id")?,
+            p.int("user_id")?,
+            p.str_or("special_title", "")?,
+            p.int_or("duration", -1)?,
+        )?),
+        "set_discuss_leave" => ok(api::set_discuss_leave(p.int("discuss_id")?)?),
+        "set_friend_add_request" => ok(api::set_friend_add_request(
+            p.str("flag")?,
+            p.bool_or("approve", true)?,
+            p.str_or("remark", "")?,
+        )?),
+        "set_group_add_request" => {
+            let sub_type = match p.get("sub_type") {
+                Some(_) => p.str("sub_type")?,
+                None => p.str("type")?,
+            };
+            let request = match sub_type.as_str() {
+                "add" => 1,
+                "invite" => 2,
+                _ => return Err(Failure::BadParams("`sub_type` should be add or invite".to_owned())),
+            };
+            ok(api::set_group_add_request_v2(
+                p.str("flag")?,
+                request,
+                p.bool_or("approve", true)?,
+                p.str_or("reason", "")?,
+            )?)
+        },
+        "get_login_info" => json!({
+            "user_id": api::get_login_qq()?.to::<i64>(),
+            "nickname": api::get_login_nick()?.to::<String>(),
+        }),
+        "get_stranger_info" => {
+            let user: User = api::get_stranger_info(p.int("user_id")?, p.bool_or("no_cache", false)?)?
+                .try_into()?;
+            json!({
+                "user_id": user.user_id,
+                "nickname": user.nickname,
+                "sex": sex(&user.sex),
+                "age": user.age,
+            })
+        },
+        "get_friend_list" => {
+            let friends: Vec<FriendInfo> = api::get_friend_list(false)?.try_into()?;
+            friends
+                .iter()
+                .map(|friend| {
+                    json!({
+                        "user_id": friend.user_id,
+                        "nickname": friend.nickname,
+                        "remark": friend.remark,
+                    })
+                })
+                .collect()
+        },
+        "get_group_info" => {
+            let group: Group = api::get_group_info(p.int("group_id")?, p.bool_or("no_cache", false)?)?
+                .try_into()?;
+            json!({
+                "group_id": group.group_id,
+                "group_name": group.group_name,
+                "member_count": group.member_count,
+                "max_member_count": group.max_member_count,
+            })
+        },
+        "get_group_list" => {
+            let groups: Vec<Group> = api::get_group_list()?.try_into()?;
+            groups
+                .iter()
+                .map(|group| json!({ "group_id": group.group_id, "group_name": group.group_name }))
+                .collect()
+        },
+        "get_group_member_info" => {
+            let member: GroupMember = api::get_group_member_info_v2(
+                p.int("group_id")?,
+                p.int("user_id")?,
+                p.bool_or("no_cache", false)?,
+            )?
+            .try_into()?;
+            group_member(&member)
+        },
+        "get_group_member_list" => {
+            let members: Vec<GroupMember> = api::get_group_member_list(p.int("group_id")?)?.try_into()?;
+            members.iter().map(group_member).collect()
+        },
+        "get_cookies" => json!({ "cookies": api::get_cookies()?.to::<String>() }),
+        "get_csrf_token" => json!({ "token": csrf_token()? }),
+        "get_credentials" => json!({
+            "cookies": api::get_cookies()?.to::<String>(),
+            "csrf_token": csrf_token()?,
+        }),
+        "get_record" => json!({
+            "file": api::get_record_v2(p.str("file")?, p.str("out_format")?)?.to::<String>(),
+        }),
+        "get_image" => json!({ "file": api::get_image(p.str("file")?)?.to::<String>() }),
+        "can_send_image" => json!({ "yes": api::can_send_image().is_ok() }),
+        "can_send_record" => json!({ "yes": api::can_send_record().is_ok() }),
+        "get_status" => json!({ "online": true, "good": true }),
+        "get_version_info" => json!({
+            "app_name": "coolq-sdk-rust",
+            "app_version": env!("CARGO_PKG_VERSION"),
+            "protocol_version": "v11",
+        }),
+        _ => return Err(Failure::Unsupported),
+    })
+}
+
+fn csrf_token() -> Result<i64, Failure> {
+    api::get_csrf_token()?
+        .to::<String>()
+        .parse()
+        .map_err(|_| Failure::InvalidData)
+}
+
+fn ok(_: Convert<i32>) -> Value {
+    Value::Null
+}
+
+fn message_id(message_id: Convert<i32>) -> Value {
+    json!({ "message_id": message_id.to::<i32>() })
+}
+
+fn sex(sex: &UserSex) -> &'static str {
+    match sex {
+        UserSex::Male => "male",
+        UserSex::Female => "female",
+        UserSex::Unknown => "unknown",
+    }
+}
+
+fn group_member(member: &GroupMember) -> Value {
+    json!({
+        "group_id": member.group_id,
+        "user_id": member.user_id,
+        "nickname": member.nickname,
+        "card": member.card,
+        "sex": sex(&member.sex),
+        "age": member.age,
+        "area": member.area,
+        "join_time": member.join_time,
+        "last_sent_time": member.last_sent_time,
+        "level": member.level,
+        "role": match member.role {
+            GroupRole::Owner => "owner",
+            GroupRole::Admin => "admin",
+            GroupRole::Member => "member",
+        },
+        "unfriendly": member.unfriendly,
+        "title": member.title,
+        "title_expire_time": member.title_expire_time,
+        "card_changeable": member.card_changeable,
+    })
+}
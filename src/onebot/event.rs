@@ -0,0 +1,165 @@
+//! 事件转换为OneBot v11的格式
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::events::*;
+
+/// 转换为OneBot的事件，酷q启动、退出和插件停用没有对应的事件，返回None
+///
+/// 为了不产生额外的api调用，`sender`中只有`user_id`。
+pub fn to_json(event: &Event, self_id: i64) -> Option<Value> {
+    let mut json = match event {
+        Event::PrivateMessage(e) => json!({
+            "post_type": "message",
+            "message_type": "private",
+            "sub_type": match e.sub_type {
+                PrivateMessageType::Friend => "friend",
+                PrivateMessageType::Group => "group",
+                PrivateMessageType::Discuss => "discuss",
+                _ => "other",
+            },
+            "message_id": e.msg.get_msg_id(),
+            "user_id": e.user.id(),
+            "message": e.msg.raw_msg,
+            "raw_message": e.msg.raw_msg,
+            "font": e.font,
+            "sender": { "user_id": e.user.id() },
+        }),
+        Event::GroupMessage(e) => json!({
+            "post_type": "message",
+            "message_type": "group",
+            "sub_type": match e.sub_type {
+                GroupMessageType::Anonymous => "anonymous",
+                GroupMessageType::System => "notice",
+                _ => "normal",
+            },
+            "message_id": e.msg.get_msg_id(),
+            "group_id": e.group.id(),
+            "user_id": e.user.id(),
+            "anonymous": e.get_anonymous().ok().filter(|_| e.is_anonymous()).map(|anonymous| json!({
+                "id": anonymous.user_id,
+                "name": anonymous.name,
+                "flag": e.anonymous_flag,
+            })),
+            "message": e.msg.raw_msg,
+            "raw_message": e.msg.raw_msg,
+            "font": e.font,
+            "sender": { "user_id": e.user.id() },
+        }),
+        Event::DiscussMessage(e) => json!({
+            "post_type": "message",
+            "message_type": "discuss",
+            "message_id": e.msg.get_msg_id(),
+            "discuss_id": e.discuss.discuss_id,
+            "user_id": e.user.id(),
+            "message": e.msg.raw_msg,
+            "raw_message": e.msg.raw_msg,
+            "font": e.font,
+            "sender": { "user_id": e.user.id() },
+        }),
+        Event::GroupUpload(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_upload",
+            "group_id": e.group.id(),
+            "user_id": e.user.id(),
+            "file": {
+                "id": e.file.id,
+                "name": e.file.name,
+                "size": e.file.size,
+                "busid": e.file.busid,
+            },
+        }),
+        Event::GroupAdmin(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_admin",
+            "sub_type": match e.sub_type {
+                GroupAdminType::Set => "set",
+                _ => "unset",
+            },
+            "group_id": e.group.id(),
+            "user_id": e.user.id(),
+        }),
+        Event::GroupMemberDecrease(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_decrease",
+            "sub_type": match e.sub_type {
+                GroupMemberDecreaseType::Kick => "kick",
+                GroupMemberDecreaseType::KickMe => "kick_me",
+                _ => "leave",
+            },
+            "group_id": e.group.id(),
+            "operator_id": e.operate_user.id(),
+            "user_id": e.being_operate_user.id(),
+        }),
+        Event::GroupMemberIncrease(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_increase",
+            "sub_type": match e.sub_type {
+                GroupMemberIncreaseType::Invite => "invite",
+                _ => "approve",
+            },
+            "group_id": e.group.id(),
+            "operator_id": e.operate_user.id(),
+            "user_id": e.being_operate_user.id(),
+        }),
+        Event::GroupBan(e) => json!({
+            "post_type": "notice",
+            "notice_type": "group_ban",
+            "sub_type": match e.sub_type {
+                GroupBanType::Ban => "ban",
+                _ => "lift_ban",
+            },
+            "group_id": e.group.id(),
+            "operator_id": e.operate_user.id(),
+            "user_id": e.being_operate_user.id(),
+            "duration": e.time,
+        }),
+        Event::FriendAdd(e) => json!({
+            "post_type": "notice",
+            "notice_type": "friend_add",
+            "user_id": e.user.id(),
+        }),
+        Event::AddFriendRequest(e) => json!({
+            "post_type": "request",
+            "request_type": "friend",
+            "user_id": e.user.id(),
+            "comment": e.msg,
+            "flag": e.flag,
+        }),
+        Event::AddGroupRequest(e) => json!({
+            "post_type": "request",
+            "request_type": "group",
+            "sub_type": match e.sub_type {
+                AddGroupRequestType::Invite => "invite",
+                _ => "add",
+            },
+            "group_id": e.group.id(),
+            "user_id": e.user.id(),
+            "comment": e.msg,
+            "flag": e.flag,
+        }),
+        Event::Start(_) | Event::Exit(_) | Event::Disable(_) => return None,
+    };
+    json["time"] = json!(event.time().map_or_else(now, i64::from));
+    json["self_id"] = json!(self_id);
+    Some(json)
+}
+
+/// 生命周期事件，WebSocket连接成功时推送`connect`
+pub fn lifecycle(sub_type: &str, self_id: i64) -> Value {
+    json!({
+        "time": now(),
+        "self_id": self_id,
+        "post_type": "meta_event",
+        "meta_event_type": "lifecycle",
+        "sub_type": sub_type,
+    })
+}
+
+fn now() -> i64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i64)
+}
@@ -0,0 +1,166 @@
+//! OneBot v11（CQHTTP）协议
+//!
+//! 在本地开启HTTP和正向WebSocket服务，把事件转换为OneBot的格式推送给WebSocket客户端，
+//! 把收到的OneBot api（`send_msg`、`set_group_ban`、`get_group_member_list`等）映射到[`api`](crate::api)，
+//! 让使用OneBot协议的程序可以控制机器人。
+//!
+//! * HTTP: `POST /send_msg`，参数为json、表单或query，不支持的api返回404
+//! * WebSocket: `/`接收事件和调用api，`/event`只接收事件，`/api`只调用api。
+//!   调用api时发送`{"action": "send_msg", "params": {..}, "echo": ..}`，返回的`echo`与发送的相同
+//!
+//! 设置了`access_token`时，需要在`Authorization`请求头中带上`Bearer {access_token}`，或者使用`?access_token=`。
+//! 未提供时返回401，不正确时返回403。
+//!
+//! [`OneBot`]是一个[模块](crate::module)，插件启用时开启服务，停用时关闭，事件由模块导出的函数接收。
+//! OneBot的api几乎用到了全部auth，源码中用到了`onebot`（导入或者调用`onebot::start`等）
+//! 或者在`main`中声明了模块时，cqrs_builder会申请全部auth，不需要自己`add_auth`。
+//! 消息使用cq码字符串格式，调用api时也可以传入消息段数组。
+//!
+//! 需要开启`onebot` feature。
+//!
+//! # Examples
+//! ```ignore
+//! use coolq_sdk_rust::onebot::OneBot;
+//!
+//! const ONEBOT: OneBot = OneBot {
+//!     access_token: Some("secret"),
+//!     ..OneBot::DEFAULT
+//! };
+//!
+//! #[coolq_sdk_rust::main(modules(ONEBOT))]
+//! fn main() {}
+//! ```
+
+use std::{
+    io,
+    net::SocketAddr,
+    sync::{
+        atomic::{AtomicI64, Ordering},
+        mpsc::Sender,
+        Mutex,
+    },
+};
+
+use crate::{
+    api::{add_log, get_login_qq, CQLogLevel},
+    events::{Event, EventResult},
+    module::Module,
+};
+
+pub mod action;
+pub mod event;
+mod server;
+
+lazy_static! {
+    static ref Servers: Mutex<Option<(Option<server::Http>, Option<server::Ws>)>> =
+        Mutex::new(None);
+    /// 接收事件的WebSocket连接
+    static ref Clients: Mutex<Vec<Sender<String>>> = Mutex::new(Vec::new());
+}
+
+static SelfId: AtomicI64 = AtomicI64::new(0);
+
+/// 服务的配置
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub struct OneBot {
+    /// HTTP服务的地址，None时不开启
+    pub http: Option<&'static str>,
+    /// 正向WebSocket服务的地址，None时不开启
+    pub ws: Option<&'static str>,
+    pub access_token: Option<&'static str>,
+}
+
+impl OneBot {
+    /// 只监听本地的5700（HTTP）和6700（WebSocket）端口，没有access token
+    pub const DEFAULT: OneBot = OneBot {
+        http: Some("127.0.0.1:5700"),
+        ws: Some("127.0.0.1:6700"),
+        access_token: None,
+    };
+}
+
+impl Default for OneBot {
+    fn default() -> Self {
+        OneBot::DEFAULT
+    }
+}
+
+impl Module for OneBot {
+    fn name(&self) -> &str {
+        "onebot"
+    }
+
+    fn on_enable(&self) {
+        if let Err(err) = start(self) {
+            let _ = add_log(
+                CQLogLevel::ERROR,
+                "onebot",
+                format!("开启OneBot服务失败: {}", err),
+            );
+        }
+    }
+
+    fn on_disable(&self) {
+        stop();
+    }
+
+    fn on_event(&self, event: &Event) -> EventResult {
+        broadcast(event);
+        EventResult::Ignore
+    }
+}
+
+/// 开启服务，已经开启时会先关闭
+///
+/// 地址的端口为0时会使用随机的端口，可以通过[`http_addr`]、[`ws_addr`]获取。
+pub fn start(config: &OneBot) -> io::Result<()> {
+    stop();
+    SelfId.store(
+        get_login_qq().map(|qq| qq.to::<i64>()).unwrap_or_default(),
+        Ordering::SeqCst,
+    );
+    let http = match config.http {
+        Some(addr) => Some(server::Http::start(addr, config.access_token)?),
+        None => None,
+    };
+    let ws = match config.ws {
+        Some(addr) => Some(server::Ws::start(addr, config.access_token)?),
+        None => None,
+    };
+    *Servers.lock().expect("cannot lock Servers") = Some((http, ws));
+    Ok(())
+}
+
+/// 关闭服务，并断开全部WebSocket连接
+pub fn stop() {
+    Servers.lock().expect("cannot lock Servers").take();
+    Clients.lock().expect("cannot lock Clients").clear();
+}
+
+pub fn http_addr() -> Option<SocketAddr> {
+    let servers = Servers.lock().expect("cannot lock Servers");
+    servers.as_ref()?.0.as_ref().map(server::Http::addr)
+}
+
+pub fn ws_addr() -> Option<SocketAddr> {
+    let servers = Servers.lock().expect("cannot lock Servers");
+    servers.as_ref()?.1.as_ref().map(server::Ws::addr)
+}
+
+/// 把事件推送给WebSocket客户端
+///
+/// 没有在`main`中注册[`OneBot`]模块时，可以在listener中调用。
+pub fn broadcast(event: &Event) {
+    let json = match event::to_json(event, self_id()) {
+        Some(json) => json.to_string(),
+        None => return,
+    };
+    Clients
+        .lock()
+        .expect("cannot lock Clients")
+        .retain(|client| client.send(json.clone()).is_ok());
+}
+
+fn self_id() -> i64 {
+    SelfId.load(Ordering::SeqCst)
+}
@@ -0,0 +1,283 @@
+//! HTTP和正向WebSocket服务
+
+use std::{
+    io::{self, ErrorKind},
+    net::{SocketAddr, TcpListener, TcpStream},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::{self, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::Duration,
+};
+
+use serde_json::{Map, Value};
+use tungstenite::{
+    handshake::server::{ErrorResponse, Request, Response as WsResponse},
+    http::StatusCode,
+    Message,
+};
+
+use crate::onebot::{action, event, self_id, Clients};
+
+/// 轮询的间隔
+const POLL: Duration = Duration::from_millis(50);
+/// 同时保持的WebSocket连接数，超过时新的连接会被直接关闭
+const MAX_CONNECTIONS: usize = 16;
+
+/// 检查access token，返回错误的状态码
+fn check_token(token: Option<&str>, header: Option<&str>, query: Option<&str>) -> Result<(), u16> {
+    let token = match token {
+        Some(token) => token,
+        None => return Ok(()),
+    };
+    let given = header
+        .map(|header| {
+            header
+                .trim_start_matches("Bearer ")
+                .trim_start_matches("Token ")
+                .trim()
+                .to_owned()
+        })
+        .or_else(|| {
+            url::form_urlencoded::parse(query.unwrap_or_default().as_bytes())
+                .find(|(key, _)| key == "access_token")
+                .map(|(_, value)| value.into_owned())
+        });
+    match given {
+        None => Err(401),
+        Some(given) if given != token => Err(403),
+        Some(_) => Ok(()),
+    }
+}
+
+pub(super) struct Http {
+    server: Arc<tiny_http::Server>,
+}
+
+impl Http {
+    pub fn start(addr: &str, token: Option<&'static str>) -> io::Result<Http> {
+        let server = tiny_http::Server::http(addr)
+            .map_err(|err| io::Error::new(ErrorKind::Other, err.to_string()))?;
+        let server = Arc::new(server);
+        let s = server.clone();
+        thread::spawn(move || {
+            for request in s.incoming_requests() {
+                handle_http(request, token);
+            }
+        });
+        Ok(Http { server })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.server.server_addr()
+    }
+}
+
+impl Drop for Http {
+    fn drop(&mut self) {
+        self.server.unblock();
+    }
+}
+
+/// `POST /send_msg`，参数可以是json、表单或query
+fn handle_http(mut request: tiny_http::Request, token: Option<&str>) {
+    let url = request.url().to_owned();
+    let (path, query) = match url.find('?') {
+        Some(i) => (&url[..i], Some(&url[i + 1..])),
+        None => (url.as_str(), None),
+    };
+    let header = |name: &'static str| {
+        request
+            .headers()
+            .iter()
+            .find(|header| header.field.equiv(name))
+            .map(|header| header.value.as_str().to_owned())
+    };
+    let authorization = header("Authorization");
+    let content_type = header("Content-Type").unwrap_or_default();
+    let json_response = |status: u16, body: String| {
+        tiny_http::Response::from_string(body)
+            .with_status_code(status)
+            .with_header(
+                "Content-Type: application/json; charset=utf-8"
+                    .parse::<tiny_http::Header>()
+                    .unwrap(),
+            )
+    };
+
+    if let Err(status) = check_token(token, authorization.as_deref(), query) {
+        request.respond(tiny_http::Response::empty(status)).ok();
+        return;
+    }
+
+    let mut params = Map::new();
+    for (key, value) in url::form_urlencoded::parse(query.unwrap_or_default().as_bytes()) {
+        params.insert(key.into_owned(), Value::String(value.into_owned()));
+    }
+    let mut body = String::new();
+    if request.as_reader().read_to_string(&mut body).is_err() {
+        request.respond(tiny_http::Response::empty(400)).ok();
+        return;
+    }
+    if content_type.starts_with("application/json") {
+        match serde_json::from_str::<Value>(&body) {
+            Ok(Value::Object(json)) => params.extend(json),
+            _ => {
+                request.respond(tiny_http::Response::empty(400)).ok();
+                return;
+            },
+        }
+    } else if content_type.starts_with("application/x-www-form-urlencoded") {
+        for (key, value) in url::form_urlencoded::parse(body.as_bytes()) {
+            params.insert(key.into_owned(), Value::String(value.into_owned()));
+        }
+    }
+
+    let response = action::handle(path.trim_matches('/'), &Value::Object(params));
+    let status = if response.is_unsupported() { 404 } else { 200 };
+    let body = serde_json::to_string(&response).expect("cannot serialize Response");
+    request.respond(json_response(status, body)).ok();
+}
+
+/// 连接的路径，`/`同时接收事件和调用api
+#[derive(Clone, Copy, Eq, PartialEq)]
+enum Role {
+    Universal,
+    Api,
+    Event,
+}
+
+pub(super) struct Ws {
+    addr: SocketAddr,
+    stopped: Arc<AtomicBool>,
+}
+
+impl Ws {
+    pub fn start(addr: &str, token: Option<&'static str>) -> io::Result<Ws> {
+        let listener = TcpListener::bind(addr)?;
+        listener.set_nonblocking(true)?;
+        let addr = listener.local_addr()?;
+        let stopped = Arc::new(AtomicBool::new(false));
+        let s = stopped.clone();
+        let connections = Arc::new(AtomicUsize::new(0));
+        thread::spawn(move || {
+            while !s.load(Ordering::SeqCst) {
+                match listener.accept() {
+                    Ok(_) if connections.load(Ordering::SeqCst) >= MAX_CONNECTIONS => {},
+                    Ok((stream, _)) => {
+                        connections.fetch_add(1, Ordering::SeqCst);
+                        let connections = connections.clone();
+                        thread::spawn(move || {
+                            serve_ws(stream, token);
+                            connections.fetch_sub(1, Ordering::SeqCst);
+                        });
+                    },
+                    Err(err) if err.kind() == ErrorKind::WouldBlock => thread::sleep(POLL),
+                    Err(_) => thread::sleep(POLL),
+                }
+            }
+        });
+        Ok(Ws { addr, stopped })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+}
+
+impl Drop for Ws {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+fn serve_ws(stream: TcpStream, token: Option<&str>) {
+    // windows下accept的连接会继承非阻塞
+    if stream.set_nonblocking(false).is_err() {
+        return;
+    }
+    let mut role = Role::Universal;
+    // 错误类型由tungstenite决定
+    #[allow(clippy::result_large_err)]
+    let callback = |request: &Request, response: WsResponse| {
+        let reject = |status: u16| {
+            let mut response = ErrorResponse::new(None);
+            *response.status_mut() = StatusCode::from_u16(status).unwrap();
+            response
+        };
+        role = match request.uri().path().trim_end_matches('/') {
+            "" => Role::Universal,
+            "/api" => Role::Api,
+            "/event" => Role::Event,
+            _ => return Err(reject(404)),
+        };
+        let authorization = request
+            .headers()
+            .get("Authorization")
+            .and_then(|value| value.to_str().ok());
+        check_token(token, authorization, request.uri().query()).map_err(reject)?;
+        Ok(response)
+    };
+    let mut ws = match tungstenite::accept_hdr(stream, callback) {
+        Ok(ws) => ws,
+        Err(_) => return,
+    };
+    if ws.get_ref().set_read_timeout(Some(POLL)).is_err() {
+        return;
+    }
+    let events = if role == Role::Api {
+        None
+    } else {
+        let (sender, receiver) = mpsc::channel();
+        Clients.lock().expect("cannot lock Clients").push(sender);
+        let connect = event::lifecycle("connect", self_id()).to_string();
+        if ws.write_message(Message::Text(connect)).is_err() {
+            return;
+        }
+        Some(receiver)
+    };
+    loop {
+        match ws.read_message() {
+            Ok(Message::Text(text)) if role != Role::Event => {
+                let response = action::handle_json(&text);
+                let response = serde_json::to_string(&response).expect("cannot serialize Response");
+                if ws.write_message(Message::Text(response)).is_err() {
+                    return;
+                }
+            },
+            Ok(Message::Close(_)) => {
+                // 回复关闭帧
+                ws.write_pending().ok();
+                return;
+            },
+            Ok(_) => {},
+            Err(tungstenite::Error::Io(err))
+                if err.kind() == ErrorKind::WouldBlock || err.kind() == ErrorKind::TimedOut => {},
+            Err(_) => return,
+        }
+        if let Some(events) = &events {
+            if !push_events(&mut ws, events) {
+                ws.close(None).ok();
+                ws.write_pending().ok();
+                return;
+            }
+        }
+    }
+}
+
+/// 推送收到的事件，服务停止时返回false
+fn push_events(ws: &mut tungstenite::WebSocket<TcpStream>, events: &Receiver<String>) -> bool {
+    loop {
+        match events.try_recv() {
+            Ok(event) => {
+                if ws.write_message(Message::Text(event)).is_err() {
+                    return false;
+                }
+            },
+            Err(TryRecvError::Empty) => return true,
+            Err(TryRecvError::Disconnected) => return false,
+        }
+    }
+}
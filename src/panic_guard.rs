@@ -0,0 +1,174 @@
+//! listener的panic隔离
+//!
+//! 每次listener调用（包括全部事件回调和中间件）都会被单独捕获panic，
+//! panic信息和事件信息会通过[`add_log`]记录，插件不会因此停用。
+//! 开启`backtrace` feature时会同时记录backtrace（需要Rust 1.65以上）。
+//!
+//! 是否交给酷q作为致命错误处理（[`set_fatal`]）由[`PanicPolicy`]决定，默认不处理。
+//!
+//! 注意: 插件的`Cargo.toml`中不能设置`panic = "abort"`，否则panic无法被捕获。
+//! 之前的示例和本sdk的release profile都设置了`panic = "abort"`，现在已经去掉，
+//! 照抄了该设置的插件需要自己删掉才能使用panic隔离；保留则和以前一样，panic时直接终止酷q。
+//!
+//! # Examples
+//! ```
+//! use coolq_sdk_rust::panic_guard::{self, PanicPolicy};
+//!
+//! // 累计panic 10次之后交给酷q处理
+//! panic_guard::set_policy(PanicPolicy::After(10));
+//! ```
+//!
+//! [`add_log`]: crate::api::add_log
+//! [`set_fatal`]: crate::api::set_fatal
+
+use std::{
+    cell::{Cell, RefCell},
+    future::Future,
+    panic::{catch_unwind, set_hook, AssertUnwindSafe},
+    pin::Pin,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        RwLock,
+    },
+    task::{Context, Poll},
+};
+
+use crate::{
+    api::{add_log, set_fatal, CQLogLevel},
+    events::EventKind,
+};
+
+static PANIC_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+lazy_static! {
+    static ref Policy: RwLock<PanicPolicy> = RwLock::new(PanicPolicy::Never);
+}
+
+thread_local! {
+    /// 当前正在处理的事件，为None则不在listener中
+    static CURRENT: Cell<Option<EventContext>> = const { Cell::new(None) };
+    static LAST_PANIC: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+/// 何时把panic交给酷q作为致命错误处理
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum PanicPolicy {
+    /// 只记录日志
+    Never,
+    /// 每次panic都交给酷q处理
+    Always,
+    /// 累计panic次数达到后交给酷q处理
+    After(usize),
+}
+
+/// panic时正在处理的事件
+#[derive(Debug, Clone, Copy)]
+pub struct EventContext {
+    pub kind: EventKind,
+    pub group_id: Option<i64>,
+    pub user_id: Option<i64>,
+}
+
+pub fn set_policy(policy: PanicPolicy) {
+    *Policy.write().expect("cannot write Policy") = policy;
+}
+
+pub fn policy() -> PanicPolicy {
+    *Policy.read().expect("cannot read Policy")
+}
+
+/// 启动以来捕获到的panic次数
+pub fn panic_count() -> usize {
+    PANIC_COUNT.load(Ordering::Relaxed)
+}
+
+pub(crate) fn install_hook() {
+    set_hook(Box::new(|info| {
+        let payload = info
+            .payload()
+            .downcast_ref::<&str>()
+            .map(|s| s.to_string())
+            .or_else(|| info.payload().downcast_ref::<String>().cloned())
+            .unwrap_or_default();
+        let msg = match info.location() {
+            Some(l) => format!("'{}', {}:{}:{}", payload, l.file(), l.line(), l.column()),
+            None => format!("'{}'", payload),
+        };
+        #[cfg(feature = "backtrace")]
+        let msg = format!("{}\n{}", msg, std::backtrace::Backtrace::force_capture());
+        if CURRENT.with(|c| c.get()).is_some() {
+            // 交给guard处理
+            LAST_PANIC.with(|p| *p.borrow_mut() = Some(msg));
+        } else {
+            report(None, msg);
+        }
+    }));
+}
+
+/// 在`context`下执行`f`，panic则记录并返回None
+pub(crate) fn guard<R>(context: EventContext, f: impl FnOnce() -> R) -> Option<R> {
+    let prev = CURRENT.with(|c| c.replace(Some(context)));
+    let result = catch_unwind(AssertUnwindSafe(f));
+    CURRENT.with(|c| c.set(prev));
+    // listener中自己捕获的panic也会留下信息，不管结果都要清掉
+    let last = LAST_PANIC.with(|p| p.borrow_mut().take());
+    match result {
+        Ok(r) => Some(r),
+        Err(_) => {
+            let msg = last.unwrap_or_else(|| "unknown panic".to_owned());
+            report(Some(context), msg);
+            None
+        },
+    }
+}
+
+/// 包装异步listener，在每次poll时捕获panic
+#[doc(hidden)]
+pub fn guard_future<F: Future + Send + 'static>(fut: F) -> impl Future<Output = ()> + Send {
+    Guarded {
+        context: CURRENT.with(|c| c.get()),
+        fut: Box::pin(fut),
+    }
+}
+
+struct Guarded<F> {
+    context: Option<EventContext>,
+    fut: Pin<Box<F>>,
+}
+
+impl<F: Future> Future for Guarded<F> {
+    type Output = ();
+
+    fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+        let this = &mut *self;
+        let fut = &mut this.fut;
+        match this.context {
+            Some(context) => match guard(context, || fut.as_mut().poll(cx)) {
+                Some(Poll::Pending) => Poll::Pending,
+                _ => Poll::Ready(()),
+            },
+            None => fut.as_mut().poll(cx).map(|_| ()),
+        }
+    }
+}
+
+fn report(context: Option<EventContext>, msg: String) {
+    let count = PANIC_COUNT.fetch_add(1, Ordering::Relaxed) + 1;
+    let msg = match context {
+        Some(c) => format!(
+            "listener panicked while handling {:?} (group: {:?}, user: {:?}): {}",
+            c.kind, c.group_id, c.user_id, msg
+        ),
+        None => msg,
+    };
+    let _ = add_log(CQLogLevel::ERROR, "panic", msg.as_str());
+    let fatal = match policy() {
+        PanicPolicy::Never => false,
+        PanicPolicy::Always => true,
+        PanicPolicy::After(n) => count >= n,
+    };
+    if fatal {
+        // 在 mirai-native 上会返回 0 而被当成错误
+        let _ = set_fatal(msg);
+    }
+}
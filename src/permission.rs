@@ -0,0 +1,230 @@
+//! 权限节点
+//!
+//! 比[`Authority`]更细粒度的权限管理。权限节点是用`.`分隔的字符串，如`moderation.ban`、`fun.dice`。
+//!
+//! * [`Role`]拥有一组权限节点，并且可以继承其他角色。
+//! * 角色可以授予给用户，可以全局生效，也可以只在某个群生效。
+//! * 用户会根据自身身份自动获得内置角色:
+//!   所有人都有[`DEFAULT`]；主人、超级管理员分别有[`MASTER`]、[`SUPER_ADMIN`]；
+//!   检查群内权限时，群主、管理员、群员分别有[`GROUP_OWNER`]、[`GROUP_ADMIN`]、[`GROUP_MEMBER`]，
+//!   群身份取自该群的群员信息，超级管理员在任何群内都有[`GROUP_OWNER`]。
+//!
+//! 节点支持通配符，`moderation.*`匹配`moderation`下的所有节点，`*`匹配全部节点。
+//! 以`-`开头的节点表示禁止，禁止优先于允许。
+//!
+//! 默认情况下[`MASTER`]拥有`*`，[`GROUP_OWNER`]继承[`GROUP_ADMIN`]，[`GROUP_ADMIN`]继承[`GROUP_MEMBER`]。
+//!
+//! # Examples
+//! ```
+//! use coolq_sdk_rust::permission::{self, Role, GROUP_ADMIN};
+//! use coolq_sdk_rust::targets::user::User;
+//!
+//! permission::add_role(Role::new(GROUP_ADMIN).inherit("group.member").node("moderation.*"));
+//! permission::add_role(Role::new("gamer").node("fun.*").node("-fun.dice"));
+//! // 全局授予
+//! permission::grant(12345, None, "gamer");
+//! // 只在群123456中授予
+//! permission::grant(12345, Some(123456), "moderator");
+//!
+//! let mut user = User::default();
+//! user.user_id = 12345;
+//! assert!(permission::check(&user, None, "fun.rps"));
+//! assert!(!permission::check(&user, None, "fun.dice"));
+//! ```
+//!
+//! [`Authority`]: crate::targets::user::Authority
+
+use std::{
+    collections::{HashMap, HashSet},
+    sync::RwLock,
+};
+
+use crate::{
+    cache,
+    targets::{
+        group::GroupRole,
+        user::{Authority, User},
+    },
+};
+
+/// 所有用户都拥有的角色
+pub const DEFAULT: &str = "default";
+/// 主人
+pub const MASTER: &str = "master";
+/// 超级管理员
+pub const SUPER_ADMIN: &str = "super_admin";
+/// 群主，仅在检查群内权限时生效
+pub const GROUP_OWNER: &str = "group.owner";
+/// 群管理员，仅在检查群内权限时生效
+pub const GROUP_ADMIN: &str = "group.admin";
+/// 群员，仅在检查群内权限时生效
+pub const GROUP_MEMBER: &str = "group.member";
+
+lazy_static! {
+    static ref Roles: RwLock<HashMap<String, Role>> = RwLock::new(
+        vec![
+            Role::new(DEFAULT),
+            Role::new(MASTER).node("*"),
+            Role::new(SUPER_ADMIN),
+            Role::new(GROUP_OWNER).inherit(GROUP_ADMIN),
+            Role::new(GROUP_ADMIN).inherit(GROUP_MEMBER),
+            Role::new(GROUP_MEMBER),
+        ]
+        .into_iter()
+        .map(|role| (role.name.clone(), role))
+        .collect()
+    );
+    /// (qq, 群号) -> 角色。群号为None即全局授予
+    static ref Grants: RwLock<GrantMap> = RwLock::new(HashMap::new());
+}
+
+type GrantMap = HashMap<(i64, Option<i64>), Vec<String>>;
+
+#[derive(Debug, Clone, Default)]
+pub struct Role {
+    pub name: String,
+    pub nodes: Vec<String>,
+    pub parents: Vec<String>,
+}
+
+impl Role {
+    pub fn new(name: &str) -> Role {
+        Role {
+            name: name.to_owned(),
+            ..Default::default()
+        }
+    }
+
+    /// 添加权限节点，以`-`开头为禁止
+    pub fn node(mut self, node: &str) -> Role {
+        self.nodes.push(node.to_owned());
+        self
+    }
+
+    /// 继承另一个角色的全部节点
+    pub fn inherit(mut self, role: &str) -> Role {
+        self.parents.push(role.to_owned());
+        self
+    }
+}
+
+/// 添加角色，同名角色（包括内置角色）会被覆盖
+pub fn add_role(role: Role) {
+    Roles
+        .write()
+        .expect("cannot write Roles")
+        .insert(role.name.clone(), role);
+}
+
+pub fn remove_role(name: &str) -> Option<Role> {
+    Roles.write().expect("cannot write Roles").remove(name)
+}
+
+pub fn get_role(name: &str) -> Option<Role> {
+    Roles.read().expect("cannot read Roles").get(name).cloned()
+}
+
+/// 授予用户角色
+///
+/// `group_id`为None则全局生效，否则只在该群生效
+pub fn grant(user_id: i64, group_id: Option<i64>, role: &str) {
+    let mut grants = Grants.write().expect("cannot write Grants");
+    let roles = grants.entry((user_id, group_id)).or_default();
+    if !roles.iter().any(|r| r == role) {
+        roles.push(role.to_owned());
+    }
+}
+
+/// 收回用户角色，`group_id`需与授予时一致
+pub fn revoke(user_id: i64, group_id: Option<i64>, role: &str) {
+    if let Some(roles) = Grants
+        .write()
+        .expect("cannot write Grants")
+        .get_mut(&(user_id, group_id))
+    {
+        roles.retain(|r| r != role);
+    }
+}
+
+/// 获取用户被授予的角色（不包括内置角色）
+pub fn get_grants(user_id: i64, group_id: Option<i64>) -> Vec<String> {
+    Grants
+        .read()
+        .expect("cannot read Grants")
+        .get(&(user_id, group_id))
+        .cloned()
+        .unwrap_or_default()
+}
+
+/// 检查用户是否拥有权限节点
+///
+/// `group_id`为Some时，会额外计算该群内授予的角色以及群身份对应的角色。
+/// 群身份从[缓存](crate::cache)中获取该群的群员信息，不是该群的群员时没有群身份。
+pub fn check(user: &User, group_id: Option<i64>, node: &str) -> bool {
+    let mut roles = vec![DEFAULT.to_owned()];
+    let authority = Authority::new(user.user_id);
+    match authority {
+        Authority::Master => roles.push(MASTER.to_owned()),
+        Authority::SuperAdmin => roles.push(SUPER_ADMIN.to_owned()),
+        _ => {},
+    }
+    roles.extend(get_grants(user.user_id, None));
+    if let Some(group_id) = group_id {
+        if authority == Authority::SuperAdmin {
+            roles.push(GROUP_OWNER.to_owned());
+        } else if let Ok(member) = cache::get_group_member(group_id, user.user_id) {
+            roles.push(
+                match member.role {
+                    GroupRole::Owner => GROUP_OWNER,
+                    GroupRole::Admin => GROUP_ADMIN,
+                    GroupRole::Member => GROUP_MEMBER,
+                }
+                .to_owned(),
+            );
+        }
+        roles.extend(get_grants(user.user_id, Some(group_id)));
+    }
+
+    let nodes = collect_nodes(roles);
+    if nodes
+        .iter()
+        .filter_map(|n| n.strip_prefix('-'))
+        .any(|n| matches(n, node))
+    {
+        return false;
+    }
+    nodes
+        .iter()
+        .filter(|n| !n.starts_with('-'))
+        .any(|n| matches(n, node))
+}
+
+/// 展开角色继承，收集全部节点
+fn collect_nodes(mut pending: Vec<String>) -> Vec<String> {
+    let all_roles = Roles.read().expect("cannot read Roles");
+    let mut visited = HashSet::new();
+    let mut nodes = Vec::new();
+    while let Some(name) = pending.pop() {
+        if !visited.insert(name.clone()) {
+            continue;
+        }
+        if let Some(role) = all_roles.get(&name) {
+            nodes.extend(role.nodes.iter().cloned());
+            pending.extend(role.parents.iter().cloned());
+        }
+    }
+    nodes
+}
+
+fn matches(pattern: &str, node: &str) -> bool {
+    if pattern == "*" || pattern == node {
+        return true;
+    }
+    match pattern.strip_suffix(".*") {
+        Some(prefix) => {
+            node == prefix
+                || (node.starts_with(prefix) && node[prefix.len()..].starts_with('.'))
+        },
+        None => false,
+    }
+}
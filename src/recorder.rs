@@ -0,0 +1,228 @@
+//! 录制事件和api调用
+//!
+//! 开始录制后，收到的每个事件（酷q传入的原始参数和解析后的内容）和每次api调用（参数和返回值）
+//! 都会带上毫秒时间戳，以json lines的格式写入插件数据目录下的`recordings`文件夹。
+//! 录下的文件可以用[`testing::replay`](crate::testing::replay)在本地回放，重现线上的问题。
+//!
+//! 酷q一次分发事件时会依次调用各个优先级的函数，只有第一个被调用的会记录事件。字符串都已转换为utf8。
+//!
+//! 需要开启`recorder` feature。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::recorder;
+//!
+//! // 一般在`main`中或者通过菜单开始录制
+//! let path = recorder::start().expect("cannot start recording");
+//! ```
+//!
+//! 录制的文件：
+//! ```text
+//! {"type":"event","time":1589000000000,"function":"on_group_msg","args":[1,1,123456,10001,"","hello",0],"event":"GroupMessageEvent { .. }"}
+//! {"type":"call","time":1589000000003,"function":"send_group_msg","args":[123456,"world"],"result":2}
+//! ```
+
+use std::{
+    cell::RefCell,
+    fmt::Debug,
+    fs::{create_dir_all, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    api::{add_log, get_app_directory, CQLogLevel},
+    events::RawValue,
+};
+
+lazy_static! {
+    static ref Recording: Mutex<Option<Recorder>> = Mutex::new(None);
+}
+
+thread_local! {
+    /// 当前线程正在分发的事件和已经调用过的导出函数
+    static DISPATCHING: RefCell<(&'static str, Vec<&'static str>)> =
+        const { RefCell::new(("", Vec::new())) };
+}
+
+/// 录制文件中的一行
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Record {
+    /// 收到的事件，`function`为不带优先级的函数名，如`on_group_msg`
+    Event(Box<EventRecord>),
+    /// api调用，`function`为[api](crate::api)中的函数名，`result`为空指针时表示调用失败
+    Call {
+        time: u64,
+        function: String,
+        args: Vec<RawValue>,
+        result: RawValue,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct EventRecord {
+    pub time: u64,
+    pub function: String,
+    pub args: Vec<RawValue>,
+    /// 解析后的事件，只用于阅读
+    pub event: String,
+}
+
+impl Record {
+    pub fn time(&self) -> u64 {
+        match self {
+            Record::Event(event) => event.time,
+            Record::Call { time, .. } => *time,
+        }
+    }
+
+    pub fn function(&self) -> &str {
+        match self {
+            Record::Event(event) => &event.function,
+            Record::Call { function, .. } => function,
+        }
+    }
+
+    pub fn args(&self) -> &[RawValue] {
+        match self {
+            Record::Event(event) => &event.args,
+            Record::Call { args, .. } => args,
+        }
+    }
+}
+
+struct Recorder {
+    file: File,
+}
+
+/// 开始录制到插件数据目录下的`recordings/{时间戳}.jsonl`，返回文件路径
+///
+/// 正在录制时会先结束之前的录制。
+pub fn start() -> io::Result<PathBuf> {
+    let app_dir = get_app_directory()
+        .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+        .to::<String>();
+    let path = PathBuf::from(app_dir)
+        .join("recordings")
+        .join(format!("{}.jsonl", now()));
+    start_with(&path)?;
+    Ok(path)
+}
+
+/// 录制到指定文件，文件存在时追加
+pub fn start_with(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    if let Some(dir) = path.parent() {
+        create_dir_all(dir)?;
+    }
+    let file = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)?;
+    *Recording.lock().expect("cannot lock Recording") = Some(Recorder { file });
+    Ok(())
+}
+
+/// 结束录制，插件停用时会自动结束
+pub fn stop() {
+    Recording.lock().expect("cannot lock Recording").take();
+}
+
+pub fn is_recording() -> bool {
+    Recording.lock().expect("cannot lock Recording").is_some()
+}
+
+/// 读取录制的文件
+pub fn read(path: impl AsRef<Path>) -> io::Result<Vec<Record>> {
+    let mut records = Vec::new();
+    for (i, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        records.push(serde_json::from_str(&line).map_err(|e| {
+            io::Error::new(io::ErrorKind::InvalidData, format!("line {}: {}", i + 1, e))
+        })?);
+    }
+    Ok(records)
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_millis() as u64)
+}
+
+/// 酷q在同一线程中依次调用一次分发的各个优先级的函数，每次分发调用的都是同一顺序的前几个，
+/// 所以调用了已经调用过的函数或者换了事件时，就是新的一次分发
+fn first_of_dispatch(function: &'static str, exported: &'static str) -> bool {
+    DISPATCHING.with(|dispatching| {
+        let mut dispatching = dispatching.borrow_mut();
+        if dispatching.0 == function && !dispatching.1.contains(&exported) {
+            dispatching.1.push(exported);
+            false
+        } else {
+            *dispatching = (function, vec![exported]);
+            true
+        }
+    })
+}
+
+pub(crate) fn record_event(
+    function: &'static str, exported: &'static str, args: impl FnOnce() -> Vec<RawValue>,
+    event: &impl Debug,
+) {
+    if !first_of_dispatch(function, exported) || !is_recording() {
+        return;
+    }
+    write(Record::Event(Box::new(EventRecord {
+        time: now(),
+        function: function.to_owned(),
+        args: args(),
+        event: format!("{:?}", event),
+    })));
+}
+
+pub(crate) fn record_call(
+    function: &'static str, args: impl FnOnce() -> Vec<RawValue>,
+    result: impl FnOnce() -> RawValue,
+) {
+    if !is_recording() {
+        return;
+    }
+    write(Record::Call {
+        time: now(),
+        function: function.to_owned(),
+        args: args(),
+        result: result(),
+    });
+}
+
+fn write(record: Record) {
+    let line = serde_json::to_string(&record).expect("cannot serialize Record");
+    let err = {
+        let mut recording = Recording.lock().expect("cannot lock Recording");
+        let recorder = match recording.as_mut() {
+            Some(recorder) => recorder,
+            None => return,
+        };
+        match writeln!(recorder.file, "{}", line) {
+            Ok(()) => return,
+            Err(err) => {
+                recording.take();
+                err
+            },
+        }
+    };
+    // 已经停止录制，add_log不会再被记录
+    let _ = add_log(
+        CQLogLevel::WARNING,
+        "recorder",
+        format!("写入录制文件失败，已停止录制: {}", err),
+    );
+}
@@ -0,0 +1,317 @@
+//! 定时任务
+//!
+//! 支持cron表达式（[`cron`]）和固定间隔（[`interval`]）两种任务，cron表达式按[`timezone`]（默认Asia/Shanghai）计算。
+//!
+//! 任务在插件启用时（`#[coolq_sdk_rust::main]`函数返回之后）开始调度，插件停用或酷q退出时停止。
+//! 每个任务在单独的线程中执行，panic只会被[记录](crate::panic_guard)。
+//!
+//! 每个任务需要一个唯一的名字，上次执行的时间会以名字为key保存在插件数据目录下的`scheduler.json`中，
+//! 重启插件后不会重复执行已经执行过的任务。
+//!
+//! 需要开启`scheduler` feature。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::{api, scheduler};
+//! use std::time::Duration;
+//!
+//! // 一般在`#[coolq_sdk_rust::main]`中注册
+//! // 每天早上8点（秒 分 时 日 月 星期）
+//! scheduler::cron("daily_report", "0 0 8 * * *", || {
+//!     api::send_group_msg(123456, "早上好").ok();
+//! })
+//! .unwrap();
+//!
+//! let cleanup = scheduler::interval("cleanup", Duration::from_secs(60 * 60), || {
+//!     // ...
+//! })
+//! .unwrap();
+//! cleanup.cancel();
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    fs::{read_to_string, rename, write},
+    path::PathBuf,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Condvar, Mutex, RwLock,
+    },
+    thread::{self, JoinHandle},
+    time::Duration,
+};
+
+use chrono::{DateTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+
+use crate::api::get_app_directory;
+
+static NEXT_ID: AtomicUsize = AtomicUsize::new(0);
+static RUNNING: AtomicBool = AtomicBool::new(false);
+
+lazy_static! {
+    static ref Tasks: Mutex<Vec<Task>> = Mutex::new(Vec::new());
+    static ref Wakeup: Condvar = Condvar::new();
+    static ref Worker: Mutex<Option<JoinHandle<()>>> = Mutex::new(None);
+    static ref Timezone: RwLock<Tz> = RwLock::new(chrono_tz::Asia::Shanghai);
+    static ref StateFile: RwLock<Option<PathBuf>> = RwLock::new(None);
+    static ref LastRuns: Mutex<HashMap<String, i64>> = Mutex::new(HashMap::new());
+}
+
+#[derive(Debug)]
+pub enum Error {
+    /// cron表达式错误
+    Cron(cron::error::Error),
+    /// 已有同名的任务
+    Duplicate(String),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Cron(err) => write!(f, "invalid cron expression: {}", err),
+            Error::Duplicate(name) => write!(f, "task `{}` already exists", name),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+enum Trigger {
+    Cron(Box<Schedule>),
+    Interval(Duration),
+}
+
+struct Task {
+    id: usize,
+    name: String,
+    trigger: Trigger,
+    func: Arc<dyn Fn() + Send + Sync>,
+    /// 下次执行的时间，None则需要重新计算
+    next: Option<DateTime<Utc>>,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl Task {
+    fn next_after(&self, now: DateTime<Utc>, last_run: Option<DateTime<Utc>>) -> Option<DateTime<Utc>> {
+        match &self.trigger {
+            Trigger::Cron(schedule) => {
+                let base = last_run.map_or(now, |last| last.max(now));
+                schedule
+                    .after(&base.with_timezone(&timezone()))
+                    .next()
+                    .map(|next| next.with_timezone(&Utc))
+            },
+            Trigger::Interval(period) => {
+                let period = chrono::Duration::from_std(*period).ok()?;
+                // 错过的任务会立即执行
+                Some(last_run.map_or(now + period, |last| (last + period).max(now)))
+            },
+        }
+    }
+}
+
+/// 任务句柄，可以用来取消任务
+#[derive(Debug, Clone)]
+pub struct TaskHandle {
+    id: usize,
+    name: String,
+    cancelled: Arc<AtomicBool>,
+}
+
+impl TaskHandle {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// 取消任务，正在执行的任务不会被中断
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+        Wakeup.notify_all();
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst)
+    }
+
+    /// 下次执行的时间，调度器没有运行或任务已取消时为None
+    pub fn next_run(&self) -> Option<DateTime<Tz>> {
+        Tasks
+            .lock()
+            .expect("cannot lock Tasks")
+            .iter()
+            .find(|task| task.id == self.id)
+            .and_then(|task| task.next)
+            .map(|next| next.with_timezone(&timezone()))
+    }
+}
+
+/// 添加cron任务
+///
+/// 表达式格式为`秒 分 时 日 月 星期 [年]`，见[cron](https://docs.rs/cron)。
+pub fn cron(name: &str, expression: &str, f: impl Fn() + Send + Sync + 'static) -> Result<TaskHandle, Error> {
+    let schedule = Schedule::from_str(expression).map_err(Error::Cron)?;
+    add(name, Trigger::Cron(Box::new(schedule)), Arc::new(f))
+}
+
+/// 添加固定间隔的任务
+pub fn interval(name: &str, period: Duration, f: impl Fn() + Send + Sync + 'static) -> Result<TaskHandle, Error> {
+    add(name, Trigger::Interval(period), Arc::new(f))
+}
+
+fn add(name: &str, trigger: Trigger, func: Arc<dyn Fn() + Send + Sync>) -> Result<TaskHandle, Error> {
+    let mut tasks = Tasks.lock().expect("cannot lock Tasks");
+    if tasks
+        .iter()
+        .any(|task| task.name == name && !task.cancelled.load(Ordering::SeqCst))
+    {
+        return Err(Error::Duplicate(name.to_owned()));
+    }
+    let handle = TaskHandle {
+        id: NEXT_ID.fetch_add(1, Ordering::SeqCst),
+        name: name.to_owned(),
+        cancelled: Arc::new(AtomicBool::new(false)),
+    };
+    tasks.push(Task {
+        id: handle.id,
+        name: handle.name.clone(),
+        trigger,
+        func,
+        next: None,
+        cancelled: handle.cancelled.clone(),
+    });
+    Wakeup.notify_all();
+    Ok(handle)
+}
+
+pub fn timezone() -> Tz {
+    *Timezone.read().expect("cannot read Timezone")
+}
+
+/// 设置cron表达式使用的时区
+pub fn set_timezone(tz: Tz) {
+    *Timezone.write().expect("cannot write Timezone") = tz;
+    Tasks
+        .lock()
+        .expect("cannot lock Tasks")
+        .iter_mut()
+        .for_each(|task| task.next = None);
+    Wakeup.notify_all();
+}
+
+/// 设置保存上次执行时间的文件，默认为插件数据目录下的`scheduler.json`
+///
+/// 需要在[`start`]之前调用。
+pub fn set_state_file(path: impl Into<PathBuf>) {
+    *StateFile.write().expect("cannot write StateFile") = Some(path.into());
+}
+
+fn state_file() -> Option<PathBuf> {
+    let mut file = StateFile.write().expect("cannot write StateFile");
+    if file.is_none() {
+        *file = get_app_directory()
+            .ok()
+            .map(|dir| PathBuf::from(dir.to::<String>()).join("scheduler.json"));
+    }
+    file.clone()
+}
+
+fn load_last_runs() {
+    let last_runs = state_file()
+        .and_then(|path| read_to_string(path).ok())
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default();
+    *LastRuns.lock().expect("cannot lock LastRuns") = last_runs;
+}
+
+fn last_run(name: &str) -> Option<DateTime<Utc>> {
+    LastRuns
+        .lock()
+        .expect("cannot lock LastRuns")
+        .get(name)
+        .and_then(|time| Utc.timestamp_millis_opt(*time).single())
+}
+
+fn save_last_run(name: &str, time: DateTime<Utc>) {
+    let mut last_runs = LastRuns.lock().expect("cannot lock LastRuns");
+    last_runs.insert(name.to_owned(), time.timestamp_millis());
+    if let Some(path) = StateFile.read().expect("cannot read StateFile").as_ref() {
+        // 先写入临时文件再替换，避免写到一半时退出
+        let tmp = path.with_extension("json.tmp");
+        let json = serde_json::to_string(&*last_runs).expect("cannot serialize last runs");
+        if write(&tmp, json).is_ok() {
+            let _ = rename(tmp, path);
+        }
+    }
+}
+
+pub fn is_running() -> bool {
+    RUNNING.load(Ordering::SeqCst)
+}
+
+/// 开始调度，插件启用时会自动调用
+pub fn start() {
+    if RUNNING.swap(true, Ordering::SeqCst) {
+        return;
+    }
+    load_last_runs();
+    *Worker.lock().expect("cannot lock Worker") = Some(
+        thread::Builder::new()
+            .name("cqrs-scheduler".to_owned())
+            .spawn(run)
+            .expect("cannot spawn scheduler thread"),
+    );
+}
+
+/// 停止调度，插件停用或酷q退出时会自动调用
+///
+/// 已注册的任务会保留，再次[`start`]后继续调度。
+pub fn stop() {
+    if !RUNNING.swap(false, Ordering::SeqCst) {
+        return;
+    }
+    Wakeup.notify_all();
+    if let Some(worker) = Worker.lock().expect("cannot lock Worker").take() {
+        let _ = worker.join();
+    }
+    Tasks
+        .lock()
+        .expect("cannot lock Tasks")
+        .iter_mut()
+        .for_each(|task| task.next = None);
+}
+
+fn run() {
+    let mut tasks = Tasks.lock().expect("cannot lock Tasks");
+    while is_running() {
+        tasks.retain(|task| !task.cancelled.load(Ordering::SeqCst));
+        let now = Utc::now();
+        for task in tasks.iter_mut() {
+            match task.next {
+                None => task.next = task.next_after(now, last_run(&task.name)),
+                Some(next) if next <= now => {
+                    let func = task.func.clone();
+                    thread::spawn(move || func());
+                    save_last_run(&task.name, now);
+                    task.next = task.next_after(now, Some(now));
+                },
+                _ => {},
+            }
+        }
+        let wait = tasks
+            .iter()
+            .filter_map(|task| task.next)
+            .min()
+            .map_or(Duration::from_secs(60), |next| {
+                (next - now).to_std().unwrap_or_default()
+            })
+            .min(Duration::from_secs(60));
+        tasks = Wakeup
+            .wait_timeout(tasks, wait)
+            .expect("cannot lock Tasks")
+            .0;
+    }
+}
@@ -0,0 +1,128 @@
+//! 悬浮窗状态
+//!
+//! 使用`#[status(name = "在线人数", period = 1000)]`定义，函数返回任何可以转换为[`Status`]的值，
+//! cqrs_builder会把它添加到app.json的status中。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::prelude::*;
+//! use coolq_sdk_rust::status::{Status, StatusColor};
+//!
+//! #[status(name = "运行时间", title = "UPTIME", period = 1000)]
+//! fn uptime() -> Status {
+//!     Status::new(42).unit("秒").color(StatusColor::Green)
+//! }
+//!
+//! #[status(name = "处理消息数")]
+//! fn handled() -> usize {
+//!     42
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    ffi::CString,
+    os::raw::c_char,
+    sync::Mutex,
+};
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use crate::iconv::IconvEncodable;
+
+lazy_static! {
+    /// 保存返回给酷q的字符串，直到下一次调用
+    static ref Returned: Mutex<HashMap<&'static str, CString>> = Mutex::new(HashMap::new());
+}
+
+/// 悬浮窗中文字的颜色
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum StatusColor {
+    Green = 1,
+    Orange = 2,
+    Red = 3,
+    DeepRed = 4,
+    Black = 5,
+    Gray = 6,
+}
+
+#[derive(Debug, Clone)]
+pub struct Status {
+    pub data: String,
+    pub unit: String,
+    pub color: StatusColor,
+}
+
+impl Status {
+    pub fn new(data: impl ToString) -> Status {
+        Status {
+            data: data.to_string(),
+            unit: String::new(),
+            color: StatusColor::Green,
+        }
+    }
+
+    pub fn unit(mut self, unit: impl ToString) -> Self {
+        self.unit = unit.to_string();
+        self
+    }
+
+    pub fn color(mut self, color: StatusColor) -> Self {
+        self.color = color;
+        self
+    }
+
+    /// 编码为酷q需要的格式，无法转换为GB18030时返回`None`
+    pub fn encode(&self) -> Option<String> {
+        let data = self.data.encode_with_encoding("GB18030")?;
+        let unit = self.unit.encode_with_encoding("GB18030")?;
+        Some(frame(&data, &unit, self.color))
+    }
+}
+
+/// i16长度 + data、unit，i32的颜色，整体base64
+///
+/// 超过`i16::MAX`字节的部分会被截掉
+#[doc(hidden)]
+pub fn frame(data: &[u8], unit: &[u8], color: StatusColor) -> String {
+    let mut b = Vec::new();
+    write_bytes(&mut b, data);
+    write_bytes(&mut b, unit);
+    b.write_i32::<BigEndian>(color as i32).unwrap();
+    base64::encode(&b)
+}
+
+fn write_bytes(b: &mut Vec<u8>, s: &[u8]) {
+    let s = &s[..s.len().min(i16::MAX as usize)];
+    b.write_i16::<BigEndian>(s.len() as i16).unwrap();
+    b.extend(s);
+}
+
+macro_rules! status_from {
+    ($($t: ty),*) => {
+        $(
+            impl From<$t> for Status {
+                fn from(data: $t) -> Self {
+                    Status::new(data)
+                }
+            }
+        )*
+    };
+}
+
+status_from!(&str, String, i32, i64, u32, u64, usize, f32, f64);
+
+/// 由[`status`](cqrs_macro::status)生成的函数调用
+#[doc(hidden)]
+pub fn encode(function: &'static str, status: impl FnOnce() -> Status) -> *const c_char {
+    // 编码也可能失败，一起放在catch_unwind里，失败时显示ascii的error，不需要转换编码
+    let encoded = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| status().encode()))
+        .ok()
+        .flatten()
+        .unwrap_or_else(|| frame(b"error", b"", StatusColor::Red));
+    let encoded = CString::new(encoded).expect("cannot encode status");
+    let mut returned = Returned.lock().expect("cannot lock Returned");
+    let ptr = encoded.as_ptr();
+    returned.insert(function, encoded);
+    ptr
+}
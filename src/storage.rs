@@ -0,0 +1,435 @@
+//! 插件数据存储
+//!
+//! 按[`Scope`]（全局、群、用户、群成员）分开保存的键值对，值可以是任何实现了serde的`Serialize`/`Deserialize`的类型。
+//!
+//! 默认的[`FileStorage`]把每个scope保存为插件数据目录下`storage`文件夹中的一个json文件，
+//! 读取过的scope会缓存在内存中。每次修改只追加到`.journal`文件，
+//! 累计[`compact_after`](FileStorage::compact_after)次修改后才通过写入临时文件并替换的方式更新json文件并清空journal，
+//! 下次读取时会重放journal中还没有写入json文件的修改。
+//!
+//! 测试时可以使用[`MemoryStorage`]。
+//!
+//! 需要开启`storage` feature。
+//!
+//! # Examples
+//! ```
+//! use coolq_sdk_rust::storage::{MemoryStorage, Scope, Store};
+//!
+//! // 插件中一般使用`storage::global()?`
+//! let store = Store::new(MemoryStorage::new());
+//!
+//! let group = store.scoped(Scope::Group(123456));
+//! group.set("welcome", &"欢迎新人").unwrap();
+//! assert_eq!(group.get::<String>("welcome").unwrap().as_deref(), Some("欢迎新人"));
+//!
+//! store.set(Scope::Member(123456, 10000), "sign_in_days", &7).unwrap();
+//! assert_eq!(store.get::<i32>(Scope::Member(123456, 10000), "sign_in_days").unwrap(), Some(7));
+//! ```
+
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    fmt::{self, Display, Formatter},
+    fs::{create_dir_all, read_to_string, remove_file, rename, File, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+    sync::{Arc, Mutex, RwLock},
+};
+
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::api::get_app_directory;
+
+lazy_static! {
+    static ref GlobalStore: RwLock<Option<Arc<Store>>> = RwLock::new(None);
+}
+
+#[derive(Debug)]
+pub enum Error {
+    Io(io::Error),
+    Json(serde_json::Error),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Io(err) => write!(f, "storage io error: {}", err),
+            Error::Json(err) => write!(f, "storage json error: {}", err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<io::Error> for Error {
+    fn from(err: io::Error) -> Self {
+        Error::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for Error {
+    fn from(err: serde_json::Error) -> Self {
+        Error::Json(err)
+    }
+}
+
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// 数据的作用范围
+#[derive(Debug, Clone, Copy, Eq, PartialEq, Hash)]
+pub enum Scope {
+    Global,
+    Group(i64),
+    User(i64),
+    /// 群号，qq号
+    Member(i64, i64),
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        match self {
+            Scope::Global => write!(f, "global"),
+            Scope::Group(group_id) => write!(f, "group_{}", group_id),
+            Scope::User(user_id) => write!(f, "user_{}", user_id),
+            Scope::Member(group_id, user_id) => write!(f, "member_{}_{}", group_id, user_id),
+        }
+    }
+}
+
+/// 存储后端
+pub trait Backend: Send + Sync + 'static {
+    fn get(&self, scope: Scope, key: &str) -> Result<Option<Value>>;
+
+    fn set(&self, scope: Scope, key: &str, value: Value) -> Result<()>;
+
+    /// 返回被删除的值
+    fn remove(&self, scope: Scope, key: &str) -> Result<Option<Value>>;
+
+    fn keys(&self, scope: Scope) -> Result<Vec<String>>;
+}
+
+/// 带类型的键值对存储
+pub struct Store {
+    backend: Box<dyn Backend>,
+}
+
+impl Store {
+    pub fn new(backend: impl Backend) -> Store {
+        Store {
+            backend: Box::new(backend),
+        }
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, scope: Scope, key: &str) -> Result<Option<T>> {
+        match self.backend.get(scope, key)? {
+            Some(value) => Ok(Some(serde_json::from_value(value)?)),
+            None => Ok(None),
+        }
+    }
+
+    pub fn get_or_default<T: DeserializeOwned + Default>(&self, scope: Scope, key: &str) -> Result<T> {
+        Ok(self.get(scope, key)?.unwrap_or_default())
+    }
+
+    pub fn set<T: Serialize + ?Sized>(&self, scope: Scope, key: &str, value: &T) -> Result<()> {
+        self.backend.set(scope, key, serde_json::to_value(value)?)
+    }
+
+    pub fn remove(&self, scope: Scope, key: &str) -> Result<bool> {
+        Ok(self.backend.remove(scope, key)?.is_some())
+    }
+
+    pub fn contains(&self, scope: Scope, key: &str) -> Result<bool> {
+        Ok(self.backend.get(scope, key)?.is_some())
+    }
+
+    pub fn keys(&self, scope: Scope) -> Result<Vec<String>> {
+        self.backend.keys(scope)
+    }
+
+    /// 读取后修改再写回，没有值时使用默认值
+    pub fn update<T, F>(&self, scope: Scope, key: &str, f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T),
+    {
+        let mut value = self.get_or_default(scope, key)?;
+        f(&mut value);
+        self.set(scope, key, &value)?;
+        Ok(value)
+    }
+
+    pub fn scoped(&self, scope: Scope) -> ScopedStore<'_> {
+        ScopedStore { store: self, scope }
+    }
+}
+
+/// 固定了[`Scope`]的[`Store`]
+pub struct ScopedStore<'a> {
+    store: &'a Store,
+    scope: Scope,
+}
+
+impl ScopedStore<'_> {
+    pub fn scope(&self) -> Scope {
+        self.scope
+    }
+
+    pub fn get<T: DeserializeOwned>(&self, key: &str) -> Result<Option<T>> {
+        self.store.get(self.scope, key)
+    }
+
+    pub fn get_or_default<T: DeserializeOwned + Default>(&self, key: &str) -> Result<T> {
+        self.store.get_or_default(self.scope, key)
+    }
+
+    pub fn set<T: Serialize + ?Sized>(&self, key: &str, value: &T) -> Result<()> {
+        self.store.set(self.scope, key, value)
+    }
+
+    pub fn remove(&self, key: &str) -> Result<bool> {
+        self.store.remove(self.scope, key)
+    }
+
+    pub fn contains(&self, key: &str) -> Result<bool> {
+        self.store.contains(self.scope, key)
+    }
+
+    pub fn keys(&self) -> Result<Vec<String>> {
+        self.store.keys(self.scope)
+    }
+
+    pub fn update<T, F>(&self, key: &str, f: F) -> Result<T>
+    where
+        T: Serialize + DeserializeOwned + Default,
+        F: FnOnce(&mut T),
+    {
+        self.store.update(self.scope, key, f)
+    }
+}
+
+/// 插件共用的[`Store`]，默认使用插件数据目录下的[`FileStorage`]
+///
+/// 无法获取插件数据目录或创建`storage`文件夹时返回错误，下次调用会重试。
+pub fn global() -> Result<Arc<Store>> {
+    if let Some(store) = GlobalStore.read().expect("cannot read GlobalStore").as_ref() {
+        return Ok(store.clone());
+    }
+    let mut global = GlobalStore.write().expect("cannot write GlobalStore");
+    if let Some(store) = global.as_ref() {
+        return Ok(store.clone());
+    }
+    let store = Arc::new(Store::new(FileStorage::new()?));
+    *global = Some(store.clone());
+    Ok(store)
+}
+
+/// 替换插件共用的[`Store`]，如在测试中使用[`MemoryStorage`]
+pub fn set_global(store: Store) {
+    *GlobalStore.write().expect("cannot write GlobalStore") = Some(Arc::new(store));
+}
+
+/// 只保存在内存中的存储，用于测试
+#[derive(Default)]
+pub struct MemoryStorage {
+    data: RwLock<HashMap<Scope, HashMap<String, Value>>>,
+}
+
+impl MemoryStorage {
+    pub fn new() -> MemoryStorage {
+        MemoryStorage::default()
+    }
+}
+
+impl Backend for MemoryStorage {
+    fn get(&self, scope: Scope, key: &str) -> Result<Option<Value>> {
+        Ok(self
+            .data
+            .read()
+            .expect("cannot read MemoryStorage")
+            .get(&scope)
+            .and_then(|data| data.get(key).cloned()))
+    }
+
+    fn set(&self, scope: Scope, key: &str, value: Value) -> Result<()> {
+        self.data
+            .write()
+            .expect("cannot write MemoryStorage")
+            .entry(scope)
+            .or_default()
+            .insert(key.to_owned(), value);
+        Ok(())
+    }
+
+    fn remove(&self, scope: Scope, key: &str) -> Result<Option<Value>> {
+        Ok(self
+            .data
+            .write()
+            .expect("cannot write MemoryStorage")
+            .get_mut(&scope)
+            .and_then(|data| data.remove(key)))
+    }
+
+    fn keys(&self, scope: Scope) -> Result<Vec<String>> {
+        Ok(self
+            .data
+            .read()
+            .expect("cannot read MemoryStorage")
+            .get(&scope)
+            .map(|data| data.keys().cloned().collect())
+            .unwrap_or_default())
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+struct JournalEntry {
+    key: String,
+    /// None为删除
+    value: Option<Value>,
+}
+
+/// 保存为json文件的存储
+pub struct FileStorage {
+    dir: PathBuf,
+    cache: RwLock<HashMap<Scope, HashMap<String, Value>>>,
+    /// 保证同一时间只有一个写入，同时记录每个scope的journal中有多少条修改
+    journal_len: Mutex<HashMap<Scope, usize>>,
+    compact_after: usize,
+}
+
+impl FileStorage {
+    /// 使用插件数据目录下的`storage`文件夹
+    pub fn new() -> io::Result<FileStorage> {
+        let app_dir = get_app_directory()
+            .map_err(|e| io::Error::new(io::ErrorKind::Other, e))?
+            .to::<String>();
+        FileStorage::with_dir(PathBuf::from(app_dir).join("storage"))
+    }
+
+    pub fn with_dir(dir: impl Into<PathBuf>) -> io::Result<FileStorage> {
+        let dir = dir.into();
+        create_dir_all(&dir)?;
+        Ok(FileStorage {
+            dir,
+            cache: RwLock::new(HashMap::new()),
+            journal_len: Mutex::new(HashMap::new()),
+            compact_after: 100,
+        })
+    }
+
+    /// journal中累计多少次修改后写入json文件，默认100，为1时每次修改都会写入
+    pub fn compact_after(mut self, n: usize) -> Self {
+        self.compact_after = n.max(1);
+        self
+    }
+
+    fn path(&self, scope: Scope, extension: &str) -> PathBuf {
+        self.dir.join(format!("{}.{}", scope, extension))
+    }
+
+    /// 读取json文件并重放journal
+    fn load(&self, scope: Scope) -> Result<HashMap<String, Value>> {
+        let mut data: HashMap<String, Value> = match read_to_string(self.path(scope, "json")) {
+            Ok(json) => serde_json::from_str(&json)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => HashMap::new(),
+            Err(err) => return Err(err.into()),
+        };
+        let journal = match read_to_string(self.path(scope, "journal")) {
+            Ok(journal) => journal,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok(data),
+            Err(err) => return Err(err.into()),
+        };
+        // 最后一行可能没有写完，忽略解析失败的行
+        journal
+            .lines()
+            .filter_map(|line| serde_json::from_str::<JournalEntry>(line).ok())
+            .for_each(|entry| match entry.value {
+                Some(value) => {
+                    data.insert(entry.key, value);
+                },
+                None => {
+                    data.remove(&entry.key);
+                },
+            });
+        self.snapshot(scope, &data)?;
+        Ok(data)
+    }
+
+    /// 写入json文件并清空journal
+    fn snapshot(&self, scope: Scope, data: &HashMap<String, Value>) -> Result<()> {
+        let path = self.path(scope, "json");
+        let tmp = self.path(scope, "json.tmp");
+        let mut file = File::create(&tmp)?;
+        file.write_all(serde_json::to_string(data)?.as_bytes())?;
+        file.sync_all()?;
+        rename(tmp, path)?;
+        match remove_file(self.path(scope, "journal")) {
+            Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+
+    fn append_journal(&self, scope: Scope, entry: &JournalEntry) -> Result<()> {
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(self.path(scope, "journal"))?;
+        file.write_all(format!("{}\n", serde_json::to_string(entry)?).as_bytes())?;
+        file.sync_all()?;
+        Ok(())
+    }
+
+    fn with_data<R>(&self, scope: Scope, f: impl FnOnce(&HashMap<String, Value>) -> R) -> Result<R> {
+        if let Some(data) = self.cache.read().expect("cannot read storage cache").get(&scope) {
+            return Ok(f(data));
+        }
+        let mut cache = self.cache.write().expect("cannot write storage cache");
+        // 加载可能失败，不能用or_insert_with
+        let data = match cache.entry(scope) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => entry.insert(self.load(scope)?),
+        };
+        Ok(f(data))
+    }
+
+    fn modify(&self, scope: Scope, key: &str, value: Option<Value>) -> Result<Option<Value>> {
+        let mut journal_len = self.journal_len.lock().expect("cannot lock storage");
+        self.with_data(scope, |_| ())?;
+        let entry = JournalEntry {
+            key: key.to_owned(),
+            value,
+        };
+        self.append_journal(scope, &entry)?;
+        let mut cache = self.cache.write().expect("cannot write storage cache");
+        let data = cache.entry(scope).or_default();
+        let old = match entry.value {
+            Some(value) => data.insert(entry.key, value),
+            None => data.remove(&entry.key),
+        };
+        let len = journal_len.entry(scope).or_default();
+        *len += 1;
+        if *len >= self.compact_after {
+            self.snapshot(scope, data)?;
+            *len = 0;
+        }
+        Ok(old)
+    }
+}
+
+impl Backend for FileStorage {
+    fn get(&self, scope: Scope, key: &str) -> Result<Option<Value>> {
+        self.with_data(scope, |data| data.get(key).cloned())
+    }
+
+    fn set(&self, scope: Scope, key: &str, value: Value) -> Result<()> {
+        self.modify(scope, key, Some(value)).map(|_| ())
+    }
+
+    fn remove(&self, scope: Scope, key: &str) -> Result<Option<Value>> {
+        self.modify(scope, key, None)
+    }
+
+    fn keys(&self, scope: Scope) -> Result<Vec<String>> {
+        self.with_data(scope, |data| data.keys().cloned().collect())
+    }
+}
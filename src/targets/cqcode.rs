@@ -191,6 +191,17 @@ impl CQStr for str {
     }
 }
 
+/// 把消息中的每个cq码替换为`f`的返回值
+pub fn replace(msg: &str, f: impl Fn(CQCode) -> String) -> String {
+    tag_regex
+        .replace_all(msg, |caps: &regex::Captures| {
+            parse(&caps[0])
+                .pop()
+                .map_or_else(|| caps[0].to_owned(), &f)
+        })
+        .to_string()
+}
+
 pub fn parse(msg: &str) -> Vec<CQCode> {
     tag_regex
         .captures_iter(msg)
@@ -0,0 +1,26 @@
+use crate::{
+    api::{send_discuss_msg, set_discuss_leave, Convert},
+    targets::message::SendMessage,
+};
+
+#[derive(Debug, Clone)]
+pub struct Discuss {
+    pub discuss_id: i64,
+}
+
+impl SendMessage for Discuss {
+    fn send(&self, msg: impl ToString) -> crate::api::Result<Convert<i32>> {
+        send_discuss_msg(self.discuss_id, msg.to_string())
+    }
+}
+
+impl Discuss {
+    pub fn new(discuss_id: i64) -> Discuss {
+        Discuss { discuss_id }
+    }
+
+    /// 退出讨论组
+    pub fn leave(&self) -> crate::api::Result<Convert<i32>> {
+        set_discuss_leave(self.discuss_id)
+    }
+}
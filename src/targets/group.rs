@@ -8,9 +8,11 @@ use byteorder::{BigEndian, ReadBytesExt};
 use crate::{
     api::{
         get_group_info, get_group_member_info_v2, get_group_member_list, send_group_msg,
-        set_group_anonymous, set_group_ban, set_group_kick, set_group_whole_ban, Convert,
+        set_group_anonymous, set_group_ban, set_group_kick, set_group_whole_ban, Convert, Error,
     },
+    cache,
     targets::{
+        decode_base64,
         message::SendMessage,
         user::{Authority, UserSex},
         ReadString,
@@ -63,7 +65,7 @@ impl SendMessage for GroupMember {
 
 impl GroupMember {
     pub(crate) fn decode(b: &[u8]) -> std::io::Result<GroupMember> {
-        let mut b = Cursor::new(base64::decode(&b).expect("Invalid base64 - decode GroupMember"));
+        let mut b = decode_base64(b)?;
         let mut gm = GroupMember {
             group_id: b.read_i64::<BigEndian>()?,
             user_id: b.read_i64::<BigEndian>()?,
@@ -102,14 +104,12 @@ impl SendMessage for Group {
 }
 
 impl Group {
+    /// 从[缓存](crate::cache)获取群信息
     pub fn new(group_id: i64) -> Group {
-        if let Ok(c) = get_group_info(group_id, false) {
-            c.try_into().expect("cannot decode group")
-        } else {
-            let mut group = Group::default();
-            group.group_id = group_id;
-            group
-        }
+        cache::get_group(group_id).unwrap_or_else(|_| Group {
+            group_id,
+            ..Default::default()
+        })
     }
 
     /// 部分参数如 area、title 等等无法获取到（为空）。要获取全部参数请使用 get_member。
@@ -119,10 +119,20 @@ impl Group {
             .expect("cannot decode GroupMember list"))
     }
 
+    /// 从[缓存](crate::cache)获取群员信息。要获取最新信息请使用[`update_member`]
+    ///
+    /// [`update_member`]: Group::update_member
     pub fn get_member(&self, user_id: i64) -> crate::api::Result<GroupMember> {
-        Ok(get_group_member_info_v2(self.group_id, user_id, false)?
+        cache::get_group_member(self.group_id, user_id)
+    }
+
+    /// 不使用缓存获取群员信息，并刷新缓存
+    pub fn update_member(&self, user_id: i64) -> crate::api::Result<GroupMember> {
+        let gm: GroupMember = get_group_member_info_v2(self.group_id, user_id, true)?
             .try_into()
-            .expect("cannot decode GroupMember"))
+            .map_err(|_| Error(0))?;
+        cache::put_group_member(gm.clone());
+        Ok(gm)
     }
 
     pub fn set_can_anonymous(&self, enable: bool) -> crate::api::Result<Convert<i32>> {
@@ -141,10 +151,13 @@ impl Group {
         set_group_kick(self.group_id, user_id, refuse_rejoin)
     }
 
+    /// 不使用缓存获取群信息，并刷新缓存
     pub fn update(&mut self) -> crate::api::Result<Group> {
-        Ok(get_group_info(self.group_id, true)?
+        let group: Group = get_group_info(self.group_id, true)?
             .try_into()
-            .expect("cannot decode Group"))
+            .map_err(|_| Error(0))?;
+        cache::put_group(group.clone());
+        Ok(group)
     }
 
     /// 用于get_group_list
@@ -159,7 +172,7 @@ impl Group {
     }
 
     pub(crate) fn decode(b: &[u8]) -> Result<Group> {
-        let mut b = Cursor::new(base64::decode(&b).unwrap());
+        let mut b = decode_base64(b)?;
         Ok(Group {
             group_id: b.read_i64::<BigEndian>()?,
             group_name: b.read_string()?,
@@ -0,0 +1,127 @@
+//! 延迟获取信息的目标
+//!
+//! 事件中的[`User`]和[`Group`]只保存qq号/群号，在第一次调用[`Lazy::get`]时才会去[缓存](crate::cache)或api获取信息，
+//! 之后的调用都返回同一个值。
+//! 只需要id的话请使用[`Lazy::id`]，不会产生任何api调用。
+//!
+//! `Lazy`没有实现`Deref`，以免在访问字段时产生意料之外的阻塞调用。
+//! 原来的`event.group.group_name`需要改为`event.group.get().group_name`。
+//!
+//! # Examples
+//! ```no_run
+//! use coolq_sdk_rust::prelude::*;
+//!
+//! #[listener]
+//! fn ban(event: GroupBanEvent) {
+//!     // 不会获取群信息
+//!     let group_id = event.group.id();
+//!     // 第一次调用get时获取
+//!     let name = &event.group.get().group_name;
+//! }
+//! ```
+
+use once_cell::sync::OnceCell;
+
+use crate::{
+    api::{send_group_msg, send_private_msg, Convert},
+    cache,
+    targets::{group::Group, message::SendMessage, user::User},
+};
+
+pub trait Resolve: Sized {
+    /// `group_id`为事件所在的群
+    fn resolve(id: i64, group_id: Option<i64>) -> Self;
+}
+
+impl Resolve for User {
+    fn resolve(id: i64, group_id: Option<i64>) -> Self {
+        let mut user = User::new(id);
+        if let Some(group_id) = group_id {
+            if let Ok(gm) = cache::get_group_member(group_id, id) {
+                user.set_authority(gm.authority);
+            }
+        }
+        user
+    }
+}
+
+impl Resolve for Group {
+    fn resolve(id: i64, _: Option<i64>) -> Self {
+        Group::new(id)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Lazy<T> {
+    id: i64,
+    group_id: Option<i64>,
+    value: OnceCell<T>,
+}
+
+impl<T: Resolve> Lazy<T> {
+    pub(crate) fn new(id: i64) -> Self {
+        Lazy {
+            id,
+            group_id: None,
+            value: OnceCell::new(),
+        }
+    }
+
+    /// 获取User时会带上在该群的权限
+    pub(crate) fn in_group(id: i64, group_id: i64) -> Self {
+        Lazy {
+            id,
+            group_id: Some(group_id),
+            value: OnceCell::new(),
+        }
+    }
+
+    /// qq号或群号
+    pub fn id(&self) -> i64 {
+        self.id
+    }
+
+    /// 获取信息，只会获取一次
+    pub fn get(&self) -> &T {
+        self.value
+            .get_or_init(|| T::resolve(self.id, self.group_id))
+    }
+
+    pub fn is_resolved(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    pub fn into_inner(self) -> T {
+        self.get();
+        self.value.into_inner().unwrap()
+    }
+}
+
+#[cfg(feature = "async-listener")]
+#[cfg_attr(docsrs, doc(cfg(feature = "async-listener")))]
+impl<T: Resolve + Send + 'static> Lazy<T> {
+    /// 在tokio的blocking线程池中获取，不会阻塞异步运行时
+    pub async fn get_async(&self) -> &T {
+        if let Some(v) = self.value.get() {
+            return v;
+        }
+        let (id, group_id) = (self.id, self.group_id);
+        let v = tokio::task::spawn_blocking(move || T::resolve(id, group_id))
+            .await
+            .expect("resolve task panicked");
+        self.value.get_or_init(|| v)
+    }
+}
+
+// 发送消息只需要id，不需要获取信息
+impl SendMessage for Lazy<Group> {
+    fn send(&self, msg: impl ToString) -> crate::api::Result<Convert<i32>> {
+        send_group_msg(self.id, msg.to_string())
+    }
+}
+
+impl SendMessage for Lazy<User> {
+    fn send(&self, msg: impl ToString) -> crate::api::Result<Convert<i32>> {
+        send_private_msg(self.id, msg.to_string())
+    }
+}
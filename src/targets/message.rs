@@ -24,6 +24,10 @@ impl Message {
         }
     }
 
+    pub fn get_msg_id(&self) -> i32 {
+        self.msg_id
+    }
+
     /// 撤回消息
     pub fn delete(&self) -> bool {
         delete_msg(self.msg_id).is_ok()
@@ -1,4 +1,4 @@
-use std::io::{Cursor, Read, Result as IOResult};
+use std::io::{Cursor, Error as IOError, ErrorKind, Read, Result as IOResult};
 
 use byteorder::{BigEndian, ReadBytesExt};
 
@@ -10,11 +10,20 @@ use crate::{
 pub mod cqcode;
 pub mod message;
 
+pub mod discuss;
 pub mod group;
+pub mod lazy;
 pub mod user;
 
+/// 酷q返回的数据不是合法的base64时返回`InvalidData`
+pub(crate) fn decode_base64(b: &[u8]) -> IOResult<Cursor<Vec<u8>>> {
+    base64::decode(b)
+        .map(Cursor::new)
+        .map_err(|err| IOError::new(ErrorKind::InvalidData, err))
+}
+
 pub(crate) fn read_multi_object(b: &[u8]) -> IOResult<Vec<Vec<u8>>> {
-    let mut b = Cursor::new(base64::decode(&b).expect("Invalid base64 - read_multi_object"));
+    let mut b = decode_base64(b)?;
     let count = b.read_i32::<BigEndian>()?;
     let mut vs = Vec::new();
     for _ in 0..count {
@@ -31,7 +40,8 @@ pub trait ReadString: Read {
         if len > 0 {
             let mut v = vec![0u8; len as usize];
             self.read_exact(&mut v)?;
-            Ok(v.decode_with_encoding("GB18030").unwrap())
+            v.decode_with_encoding("GB18030")
+                .ok_or_else(|| IOError::new(ErrorKind::InvalidData, "invalid GB18030 string"))
         } else {
             Ok(String::new())
         }
@@ -40,7 +50,7 @@ pub trait ReadString: Read {
 
 impl<R: Read + ?Sized> ReadString for R {}
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Default)]
 pub struct File {
     pub id: String,
     pub name: String,
@@ -50,7 +60,7 @@ pub struct File {
 
 impl File {
     pub(crate) fn decode(b: &[u8]) -> IOResult<File> {
-        let mut b = Cursor::new(base64::decode(&b).expect("Invalid base64 - decode File"));
+        let mut b = decode_base64(b)?;
         Ok(File {
             id: b.read_string()?,
             name: b.read_string()?,
@@ -58,6 +68,68 @@ impl File {
             busid: b.read_i64::<BigEndian>()?,
         })
     }
+
+    /// 文件扩展名（小写），没有则为空
+    pub fn extension(&self) -> String {
+        match self.name.rfind('.') {
+            Some(i) if i > 0 => self.name[i + 1..].to_lowercase(),
+            _ => String::new(),
+        }
+    }
+
+    /// 根据扩展名判断文件类型
+    pub fn file_type(&self) -> FileType {
+        FileType::from_extension(&self.extension())
+    }
+
+    /// 人类可读的文件大小，如`1.5 MB`
+    pub fn human_size(&self) -> String {
+        const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+        let mut size = self.size as f64;
+        let mut unit = 0;
+        while size >= 1024.0 && unit < UNITS.len() - 1 {
+            size /= 1024.0;
+            unit += 1;
+        }
+        if unit == 0 {
+            format!("{} {}", self.size, UNITS[0])
+        } else {
+            format!("{:.1} {}", size, UNITS[unit])
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum FileType {
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Document,
+    Text,
+    Executable,
+    Other,
+}
+
+impl FileType {
+    pub fn from_extension(ext: &str) -> FileType {
+        match ext.to_lowercase().as_str() {
+            "jpg" | "jpeg" | "png" | "gif" | "bmp" | "webp" | "svg" | "ico" => FileType::Image,
+            "mp3" | "wav" | "flac" | "ape" | "aac" | "ogg" | "m4a" | "amr" | "silk" => {
+                FileType::Audio
+            },
+            "mp4" | "mkv" | "avi" | "mov" | "wmv" | "flv" | "webm" | "rmvb" => FileType::Video,
+            "zip" | "rar" | "7z" | "tar" | "gz" | "bz2" | "xz" => FileType::Archive,
+            "doc" | "docx" | "xls" | "xlsx" | "ppt" | "pptx" | "pdf" | "odt" => {
+                FileType::Document
+            },
+            "txt" | "md" | "log" | "json" | "xml" | "csv" | "ini" | "toml" | "yml" | "yaml" => {
+                FileType::Text
+            },
+            "exe" | "msi" | "apk" | "bat" | "dll" => FileType::Executable,
+            _ => FileType::Other,
+        }
+    }
 }
 
 #[derive(Debug, Default, Clone)]
@@ -74,7 +146,7 @@ impl Anonymous {
     }
 
     pub(crate) fn decode(b: &[u8], group_id: i64) -> IOResult<Anonymous> {
-        let mut c = Cursor::new(base64::decode(&b).expect("Invalid base64 - decode Anonymous"));
+        let mut c = decode_base64(b)?;
         Ok(Anonymous {
             group_id: group_id,
             user_id: c.read_i64::<BigEndian>()?,
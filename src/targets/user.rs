@@ -7,18 +7,23 @@
 //!
 //! 使用[`check_authority`]来检查用户权限。
 //!
+//! 需要更细的权限划分请使用[`permission`]。
+//!
 //! [Authorit]: Authority
 //! [`add_master`]: User::add_master
 //! [`add_super_admin`]: User::add_super_admin
 //! [`check_authority`]: Authority::check_authority
+//! [`permission`]: crate::permission
 
 use std::{convert::TryInto, io::Cursor, sync::RwLock};
 
 use byteorder::{BigEndian, ReadBytesExt};
 
 use crate::{
-    api::{get_stranger_info, send_private_msg, Convert},
+    api::{get_stranger_info, send_private_msg, Convert, Error},
+    cache,
     targets::{
+        decode_base64,
         group::{GroupMember, GroupRole},
         message::SendMessage,
         ReadString,
@@ -67,21 +72,20 @@ impl Authority {
         self <= &authority
     }
 
+    /// 同时在主人和超级管理员列表中的用户视为主人
     pub fn new(id: i64) -> Authority {
-        if !SuperAdminList
-            .read()
-            .expect("cannot read SuperAdminList")
-            .iter()
-            .all(|qq| *qq != id)
-        {
-            Authority::SuperAdmin
-        } else if !MasterList
+        if MasterList
             .read()
             .expect("cannot read MasterList")
-            .iter()
-            .all(|qq| *qq != id)
+            .contains(&id)
         {
             Authority::Master
+        } else if SuperAdminList
+            .read()
+            .expect("cannot read SuperAdminList")
+            .contains(&id)
+        {
+            Authority::SuperAdmin
         } else {
             Authority::User
         }
@@ -161,30 +165,36 @@ impl User {
     //为了防止获取频率过大，所有从事件获取到的User皆是从缓存取的。
     //如果想获得最新信息，请使用update。
     pub(crate) fn new(user_id: i64) -> User {
-        let mut user: User = if let Ok(c) = get_stranger_info(user_id, false) {
-            c.try_into().expect("cannot decode User")
-        } else {
-            Default::default()
-        };
+        let mut user = cache::get_user(user_id).unwrap_or_default();
         user.user_id = user_id;
         user.set_authority(Authority::new(user_id));
         user
     }
 
+    /// 检查权限节点，详见[`permission::check`]
+    ///
+    /// [`permission::check`]: crate::permission::check
+    pub fn has_permission(&self, group_id: Option<i64>, node: &str) -> bool {
+        crate::permission::check(self, group_id, node)
+    }
+
     pub(crate) fn set_authority(&mut self, authority: Authority) {
         if !self.authority.check_authority(authority) {
             self.authority = authority
         }
     }
 
+    /// 不使用缓存获取信息，并刷新缓存
     pub fn update(&mut self) -> crate::api::Result<User> {
-        Ok(get_stranger_info(self.user_id, true)?
+        let user: User = get_stranger_info(self.user_id, true)?
             .try_into()
-            .expect("cannot decode User"))
+            .map_err(|_| Error(0))?;
+        cache::put_user(user.clone());
+        Ok(user)
     }
 
     pub(crate) fn decode(b: &[u8]) -> std::io::Result<User> {
-        let mut b = Cursor::new(base64::decode(&b).expect("Invalid base64 - decode User"));
+        let mut b = decode_base64(b)?;
         Ok(User {
             user_id: b.read_i64::<BigEndian>()?,
             nickname: b.read_string()?,
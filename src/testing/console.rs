@@ -0,0 +1,305 @@
+//! 在终端中和插件对话
+//!
+//! 插件作为库链接进来，注册消息listener后，输入的文字会以当前用户的身份发送到当前的群、讨论组或私聊，
+//! 插件发出的消息（cq码会转换为可读的文字）、管理操作和警告以上的日志会被打印出来。
+//!
+//! 以`/`开头的是命令：
+//!
+//! * `/user 10001`: 切换发送消息的用户
+//! * `/group 123456`、`/discuss 123456`、`/private`: 切换到群、讨论组、私聊
+//! * `/login 10000`: 修改机器人的qq号
+//! * `/quit`: 退出
+//!
+//! # Examples
+//! 完整的例子见`examples/console.rs`，用`cargo run --example console --features testing`运行。
+//! ```ignore
+//! // examples/console.rs
+//! use coolq_sdk_rust::testing::console::Console;
+//!
+//! fn main() {
+//!     Console::new()
+//!         .on_private(my_plugin::on_private_msg_medium)
+//!         .on_group(my_plugin::on_group_msg_medium)
+//!         .run();
+//! }
+//! ```
+//!
+//! ## 脚本
+//!
+//! 脚本中`>`开头的行为输入，`<`开头的行为期望的输出，`#`开头的行为注释。
+//! 每次输入后的全部输出都需要写出来，否则会失败。
+//! ```ignore
+//! #[test]
+//! fn welcome() {
+//!     Console::new().on_group(my_plugin::on_group_msg_medium).assert_script(r#"
+//!         > /group 123456
+//!         > hello
+//!         < [group 123456] @10001 你好
+//!     "#);
+//! }
+//! ```
+//! 期望的输出是渲染后的文字，如`[CQ:at,qq=10001]`应该写为`@10001`。
+
+use std::{
+    io::{self, BufRead, Write},
+    os::raw::c_char,
+};
+
+use crate::{
+    events::EventResult,
+    targets::cqcode::{self, CQCode},
+    testing::{ApiCall, Simulator, Target},
+};
+
+pub type PrivateListener = extern "system" fn(i32, i32, i64, *const c_char, i32) -> i32;
+pub type GroupListener =
+    extern "system" fn(i32, i32, i64, i64, *const c_char, *const c_char, i32) -> i32;
+pub type DiscussListener = extern "system" fn(i32, i32, i64, i64, *const c_char, i32) -> i32;
+
+pub struct Console {
+    sim: Simulator,
+    private: Vec<PrivateListener>,
+    group: Vec<GroupListener>,
+    discuss: Vec<DiscussListener>,
+    user_id: i64,
+    target: Target,
+    /// 已经输出过的api调用
+    handled: usize,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Console::new()
+    }
+}
+
+impl Console {
+    /// 默认以10001的身份私聊
+    pub fn new() -> Console {
+        Console {
+            sim: Simulator::new(),
+            private: Vec::new(),
+            group: Vec::new(),
+            discuss: Vec::new(),
+            user_id: 10001,
+            target: Target::Private(10001),
+            handled: 0,
+        }
+    }
+
+    /// 用于添加群、群员等信息
+    pub fn simulator(&self) -> &Simulator {
+        &self.sim
+    }
+
+    /// 按注册顺序调用，有listener拦截时不再调用后面的
+    pub fn on_private(&mut self, listener: PrivateListener) -> &mut Self {
+        self.private.push(listener);
+        self
+    }
+
+    pub fn on_group(&mut self, listener: GroupListener) -> &mut Self {
+        self.group.push(listener);
+        self
+    }
+
+    pub fn on_discuss(&mut self, listener: DiscussListener) -> &mut Self {
+        self.discuss.push(listener);
+        self
+    }
+
+    /// 处理一行输入，返回插件的输出
+    pub fn input(&mut self, line: &str) -> Result<Vec<String>, String> {
+        let line = line.trim();
+        if line.starts_with('/') {
+            self.command(line)?;
+        } else if !line.is_empty() {
+            self.send(line);
+        }
+        Ok(self.output())
+    }
+
+    fn command(&mut self, line: &str) -> Result<(), String> {
+        let mut args = line.split_whitespace();
+        let command = args.next().unwrap_or_default();
+        let mut id = || {
+            args.next()
+                .and_then(|id| id.parse::<i64>().ok())
+                .ok_or_else(|| format!("{} needs a number.", command))
+        };
+        match command {
+            "/user" => {
+                self.user_id = id()?;
+                if let Target::Private(_) = self.target {
+                    self.target = Target::Private(self.user_id);
+                }
+            },
+            "/group" => self.target = Target::Group(id()?),
+            "/discuss" => self.target = Target::Discuss(id()?),
+            "/private" => self.target = Target::Private(self.user_id),
+            "/login" => {
+                self.sim.login_qq(id()?);
+            },
+            _ => return Err(format!("unknown command `{}`.", command)),
+        }
+        Ok(())
+    }
+
+    fn send(&mut self, msg: &str) {
+        let sim = &self.sim;
+        match self.target {
+            Target::Private(user_id) => {
+                let event = sim.private_message(user_id, msg);
+                for f in &self.private {
+                    if event.clone().call(*f) == EventResult::Block {
+                        break;
+                    }
+                }
+            },
+            Target::Group(group_id) => {
+                let event = sim.group_message(group_id, self.user_id, msg);
+                for f in &self.group {
+                    if event.clone().call(*f) == EventResult::Block {
+                        break;
+                    }
+                }
+            },
+            Target::Discuss(discuss_id) => {
+                let event = sim.discuss_message(discuss_id, self.user_id, msg);
+                for f in &self.discuss {
+                    if event.clone().call(*f) == EventResult::Block {
+                        break;
+                    }
+                }
+            },
+        }
+    }
+
+    fn output(&mut self) -> Vec<String> {
+        let calls = self.sim.calls();
+        let output = calls
+            .get(self.handled..)
+            .unwrap_or_default()
+            .iter()
+            .filter_map(render_call)
+            .collect();
+        self.handled = calls.len();
+        output
+    }
+
+    fn prompt(&self) -> String {
+        match self.target {
+            Target::Private(_) => format!("{}> ", self.user_id),
+            Target::Group(group_id) => format!("{}@group {}> ", self.user_id, group_id),
+            Target::Discuss(discuss_id) => format!("{}@discuss {}> ", self.user_id, discuss_id),
+        }
+    }
+
+    /// 从标准输入读取，直到`/quit`或EOF
+    pub fn run(&mut self) {
+        let stdin = io::stdin();
+        let mut lines = stdin.lock().lines();
+        loop {
+            print!("{}", self.prompt());
+            io::stdout().flush().ok();
+            let line = match lines.next() {
+                Some(Ok(line)) => line,
+                _ => break,
+            };
+            if line.trim() == "/quit" {
+                break;
+            }
+            match self.input(&line) {
+                Ok(output) => output.iter().for_each(|line| println!("{}", line)),
+                Err(err) => println!("error: {}", err),
+            }
+        }
+    }
+
+    /// 运行脚本，输出与期望不符时返回错误
+    pub fn run_script(&mut self, script: &str) -> Result<(), String> {
+        let mut pending: Vec<String> = Vec::new();
+        let unexpected = |pending: &[String], line: usize| {
+            format!("line {}: unexpected output `{}`.", line, pending[0])
+        };
+        for (i, line) in script.lines().enumerate() {
+            let n = i + 1;
+            let line = line.trim();
+            if let Some(input) = line.strip_prefix('>') {
+                if !pending.is_empty() {
+                    return Err(unexpected(&pending, n));
+                }
+                pending = self
+                    .input(input)
+                    .map_err(|err| format!("line {}: {}", n, err))?;
+            } else if let Some(expected) = line.strip_prefix('<') {
+                let expected = expected.trim();
+                if pending.is_empty() {
+                    return Err(format!("line {}: expected `{}`, found nothing.", n, expected));
+                }
+                let actual = pending.remove(0);
+                if actual != expected {
+                    return Err(format!("line {}: expected `{}`, found `{}`.", n, expected, actual));
+                }
+            } else if !line.is_empty() && !line.starts_with('#') {
+                return Err(format!("line {}: should start with `>`, `<` or `#`.", n));
+            }
+        }
+        if pending.is_empty() {
+            Ok(())
+        } else {
+            Err(unexpected(&pending, script.lines().count()))
+        }
+    }
+
+    pub fn assert_script(&mut self, script: &str) {
+        if let Err(err) = self.run_script(script) {
+            panic!("{}", err);
+        }
+    }
+}
+
+fn render_call(call: &ApiCall) -> Option<String> {
+    if let Some(sent) = call.sent() {
+        let target = match sent.target {
+            Target::Private(user_id) => format!("private {}", user_id),
+            Target::Group(group_id) => format!("group {}", group_id),
+            Target::Discuss(discuss_id) => format!("discuss {}", discuss_id),
+        };
+        Some(format!("[{}] {}", target, render(&sent.msg)))
+    } else if let Some(action) = call.action() {
+        Some(format!("* {:?}", action))
+    } else {
+        // 只显示警告以上的日志
+        call.log()
+            .filter(|log| log.priority >= 20)
+            .map(|log| format!("! [{}] {}", log.tag, log.msg))
+    }
+}
+
+/// 把cq码转换为可读的文字
+pub fn render(msg: &str) -> String {
+    cqcode::replace(msg, |code| match code {
+        CQCode::At(qq) => format!("@{}", qq),
+        CQCode::AtAll() => "@全体成员".to_owned(),
+        CQCode::Face(id) | CQCode::Bface(id) | CQCode::Sface(id) => format!("[表情{}]", id),
+        CQCode::Emoji(id) => std::char::from_u32(id as u32)
+            .map_or_else(|| format!("[emoji{}]", id), |c| c.to_string()),
+        CQCode::Image(file) => format!("[图片 {}]", file),
+        CQCode::Record(file, _) => format!("[语音 {}]", file),
+        CQCode::Rps(_) => "[猜拳]".to_owned(),
+        CQCode::Dice(_) => "[骰子]".to_owned(),
+        CQCode::Shake() => "[戳一戳]".to_owned(),
+        CQCode::Anonymous(_) => String::new(),
+        CQCode::Location(_, _, title, _) => format!("[位置 {}]", title),
+        CQCode::Sign(_, title, _) => format!("[签到 {}]", title),
+        CQCode::Music(..) | CQCode::MusicCustom(..) => "[音乐]".to_owned(),
+        CQCode::Share(url, title, _, _) => format!("[分享 {} {}]", title, url),
+        CQCode::Contact(id, _) => format!("[名片 {}]", id),
+        CQCode::Unknown(code) => code,
+    })
+    .replace("&#91;", "[")
+    .replace("&#93;", "]")
+    .replace("&#44;", ",")
+    .replace("&amp;", "&")
+}
@@ -0,0 +1,114 @@
+//! 按酷q的格式编码群、群员、用户等信息，是`targets`中`decode`的逆过程
+//!
+//! 模拟时字符串不转换编码，直接写入utf8。
+
+use byteorder::{BigEndian, WriteBytesExt};
+
+use crate::targets::{
+    group::{Group, GroupMember, GroupRole},
+    user::{FriendInfo, User, UserSex},
+    File,
+};
+
+fn write_string(b: &mut Vec<u8>, s: &str) {
+    b.write_i16::<BigEndian>(s.len() as i16).unwrap();
+    b.extend(s.as_bytes());
+}
+
+fn sex(sex: &UserSex) -> i32 {
+    match sex {
+        UserSex::Male => 0,
+        UserSex::Female => 1,
+        UserSex::Unknown => 255,
+    }
+}
+
+fn role(role: &GroupRole) -> i32 {
+    match role {
+        GroupRole::Member => 1,
+        GroupRole::Admin => 2,
+        GroupRole::Owner => 3,
+    }
+}
+
+/// 多个对象，`get_group_list`等api的返回值
+pub fn multi_object(objects: &[Vec<u8>]) -> String {
+    let mut b = Vec::new();
+    b.write_i32::<BigEndian>(objects.len() as i32).unwrap();
+    for object in objects {
+        b.write_i16::<BigEndian>(object.len() as i16).unwrap();
+        b.extend(object);
+    }
+    base64::encode(&b)
+}
+
+pub fn group(group: &Group) -> String {
+    let mut b = Vec::new();
+    b.write_i64::<BigEndian>(group.group_id).unwrap();
+    write_string(&mut b, &group.group_name);
+    b.write_i32::<BigEndian>(group.member_count).unwrap();
+    b.write_i32::<BigEndian>(group.max_member_count).unwrap();
+    base64::encode(&b)
+}
+
+/// 群列表中的群，只有群号和群名
+pub fn group_small(group: &Group) -> Vec<u8> {
+    let mut b = Vec::new();
+    b.write_i64::<BigEndian>(group.group_id).unwrap();
+    write_string(&mut b, &group.group_name);
+    b
+}
+
+pub fn group_member(gm: &GroupMember) -> String {
+    let mut b = Vec::new();
+    b.write_i64::<BigEndian>(gm.group_id).unwrap();
+    b.write_i64::<BigEndian>(gm.user_id).unwrap();
+    write_string(&mut b, &gm.nickname);
+    write_string(&mut b, &gm.card);
+    b.write_i32::<BigEndian>(sex(&gm.sex)).unwrap();
+    b.write_i32::<BigEndian>(gm.age).unwrap();
+    write_string(&mut b, &gm.area);
+    b.write_i32::<BigEndian>(gm.join_time).unwrap();
+    b.write_i32::<BigEndian>(gm.last_sent_time).unwrap();
+    write_string(&mut b, &gm.level);
+    b.write_i32::<BigEndian>(role(&gm.role)).unwrap();
+    b.write_i32::<BigEndian>(gm.unfriendly as i32).unwrap();
+    write_string(&mut b, &gm.title);
+    b.write_i32::<BigEndian>(gm.title_expire_time).unwrap();
+    b.write_i32::<BigEndian>(gm.card_changeable as i32).unwrap();
+    base64::encode(&b)
+}
+
+pub fn user(user: &User) -> String {
+    let mut b = Vec::new();
+    b.write_i64::<BigEndian>(user.user_id).unwrap();
+    write_string(&mut b, &user.nickname);
+    b.write_i32::<BigEndian>(sex(&user.sex)).unwrap();
+    b.write_i32::<BigEndian>(user.age).unwrap();
+    base64::encode(&b)
+}
+
+pub fn friend(friend: &FriendInfo) -> Vec<u8> {
+    let mut b = Vec::new();
+    b.write_i64::<BigEndian>(friend.user_id).unwrap();
+    write_string(&mut b, &friend.nickname);
+    write_string(&mut b, &friend.remark);
+    b
+}
+
+pub fn file(file: &File) -> String {
+    let mut b = Vec::new();
+    write_string(&mut b, &file.id);
+    write_string(&mut b, &file.name);
+    b.write_i64::<BigEndian>(file.size).unwrap();
+    b.write_i64::<BigEndian>(file.busid).unwrap();
+    base64::encode(&b)
+}
+
+/// 匿名消息的`anonymous_flag`
+pub fn anonymous(user_id: i64, name: &str) -> String {
+    let mut b = Vec::new();
+    b.write_i64::<BigEndian>(user_id).unwrap();
+    write_string(&mut b, name);
+    base64::encode(&b)
+}
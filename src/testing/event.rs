@@ -0,0 +1,461 @@
+use std::{
+    ffi::CString,
+    os::raw::c_char,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use crate::{
+    events::*,
+    targets::File,
+    testing::{encode, is_active, state, Simulator},
+};
+
+/// 事件的参数转换为酷q传入的类型
+#[doc(hidden)]
+pub trait EventArg: Sized {
+    /// 调用期间需要保存的值
+    type Raw;
+    type Arg;
+
+    fn raw(&self) -> Self::Raw;
+    fn arg(raw: &Self::Raw) -> Self::Arg;
+    fn from_raw(value: &RawValue) -> Option<Self>;
+}
+
+macro_rules! event_arg_copy {
+    ($($t: ty),*) => {
+        $(
+            impl EventArg for $t {
+                type Raw = $t;
+                type Arg = $t;
+
+                fn raw(&self) -> $t {
+                    *self
+                }
+
+                fn arg(raw: &$t) -> $t {
+                    *raw
+                }
+
+                fn from_raw(value: &RawValue) -> Option<$t> {
+                    value.as_int().map(|i| i as $t)
+                }
+            }
+        )*
+    };
+}
+
+event_arg_copy!(i32, i64);
+
+macro_rules! event_arg_sub_type {
+    ($($t: ty),*) => {
+        $(
+            impl EventArg for $t {
+                type Raw = i32;
+                type Arg = i32;
+
+                fn raw(&self) -> i32 {
+                    i32::from(*self)
+                }
+
+                fn arg(raw: &i32) -> i32 {
+                    *raw
+                }
+
+                fn from_raw(value: &RawValue) -> Option<$t> {
+                    value.as_int().map(|i| <$t>::from(i as i32))
+                }
+            }
+        )*
+    };
+}
+
+event_arg_sub_type!(
+    PrivateMessageType,
+    GroupMessageType,
+    DiscussMessageType,
+    GroupUploadType,
+    GroupAdminType,
+    GroupMemberDecreaseType,
+    GroupMemberIncreaseType,
+    GroupBanType,
+    FriendAddType,
+    AddFriendRequestType,
+    AddGroupRequestType
+);
+
+impl EventArg for String {
+    type Raw = CString;
+    type Arg = *const c_char;
+
+    fn raw(&self) -> CString {
+        CString::new(self.as_str()).expect("event string cannot contain \\0")
+    }
+
+    fn arg(raw: &CString) -> *const c_char {
+        raw.as_ptr()
+    }
+
+    fn from_raw(value: &RawValue) -> Option<String> {
+        value.as_str().map(str::to_owned)
+    }
+}
+
+impl EventArg for File {
+    type Raw = CString;
+    type Arg = *const c_char;
+
+    fn raw(&self) -> CString {
+        CString::new(encode::file(self)).unwrap()
+    }
+
+    fn arg(raw: &CString) -> *const c_char {
+        raw.as_ptr()
+    }
+
+    fn from_raw(value: &RawValue) -> Option<File> {
+        File::decode(&base64::decode(value.as_str()?).ok()?).ok()
+    }
+}
+
+/// 可以用录制的原始参数构造的事件，用于[回放](crate::testing::replay)
+pub trait RawEvent: Sized {
+    /// 不带优先级的函数名，如`on_group_msg`
+    const FUNCTION: &'static str;
+    /// `#[listener]`生成的函数的类型
+    type Listener: Copy + 'static;
+
+    /// 参数的数量或类型不对时返回None
+    fn from_raw(args: &[RawValue]) -> Option<Self>;
+    fn call_listener(self, listener: Self::Listener) -> EventResult;
+}
+
+macro_rules! gen_event_builder {
+    ($($(#[$doc: meta])* $name: ident($function: literal, $event: ident) { $($field: ident: $t: ty),* }),*) => {
+        $(
+            $(#[$doc])*
+            #[derive(Debug, Clone)]
+            pub struct $name {
+                $(pub $field: $t),*
+            }
+
+            impl $name {
+                $(
+                    pub fn $field(mut self, $field: impl Into<$t>) -> Self {
+                        self.$field = $field.into();
+                        self
+                    }
+                )*
+
+                /// 调用`#[listener]`生成的函数，如`on_group_msg_medium`
+                pub fn call(self, listener: extern "system" fn($(<$t as EventArg>::Arg),*) -> i32) -> EventResult {
+                    assert!(is_active(), "Simulator is not running.");
+                    $(let $field = EventArg::raw(&self.$field);)*
+                    EventResult::from(listener($(<$t as EventArg>::arg(&$field)),*))
+                }
+
+                /// 调用`listen_all`注册的回调，与酷q调用`#[coolq_sdk_rust::main]`导出的`catch_all_*`相同
+                pub fn catch_all(&self) {
+                    assert!(is_active(), "Simulator is not running.");
+                    $(let $field = EventArg::raw(&self.$field);)*
+                    dispatch_all($event::new($(<$t as EventArg>::arg(&$field)),*));
+                }
+
+                /// 把事件分发给注册的模块，与酷q调用`#[coolq_sdk_rust::main(modules(..))]`导出的`module_*`相同
+                pub fn module(&self) -> EventResult {
+                    assert!(is_active(), "Simulator is not running.");
+                    $(let $field = EventArg::raw(&self.$field);)*
+                    EventResult::from(dispatch(
+                        $event::new($(<$t as EventArg>::arg(&$field)),*),
+                        |event| crate::module::dispatch(event.into()),
+                    ))
+                }
+            }
+
+            impl RawEvent for $name {
+                const FUNCTION: &'static str = $function;
+                type Listener = extern "system" fn($(<$t as EventArg>::Arg),*) -> i32;
+
+                #[allow(unused_mut, unused_variables)]
+                fn from_raw(args: &[RawValue]) -> Option<Self> {
+                    let mut args = args.iter();
+                    let event = $name {
+                        $($field: EventArg::from_raw(args.next()?)?),*
+                    };
+                    match args.next() {
+                        Some(_) => None,
+                        None => Some(event),
+                    }
+                }
+
+                fn call_listener(self, listener: Self::Listener) -> EventResult {
+                    self.call(listener)
+                }
+            }
+        )*
+    };
+}
+
+gen_event_builder!(
+    /// 酷q启动，对应`on_start_*`
+    Start("on_start", StartEvent) {},
+    /// 酷q退出，对应`on_exit`
+    Exit("on_exit", ExitEvent) {},
+    /// 插件停用，对应`on_disable`
+    Disable("on_disable", DisableEvent) {},
+    /// 对应`on_private_msg_*`
+    PrivateMessage("on_private_msg", PrivateMessageEvent) {
+        sub_type: PrivateMessageType,
+        msg_id: i32,
+        user_id: i64,
+        msg: String,
+        font: i32
+    },
+    /// 对应`on_group_msg_*`，匿名消息的`anonymous_flag`可以用[`encode::anonymous`]生成
+    GroupMessage("on_group_msg", GroupMessageEvent) {
+        sub_type: GroupMessageType,
+        msg_id: i32,
+        group_id: i64,
+        user_id: i64,
+        anonymous_flag: String,
+        msg: String,
+        font: i32
+    },
+    /// 对应`on_discuss_msg_*`
+    DiscussMessage("on_discuss_msg", DiscussMessageEvent) {
+        sub_type: DiscussMessageType,
+        msg_id: i32,
+        discuss_id: i64,
+        user_id: i64,
+        msg: String,
+        font: i32
+    },
+    /// 对应`on_group_upload_*`
+    GroupUpload("on_group_upload", GroupUploadEvent) {
+        sub_type: GroupUploadType,
+        send_time: i32,
+        group_id: i64,
+        user_id: i64,
+        file: File
+    },
+    /// 对应`on_group_admin_*`
+    GroupAdmin("on_group_admin", GroupAdminEvent) {
+        sub_type: GroupAdminType,
+        send_time: i32,
+        group_id: i64,
+        user_id: i64
+    },
+    /// 对应`on_group_member_decrease_*`
+    GroupMemberDecrease("on_group_member_decrease", GroupMemberDecreaseEvent) {
+        sub_type: GroupMemberDecreaseType,
+        send_time: i32,
+        group_id: i64,
+        operate_user_id: i64,
+        being_operate_user_id: i64
+    },
+    /// 对应`on_group_member_increase_*`
+    GroupMemberIncrease("on_group_member_increase", GroupMemberIncreaseEvent) {
+        sub_type: GroupMemberIncreaseType,
+        send_time: i32,
+        group_id: i64,
+        operate_user_id: i64,
+        being_operate_user_id: i64
+    },
+    /// 对应`on_group_ban_*`
+    GroupBan("on_group_ban", GroupBanEvent) {
+        sub_type: GroupBanType,
+        send_time: i32,
+        group_id: i64,
+        operate_user_id: i64,
+        being_operate_user_id: i64,
+        time: i64
+    },
+    /// 对应`on_friend_add_*`
+    FriendAdd("on_friend_add", FriendAddEvent) {
+        sub_type: FriendAddType,
+        send_time: i32,
+        user_id: i64
+    },
+    /// 对应`on_add_friend_request_*`
+    AddFriendRequest("on_add_friend_request", AddFriendRequestEvent) {
+        sub_type: AddFriendRequestType,
+        send_time: i32,
+        user_id: i64,
+        msg: String,
+        flag: String
+    },
+    /// 对应`on_add_group_request_*`
+    AddGroupRequest("on_add_group_request", AddGroupRequestEvent) {
+        sub_type: AddGroupRequestType,
+        send_time: i32,
+        group_id: i64,
+        user_id: i64,
+        msg: String,
+        flag: String
+    }
+);
+
+fn now() -> i32 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |d| d.as_secs() as i32)
+}
+
+/// 消息id和请求的flag共用一个计数
+fn next_id() -> i32 {
+    let mut state = state();
+    state.msg_id += 1;
+    state.msg_id
+}
+
+/// 构造事件，其他字段可以用同名方法修改
+impl Simulator {
+    pub fn start(&self) -> Start {
+        Start {}
+    }
+
+    pub fn exit(&self) -> Exit {
+        Exit {}
+    }
+
+    pub fn disable(&self) -> Disable {
+        Disable {}
+    }
+
+    /// 好友私聊
+    pub fn private_message(&self, user_id: i64, msg: impl ToString) -> PrivateMessage {
+        PrivateMessage {
+            sub_type: PrivateMessageType::Friend,
+            msg_id: next_id(),
+            user_id,
+            msg: msg.to_string(),
+            font: 0,
+        }
+    }
+
+    pub fn group_message(&self, group_id: i64, user_id: i64, msg: impl ToString) -> GroupMessage {
+        GroupMessage {
+            sub_type: GroupMessageType::Normal,
+            msg_id: next_id(),
+            group_id,
+            user_id,
+            anonymous_flag: String::new(),
+            msg: msg.to_string(),
+            font: 0,
+        }
+    }
+
+    pub fn discuss_message(
+        &self, discuss_id: i64, user_id: i64, msg: impl ToString,
+    ) -> DiscussMessage {
+        DiscussMessage {
+            sub_type: DiscussMessageType::Normal,
+            msg_id: next_id(),
+            discuss_id,
+            user_id,
+            msg: msg.to_string(),
+            font: 0,
+        }
+    }
+
+    pub fn group_upload(&self, group_id: i64, user_id: i64, file: File) -> GroupUpload {
+        GroupUpload {
+            sub_type: GroupUploadType::Upload,
+            send_time: now(),
+            group_id,
+            user_id,
+            file,
+        }
+    }
+
+    pub fn group_admin(&self, group_id: i64, user_id: i64, set: bool) -> GroupAdmin {
+        GroupAdmin {
+            sub_type: if set {
+                GroupAdminType::Set
+            } else {
+                GroupAdminType::Unset
+            },
+            send_time: now(),
+            group_id,
+            user_id,
+        }
+    }
+
+    /// `operate_user_id`与`being_operate_user_id`相同时为主动退出，否则为被踢出
+    pub fn group_member_decrease(
+        &self, group_id: i64, operate_user_id: i64, being_operate_user_id: i64,
+    ) -> GroupMemberDecrease {
+        GroupMemberDecrease {
+            sub_type: if operate_user_id == being_operate_user_id {
+                GroupMemberDecreaseType::Quit
+            } else {
+                GroupMemberDecreaseType::Kick
+            },
+            send_time: now(),
+            group_id,
+            operate_user_id,
+            being_operate_user_id,
+        }
+    }
+
+    pub fn group_member_increase(
+        &self, group_id: i64, operate_user_id: i64, being_operate_user_id: i64,
+    ) -> GroupMemberIncrease {
+        GroupMemberIncrease {
+            sub_type: GroupMemberIncreaseType::Approve,
+            send_time: now(),
+            group_id,
+            operate_user_id,
+            being_operate_user_id,
+        }
+    }
+
+    /// `time`为0时为解除禁言
+    pub fn group_ban(
+        &self, group_id: i64, operate_user_id: i64, being_operate_user_id: i64, time: i64,
+    ) -> GroupBan {
+        GroupBan {
+            sub_type: if time == 0 {
+                GroupBanType::Unban
+            } else {
+                GroupBanType::Ban
+            },
+            send_time: now(),
+            group_id,
+            operate_user_id,
+            being_operate_user_id,
+            time,
+        }
+    }
+
+    pub fn friend_add(&self, user_id: i64) -> FriendAdd {
+        FriendAdd {
+            sub_type: FriendAddType::Added,
+            send_time: now(),
+            user_id,
+        }
+    }
+
+    pub fn add_friend_request(&self, user_id: i64, msg: impl ToString) -> AddFriendRequest {
+        AddFriendRequest {
+            sub_type: AddFriendRequestType::Request,
+            send_time: now(),
+            user_id,
+            msg: msg.to_string(),
+            flag: format!("flag{}", next_id()),
+        }
+    }
+
+    pub fn add_group_request(
+        &self, group_id: i64, user_id: i64, msg: impl ToString,
+    ) -> AddGroupRequest {
+        AddGroupRequest {
+            sub_type: AddGroupRequestType::Application,
+            send_time: now(),
+            group_id,
+            user_id,
+            msg: msg.to_string(),
+            flag: format!("flag{}", next_id()),
+        }
+    }
+}
@@ -0,0 +1,525 @@
+//! 在没有酷q的环境下测试插件
+//!
+//! [`Simulator`]存在期间，全部[api](crate::api)调用都会被记录下来并返回模拟的结果，
+//! 可以用它构造任意事件，调用`#[listener]`生成的函数（如`on_group_msg_medium`），然后检查插件发送的消息和执行的管理操作。
+//!
+//! 测试时插件需要同时编译为`rlib`：`crate-type = ["cdylib", "rlib"]`。
+//! 同一时间只能有一个[`Simulator`]，其他线程的[`Simulator::new`]会等待它被drop。
+//!
+//! 想直接和插件对话，或者用对话脚本做回归测试，请使用[`console`]。
+//! 开启`recorder` feature时，可以用[`replay`]回放线上录制的事件。
+//!
+//! # Examples
+//! ```ignore
+//! use coolq_sdk_rust::testing::{Action, Simulator, Target};
+//!
+//! #[test]
+//! fn welcome() {
+//!     let sim = Simulator::new();
+//!     sim.group_member_increase(123456, 1, 10001).call(my_plugin::on_group_member_increase_medium);
+//!     assert_eq!(sim.sent()[0].target, Target::Group(123456));
+//!
+//!     sim.group_message(123456, 10001, "广告").call(my_plugin::on_group_msg_medium);
+//!     assert!(sim.actions().contains(&Action::Ban { group_id: 123456, user_id: 10001, time: 600 }));
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    ffi::{CStr, CString},
+    os::raw::c_char,
+    path::PathBuf,
+    ptr::null,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex, MutexGuard,
+    },
+};
+
+use crate::{
+    cache,
+    targets::{
+        group::{Group, GroupMember},
+        user::{FriendInfo, User},
+    },
+};
+
+pub mod console;
+pub mod encode;
+mod event;
+#[cfg(feature = "recorder")]
+#[cfg_attr(docsrs, doc(cfg(feature = "recorder")))]
+pub mod replay;
+
+pub use event::*;
+
+lazy_static! {
+    /// 保证同一时间只有一个Simulator
+    static ref Running: Mutex<()> = Mutex::new(());
+    static ref Mock: Mutex<State> = Mutex::new(State::default());
+}
+
+static Active: AtomicBool = AtomicBool::new(false);
+
+/// api的参数和返回值
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Int(i64),
+    Bool(bool),
+    Str(String),
+}
+
+impl Value {
+    pub fn as_int(&self) -> i64 {
+        match self {
+            Value::Int(i) => *i,
+            Value::Bool(b) => *b as i64,
+            Value::Str(_) => 0,
+        }
+    }
+
+    pub fn as_str(&self) -> &str {
+        match self {
+            Value::Str(s) => s,
+            _ => "",
+        }
+    }
+}
+
+/// 一次api调用，`name`为[api](crate::api)中的函数名
+#[derive(Debug, Clone, PartialEq)]
+pub struct ApiCall {
+    pub name: String,
+    pub args: Vec<Value>,
+    /// 返回值，`None`表示调用失败
+    pub result: Option<Value>,
+}
+
+#[derive(Debug, Clone, Copy, Eq, PartialEq)]
+pub enum Target {
+    Private(i64),
+    Group(i64),
+    Discuss(i64),
+}
+
+/// 插件发送的消息
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct SentMessage {
+    pub target: Target,
+    pub msg: String,
+    pub msg_id: i32,
+}
+
+/// 插件执行的管理操作
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub enum Action {
+    DeleteMessage { msg_id: i32 },
+    Like { user_id: i64, times: i32 },
+    Kick { group_id: i64, user_id: i64, refuse_rejoin: bool },
+    Ban { group_id: i64, user_id: i64, time: i64 },
+    AnonymousBan { group_id: i64, flag: String, time: i64 },
+    WholeBan { group_id: i64, enable: bool },
+    SetAdmin { group_id: i64, user_id: i64, set_admin: bool },
+    SetSpecialTitle { group_id: i64, user_id: i64, title: String },
+    SetAnonymous { group_id: i64, enable: bool },
+    SetCard { group_id: i64, user_id: i64, card: String },
+    LeaveGroup { group_id: i64, dismiss: bool },
+    LeaveDiscuss { discuss_id: i64 },
+    FriendRequest { flag: String, approve: bool, comment: String },
+    GroupRequest { flag: String, request: i32, approve: bool, reason: String },
+}
+
+/// `add_log`输出的日志
+#[derive(Debug, Clone, Eq, PartialEq)]
+pub struct Log {
+    pub priority: i32,
+    pub tag: String,
+    pub msg: String,
+}
+
+type Handler = Arc<dyn Fn(&[Value]) -> Option<Value> + Send + Sync>;
+
+#[derive(Default)]
+struct State {
+    calls: Vec<ApiCall>,
+    handlers: HashMap<String, Handler>,
+    groups: Vec<Group>,
+    members: Vec<GroupMember>,
+    users: Vec<User>,
+    friends: Vec<FriendInfo>,
+    login_qq: i64,
+    login_nick: String,
+    app_directory: PathBuf,
+    msg_id: i32,
+    /// 返回给插件的字符串，保存到Simulator被drop
+    returned: Vec<CString>,
+}
+
+pub struct Simulator {
+    _running: MutexGuard<'static, ()>,
+}
+
+impl Simulator {
+    /// 开始模拟，会清空[缓存](crate::cache)
+    pub fn new() -> Simulator {
+        // 之前的测试panic不影响之后的测试
+        let running = Running.lock().unwrap_or_else(|err| err.into_inner());
+        *state() = State {
+            login_qq: 10000,
+            login_nick: "bot".to_owned(),
+            app_directory: std::env::temp_dir().join("coolq-sdk-rust-testing"),
+            ..State::default()
+        };
+        cache::clear();
+        Active.store(true, Ordering::SeqCst);
+        Simulator { _running: running }
+    }
+
+    pub fn login_qq(&self, user_id: i64) -> &Self {
+        state().login_qq = user_id;
+        self
+    }
+
+    pub fn login_nick(&self, nick: &str) -> &Self {
+        state().login_nick = nick.to_owned();
+        self
+    }
+
+    /// `get_app_directory`返回的目录，默认为临时目录下的`coolq-sdk-rust-testing`
+    pub fn app_directory(&self, dir: impl Into<PathBuf>) -> &Self {
+        state().app_directory = dir.into();
+        self
+    }
+
+    /// 添加群，`get_group_info`、`get_group_list`会返回它
+    pub fn add_group(&self, group: Group) -> &Self {
+        let mut state = state();
+        state.groups.retain(|g| g.group_id != group.group_id);
+        state.groups.push(group);
+        self
+    }
+
+    /// 添加群员，`get_group_member_info_v2`、`get_group_member_list`会返回它
+    pub fn add_member(&self, member: GroupMember) -> &Self {
+        let mut state = state();
+        state
+            .members
+            .retain(|m| (m.group_id, m.user_id) != (member.group_id, member.user_id));
+        state.members.push(member);
+        self
+    }
+
+    /// 添加用户，`get_stranger_info`会返回它
+    pub fn add_user(&self, user: User) -> &Self {
+        let mut state = state();
+        state.users.retain(|u| u.user_id != user.user_id);
+        state.users.push(user);
+        self
+    }
+
+    pub fn add_friend(&self, friend: FriendInfo) -> &Self {
+        state().friends.push(friend);
+        self
+    }
+
+    /// 自定义api的返回值，返回`None`表示调用失败
+    ///
+    /// `name`为[api](crate::api)中的函数名，如`send_group_msg`。
+    pub fn on_call(
+        &self, name: &str, handler: impl Fn(&[Value]) -> Option<Value> + Send + Sync + 'static,
+    ) -> &Self {
+        state().handlers.insert(name.to_owned(), Arc::new(handler));
+        self
+    }
+
+    /// 全部api调用
+    pub fn calls(&self) -> Vec<ApiCall> {
+        state().calls.clone()
+    }
+
+    /// 插件发送的消息
+    pub fn sent(&self) -> Vec<SentMessage> {
+        state()
+            .calls
+            .iter()
+            .filter_map(ApiCall::sent)
+            .collect()
+    }
+
+    /// 插件执行的管理操作
+    pub fn actions(&self) -> Vec<Action> {
+        state().calls.iter().filter_map(ApiCall::action).collect()
+    }
+
+    pub fn logs(&self) -> Vec<Log> {
+        state().calls.iter().filter_map(ApiCall::log).collect()
+    }
+
+    /// 清空记录的api调用
+    pub fn clear(&self) {
+        state().calls.clear();
+    }
+}
+
+impl Default for Simulator {
+    fn default() -> Self {
+        Simulator::new()
+    }
+}
+
+impl Drop for Simulator {
+    fn drop(&mut self) {
+        Active.store(false, Ordering::SeqCst);
+        cache::clear();
+    }
+}
+
+fn state() -> MutexGuard<'static, State> {
+    Mock.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+/// 是否正在模拟
+pub fn is_active() -> bool {
+    Active.load(Ordering::SeqCst)
+}
+
+impl ApiCall {
+    fn arg(&self, i: usize) -> &Value {
+        &self.args[i]
+    }
+
+    fn int(&self, i: usize) -> i64 {
+        self.arg(i).as_int()
+    }
+
+    fn string(&self, i: usize) -> String {
+        self.arg(i).as_str().to_owned()
+    }
+
+    pub fn sent(&self) -> Option<SentMessage> {
+        let target = match self.name.as_str() {
+            "send_private_msg" => Target::Private(self.int(0)),
+            "send_group_msg" => Target::Group(self.int(0)),
+            "send_discuss_msg" => Target::Discuss(self.int(0)),
+            _ => return None,
+        };
+        Some(SentMessage {
+            target,
+            msg: self.string(1),
+            msg_id: self.result.as_ref().map_or(-1, |result| result.as_int() as i32),
+        })
+    }
+
+    pub fn action(&self) -> Option<Action> {
+        Some(match self.name.as_str() {
+            "delete_msg" => Action::DeleteMessage { msg_id: self.int(0) as i32 },
+            "send_like_v2" => Action::Like { user_id: self.int(0), times: self.int(1) as i32 },
+            "set_group_kick" => Action::Kick {
+                group_id: self.int(0),
+                user_id: self.int(1),
+                refuse_rejoin: self.int(2) != 0,
+            },
+            "set_group_ban" => Action::Ban {
+                group_id: self.int(0),
+                user_id: self.int(1),
+                time: self.int(2),
+            },
+            "set_group_anonymous_ban" => Action::AnonymousBan {
+                group_id: self.int(0),
+                flag: self.string(1),
+                time: self.int(2),
+            },
+            "set_group_whole_ban" => Action::WholeBan {
+                group_id: self.int(0),
+                enable: self.int(1) != 0,
+            },
+            "set_group_admin" => Action::SetAdmin {
+                group_id: self.int(0),
+                user_id: self.int(1),
+                set_admin: self.int(2) != 0,
+            },
+            "set_group_special_title" => Action::SetSpecialTitle {
+                group_id: self.int(0),
+                user_id: self.int(1),
+                title: self.string(2),
+            },
+            "set_group_anonymous" => Action::SetAnonymous {
+                group_id: self.int(0),
+                enable: self.int(1) != 0,
+            },
+            "set_group_card" => Action::SetCard {
+                group_id: self.int(0),
+                user_id: self.int(1),
+                card: self.string(2),
+            },
+            "set_group_leave" => Action::LeaveGroup {
+                group_id: self.int(0),
+                dismiss: self.int(1) != 0,
+            },
+            "set_discuss_leave" => Action::LeaveDiscuss { discuss_id: self.int(0) },
+            "set_friend_add_request" => Action::FriendRequest {
+                flag: self.string(0),
+                approve: self.int(1) != 0,
+                comment: self.string(2),
+            },
+            "set_group_add_request_v2" => Action::GroupRequest {
+                flag: self.string(0),
+                request: self.int(1) as i32,
+                approve: self.int(2) != 0,
+                reason: self.string(3),
+            },
+            _ => return None,
+        })
+    }
+
+    pub fn log(&self) -> Option<Log> {
+        if self.name == "add_log" {
+            Some(Log {
+                priority: self.int(0) as i32,
+                tag: self.string(1),
+                msg: self.string(2),
+            })
+        } else {
+            None
+        }
+    }
+}
+
+/// api的参数转换为[`Value`]
+#[doc(hidden)]
+pub trait MockArg {
+    fn to_value(&self) -> Value;
+}
+
+macro_rules! mock_int {
+    ($($t: ty),*) => {
+        $(
+            impl MockArg for $t {
+                fn to_value(&self) -> Value {
+                    Value::Int(*self as i64)
+                }
+            }
+        )*
+    };
+}
+
+mock_int!(i32, i64);
+
+impl MockArg for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+}
+
+impl MockArg for *const c_char {
+    fn to_value(&self) -> Value {
+        if self.is_null() {
+            Value::Str(String::new())
+        } else {
+            Value::Str(unsafe { CStr::from_ptr(*self) }.to_string_lossy().into_owned())
+        }
+    }
+}
+
+/// [`Value`]转换为api的返回值，`None`表示调用失败
+#[doc(hidden)]
+pub trait MockReturn: Sized {
+    fn from_value(value: Option<Value>) -> Self;
+}
+
+impl MockReturn for i32 {
+    fn from_value(value: Option<Value>) -> Self {
+        value.map_or(-1, |value| value.as_int() as i32)
+    }
+}
+
+impl MockReturn for i64 {
+    fn from_value(value: Option<Value>) -> Self {
+        value.map_or(0, |value| value.as_int())
+    }
+}
+
+impl MockReturn for bool {
+    fn from_value(value: Option<Value>) -> Self {
+        value.is_some_and(|value| value.as_int() != 0)
+    }
+}
+
+impl MockReturn for *const c_char {
+    fn from_value(value: Option<Value>) -> Self {
+        match value {
+            Some(value) => {
+                let s = CString::new(value.as_str()).expect("cannot return string with \\0");
+                let ptr = s.as_ptr();
+                state().returned.push(s);
+                ptr
+            },
+            None => null(),
+        }
+    }
+}
+
+/// 由[api](crate::api)中的函数调用
+#[doc(hidden)]
+pub fn call<R: MockReturn>(name: &str, args: Vec<Value>) -> R {
+    let handler = state().handlers.get(name).cloned();
+    // 不持有锁，handler中可以调用api
+    let value = match handler {
+        Some(handler) => handler(&args),
+        None => default_return(name, &args),
+    };
+    state().calls.push(ApiCall {
+        name: name.to_owned(),
+        args,
+        result: value.clone(),
+    });
+    R::from_value(value)
+}
+
+fn default_return(name: &str, args: &[Value]) -> Option<Value> {
+    let mut state = state();
+    let int = |i: usize| args[i].as_int();
+    Some(match name {
+        "send_private_msg" | "send_group_msg" | "send_discuss_msg" => {
+            state.msg_id += 1;
+            Value::Int(state.msg_id as i64)
+        },
+        "get_group_info" => Value::Str(encode::group(
+            state.groups.iter().find(|g| g.group_id == int(0))?,
+        )),
+        "get_group_list" => Value::Str(encode::multi_object(
+            &state.groups.iter().map(encode::group_small).collect::<Vec<_>>(),
+        )),
+        "get_group_member_info_v2" => Value::Str(encode::group_member(
+            state
+                .members
+                .iter()
+                .find(|m| m.group_id == int(0) && m.user_id == int(1))?,
+        )),
+        "get_group_member_list" => Value::Str(encode::multi_object(
+            &state
+                .members
+                .iter()
+                .filter(|m| m.group_id == int(0))
+                .map(|m| encode::group_member(m).into_bytes())
+                .collect::<Vec<_>>(),
+        )),
+        "get_stranger_info" => Value::Str(encode::user(
+            state.users.iter().find(|u| u.user_id == int(0))?,
+        )),
+        "get_friend_list" => Value::Str(encode::multi_object(
+            &state.friends.iter().map(encode::friend).collect::<Vec<_>>(),
+        )),
+        "get_login_qq" => Value::Int(state.login_qq),
+        "get_login_nick" => Value::Str(state.login_nick.clone()),
+        "get_app_directory" => {
+            let mut dir = state.app_directory.to_string_lossy().into_owned();
+            dir.push(std::path::MAIN_SEPARATOR);
+            Value::Str(dir)
+        },
+        "can_send_image" | "can_send_record" => Value::Bool(true),
+        "get_cookies" | "get_cookies_v2" | "get_csrf_token" | "get_record_v2" | "get_image"
+        | "set_fatal" => Value::Str(String::new()),
+        _ => Value::Int(0),
+    })
+}
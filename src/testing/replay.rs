@@ -0,0 +1,260 @@
+//! 回放[录制](crate::recorder)的事件
+//!
+//! 按录制的顺序用原始参数调用注册的listener，插件调用api时返回录制时的结果：
+//! 先查找同一事件中函数名和参数都相同的调用，其次是之前的事件中的（线上可能命中了缓存），都没有时使用[`Simulator`]的默认值。
+//!
+//! 回放结束后比较每个事件中发送消息、管理操作、日志等调用，返回与录制时不一致的事件，
+//! `get_*`、`can_*`这类只读的调用不参与比较。没有注册listener的事件会被跳过。
+//!
+//! 需要同时开启`testing`和`recorder` feature。
+//!
+//! # Examples
+//! ```ignore
+//! use coolq_sdk_rust::testing::{replay::Replayer, GroupMessage, PrivateMessage};
+//!
+//! #[test]
+//! fn issue_42() {
+//!     Replayer::open("tests/recordings/issue_42.jsonl")
+//!         .unwrap()
+//!         .on::<GroupMessage>(my_plugin::on_group_msg_medium)
+//!         .on::<PrivateMessage>(my_plugin::on_private_msg_medium)
+//!         .assert_same();
+//! }
+//! ```
+
+use std::{
+    collections::HashMap,
+    fmt::{self, Display, Formatter},
+    io,
+    path::Path,
+    sync::{Arc, Mutex},
+};
+
+use crate::{
+    events::{EventResult, RawValue},
+    recorder::{self, Record},
+    testing::{default_return, ApiCall, RawEvent, Simulator, Value},
+};
+
+type Listener = Box<dyn Fn(&[RawValue]) -> Option<EventResult>>;
+
+/// 与录制时不一致的事件
+#[derive(Debug, Clone, PartialEq)]
+pub struct Mismatch {
+    /// 事件在录制中的序号
+    pub index: usize,
+    pub function: String,
+    /// 录制时解析后的事件
+    pub event: String,
+    pub expected: Vec<ApiCall>,
+    pub actual: Vec<ApiCall>,
+}
+
+impl Display for Mismatch {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        let calls = |calls: &[ApiCall]| {
+            calls
+                .iter()
+                .map(|call| {
+                    let args = call.args.iter().map(|arg| format!("{:?}", arg));
+                    format!("{}({})", call.name, args.collect::<Vec<_>>().join(", "))
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        write!(
+            f,
+            "record {} ({}): expected [{}], found [{}]",
+            self.index,
+            self.function,
+            calls(&self.expected),
+            calls(&self.actual)
+        )
+    }
+}
+
+/// 录制的api调用，回放时用于返回结果
+#[derive(Default)]
+struct Responses {
+    /// 当前事件中还没有用过的调用
+    current: Vec<ApiCall>,
+    /// 之前的全部调用
+    history: Vec<ApiCall>,
+}
+
+impl Responses {
+    fn take(&mut self, name: &str, args: &[Value]) -> Option<Option<Value>> {
+        let matches = |call: &ApiCall| call.name == name && call.args == args;
+        if let Some(i) = self.current.iter().position(matches) {
+            return Some(self.current.remove(i).result);
+        }
+        self.history.iter().rev().find(|call| matches(call)).map(|call| call.result.clone())
+    }
+}
+
+pub struct Replayer {
+    sim: Simulator,
+    records: Vec<Record>,
+    listeners: HashMap<&'static str, Vec<Listener>>,
+}
+
+impl Replayer {
+    pub fn new(records: Vec<Record>) -> Replayer {
+        Replayer {
+            sim: Simulator::new(),
+            records,
+            listeners: HashMap::new(),
+        }
+    }
+
+    /// 读取录制的文件
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Replayer> {
+        Ok(Replayer::new(recorder::read(path)?))
+    }
+
+    /// 回放结束后可以检查全部api调用
+    pub fn simulator(&self) -> &Simulator {
+        &self.sim
+    }
+
+    /// 注册listener，如`.on::<GroupMessage>(on_group_msg_medium)`
+    ///
+    /// 同一事件按注册顺序调用，有listener拦截时不再调用后面的。
+    pub fn on<E: RawEvent>(&mut self, listener: E::Listener) -> &mut Self {
+        self.listeners
+            .entry(E::FUNCTION)
+            .or_default()
+            .push(Box::new(move |args| {
+                E::from_raw(args).map(|event| event.call_listener(listener))
+            }));
+        self
+    }
+
+    /// 回放全部事件，返回与录制时不一致的事件
+    ///
+    /// # Panics
+    /// 录制的参数与事件不符时panic
+    pub fn run(&mut self) -> Vec<Mismatch> {
+        let responses = Arc::new(Mutex::new(Responses::default()));
+        let mut names = Vec::new();
+        for record in &self.records {
+            if let Record::Call { function, .. } = record {
+                if !names.contains(function) {
+                    names.push(function.clone());
+                }
+            }
+        }
+        for name in names {
+            let responses = responses.clone();
+            let api = name.clone();
+            self.sim.on_call(&name, move |args| {
+                let recorded = responses
+                    .lock()
+                    .expect("cannot lock Responses")
+                    .take(&api, args);
+                recorded.unwrap_or_else(|| default_return(&api, args))
+            });
+        }
+
+        let mut mismatches = Vec::new();
+        let mut i = 0;
+        while i < self.records.len() {
+            let (function, args, event) = match &self.records[i] {
+                Record::Event(record) => (&record.function, &record.args, &record.event),
+                Record::Call { .. } => {
+                    // 第一个事件之前的调用
+                    let call = to_call(&self.records[i]);
+                    responses.lock().expect("cannot lock Responses").history.push(call);
+                    i += 1;
+                    continue;
+                },
+            };
+            let expected = self.records[i + 1..]
+                .iter()
+                .take_while(|record| matches!(record, Record::Call { .. }))
+                .map(to_call)
+                .collect::<Vec<_>>();
+            let index = i;
+            i += expected.len() + 1;
+
+            let listeners = match self.listeners.get(function.as_str()) {
+                Some(listeners) => listeners,
+                None => continue,
+            };
+            responses.lock().expect("cannot lock Responses").current = expected.clone();
+            let start = self.sim.calls().len();
+            for listener in listeners {
+                let result = listener(args).unwrap_or_else(|| {
+                    panic!("record {}: invalid arguments for {}.", index, function)
+                });
+                if result == EventResult::Block {
+                    break;
+                }
+            }
+
+            let actual = self.sim.calls().split_off(start);
+            {
+                let mut responses = responses.lock().expect("cannot lock Responses");
+                responses.current.clear();
+                responses.history.extend(expected.iter().cloned());
+            }
+            let (expected, actual) = (side_effects(expected), side_effects(actual));
+            let same = expected.len() == actual.len()
+                && expected
+                    .iter()
+                    .zip(&actual)
+                    .all(|(e, a)| e.name == a.name && e.args == a.args);
+            if !same {
+                mismatches.push(Mismatch {
+                    index,
+                    function: function.clone(),
+                    event: event.clone(),
+                    expected,
+                    actual,
+                });
+            }
+        }
+        mismatches
+    }
+
+    /// 回放全部事件，有不一致时panic
+    pub fn assert_same(&mut self) {
+        let mismatches = self.run();
+        if !mismatches.is_empty() {
+            let mismatches = mismatches.iter().map(|m| m.to_string()).collect::<Vec<_>>();
+            panic!("replay mismatched:\n{}", mismatches.join("\n"));
+        }
+    }
+}
+
+fn to_value(raw: &RawValue) -> Option<Value> {
+    match raw {
+        RawValue::Null => None,
+        RawValue::Int(i) => Some(Value::Int(*i)),
+        RawValue::Bool(b) => Some(Value::Bool(*b)),
+        RawValue::Str(s) => Some(Value::Str(s.clone())),
+    }
+}
+
+fn to_call(record: &Record) -> ApiCall {
+    match record {
+        Record::Call { function, args, result, .. } => ApiCall {
+            name: function.clone(),
+            // 空指针参数在模拟时为空字符串
+            args: args
+                .iter()
+                .map(|arg| to_value(arg).unwrap_or_else(|| Value::Str(String::new())))
+                .collect(),
+            result: to_value(result),
+        },
+        Record::Event { .. } => unreachable!(),
+    }
+}
+
+/// 去掉只读的调用
+fn side_effects(calls: Vec<ApiCall>) -> Vec<ApiCall> {
+    calls
+        .into_iter()
+        .filter(|call| !call.name.starts_with("get_") && !call.name.starts_with("can_"))
+        .collect()
+}
@@ -0,0 +1,62 @@
+#![cfg(feature = "testing")]
+
+use std::{thread, time::Duration};
+
+use coolq_sdk_rust::{
+    cache,
+    targets::group::Group,
+    testing::{Simulator, Value},
+};
+
+fn fetches(sim: &Simulator) -> usize {
+    sim.calls()
+        .iter()
+        .filter(|call| call.name == "get_group_info")
+        .count()
+}
+
+#[test]
+fn ttl_and_invalidation() {
+    let sim = Simulator::new();
+    sim.add_group(Group {
+        group_id: 1,
+        group_name: "a".to_owned(),
+        ..Default::default()
+    });
+    sim.add_group(Group {
+        group_id: 2,
+        group_name: "b".to_owned(),
+        ..Default::default()
+    });
+    cache::set_ttl(Duration::from_millis(200));
+    let before = cache::stats().groups;
+
+    assert_eq!(cache::get_group(1).unwrap().group_name, "a");
+    assert_eq!(cache::get_group(1).unwrap().group_name, "a");
+    assert_eq!(fetches(&sim), 1);
+    let stats = cache::stats().groups;
+    assert_eq!(stats.misses - before.misses, 1);
+    assert_eq!(stats.hits - before.hits, 1);
+    assert_eq!(stats.len, 1);
+
+    cache::invalidate_group(1);
+    assert_eq!(cache::stats().groups.len, 0);
+    cache::get_group(1).unwrap();
+    assert_eq!(fetches(&sim), 2);
+
+    // 过期后重新获取，插入时清理其他过期条目
+    thread::sleep(Duration::from_millis(300));
+    assert_eq!(cache::stats().groups.len, 0);
+    cache::get_group(2).unwrap();
+    assert_eq!(fetches(&sim), 3);
+    assert_eq!(cache::stats().groups.len, 1);
+
+    cache::set_ttl(Duration::from_secs(60));
+}
+
+#[test]
+fn decode_error() {
+    let sim = Simulator::new();
+    sim.on_call("get_group_info", |_| Some(Value::Str("invalid".to_owned())));
+    assert!(cache::get_group(3).is_err());
+}
@@ -0,0 +1,44 @@
+#![cfg(feature = "testing")]
+
+use coolq_sdk_rust::{
+    events::{listen_all, Event},
+    prelude::*,
+    testing::Simulator,
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static PRIVATE: AtomicUsize = AtomicUsize::new(0);
+static START: AtomicUsize = AtomicUsize::new(0);
+static NESTED: AtomicUsize = AtomicUsize::new(0);
+
+#[listener(priority = "high")]
+fn high(_event: PrivateMessageEvent) {}
+
+#[listener]
+fn medium(_event: PrivateMessageEvent) {}
+
+#[test]
+fn once_per_event() {
+    let sim = Simulator::new();
+    listen_all(|event| {
+        match event {
+            Event::PrivateMessage(_) => PRIVATE.fetch_add(1, Ordering::SeqCst),
+            Event::Start(_) => START.fetch_add(1, Ordering::SeqCst),
+            _ => 0,
+        };
+        // 回调中可以注册新的回调
+        if NESTED.fetch_add(1, Ordering::SeqCst) == 0 {
+            listen_all(|_| {});
+        }
+    });
+
+    let event = sim.private_message(10001, "hello");
+    event.catch_all();
+    event.clone().call(on_private_msg_high);
+    event.call(on_private_msg_medium);
+    assert_eq!(PRIVATE.load(Ordering::SeqCst), 1);
+
+    // 没有listener的事件
+    sim.start().catch_all();
+    assert_eq!(START.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,63 @@
+#![cfg(feature = "config")]
+
+use coolq_sdk_rust::config::{self, Config, Validate};
+use serde::{Deserialize, Serialize};
+use std::{fs, thread, time::Duration};
+
+#[derive(Default, Serialize, Deserialize)]
+struct Settings {
+    admins: Vec<i64>,
+    greeting: String,
+}
+
+impl Validate for Settings {}
+
+#[test]
+fn load_writes_default() {
+    let dir = std::env::temp_dir().join("cqrs_config_test");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("config.toml");
+
+    let config = Config::<Settings>::load(&path).unwrap();
+    assert!(fs::read_to_string(&path).unwrap().contains("greeting"));
+    assert!(config.get().admins.is_empty());
+
+    fs::write(&path, "admins = [10000]\ngreeting = \"hi\"\n").unwrap();
+    Config::<Settings>::load(&path).unwrap();
+    assert_eq!(config::get::<Settings>().admins, vec![10000]);
+    assert_eq!(config::get::<Settings>().greeting, "hi");
+}
+
+#[derive(Default, Serialize, Deserialize)]
+struct Watched {
+    greeting: String,
+}
+
+impl Validate for Watched {}
+
+#[test]
+fn watch_until_unwatch() {
+    let dir = std::env::temp_dir().join(format!("cqrs_config_watch_{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("watched.json");
+    fs::write(&path, r#"{"greeting": "a"}"#).unwrap();
+    Config::<Watched>::load(&path).unwrap();
+
+    config::watch(Duration::from_millis(10));
+    thread::sleep(Duration::from_millis(20));
+    fs::write(&path, r#"{"greeting": "b"}"#).unwrap();
+    for _ in 0..100 {
+        if config::get::<Watched>().greeting == "b" {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(config::get::<Watched>().greeting, "b");
+
+    config::unwatch();
+    fs::write(&path, r#"{"greeting": "c"}"#).unwrap();
+    thread::sleep(Duration::from_millis(50));
+    assert_eq!(config::get::<Watched>().greeting, "b");
+    fs::remove_dir_all(dir).unwrap();
+}
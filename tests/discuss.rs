@@ -0,0 +1,45 @@
+#![cfg(feature = "testing")]
+
+use coolq_sdk_rust::{
+    prelude::*,
+    testing::{Action, SentMessage, Simulator, Target},
+};
+
+#[listener]
+fn discuss_msg(event: DiscussMessageEvent) {
+    match event.get_message().raw_msg.as_str() {
+        "ping" => event.reply("pong").map(drop),
+        "at" => event.reply_at("hi").map(drop),
+        _ => event.leave().map(drop),
+    }
+    .ok();
+}
+
+#[test]
+#[allow(deprecated)]
+fn reply() {
+    let sim = Simulator::new();
+    sim.discuss_message(1000, 10001, "ping").call(on_discuss_msg_medium);
+    sim.discuss_message(1000, 10001, "at").call(on_discuss_msg_medium);
+    let sent = sim.sent();
+    assert_eq!(sent, vec![
+        SentMessage {
+            target: Target::Discuss(1000),
+            msg: "pong".to_owned(),
+            msg_id: sent[0].msg_id,
+        },
+        SentMessage {
+            target: Target::Discuss(1000),
+            msg: "[CQ:at,qq=10001]hi".to_owned(),
+            msg_id: sent[1].msg_id,
+        },
+    ]);
+
+    sim.clear();
+    sim.discuss_message(1000, 10001, "bye").call(on_discuss_msg_medium);
+    assert_eq!(sim.actions(), vec![Action::LeaveDiscuss { discuss_id: 1000 }]);
+
+    let msg = std::ffi::CString::new("hi").unwrap();
+    let event = DiscussMessageEvent::new(1, 7, 1000, 10001, msg.as_ptr(), 0);
+    assert_eq!((event.msg_id, event.discuss_id, event.user_id), (7, 1000, 10001));
+}
@@ -0,0 +1,10 @@
+use coolq_sdk_rust::events::{EventResult, IntoEventResult};
+
+#[test]
+fn into_event_result() {
+    assert_eq!(().into_event_result(), EventResult::Ignore);
+    assert_eq!(1.into_event_result(), EventResult::Block);
+    assert_eq!(0.into_event_result(), EventResult::Ignore);
+    assert_eq!(Ok::<_, String>(EventResult::Block).into_event_result(), EventResult::Block);
+    assert_eq!(i32::from(EventResult::Block), 1);
+}
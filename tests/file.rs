@@ -0,0 +1,27 @@
+use coolq_sdk_rust::targets::{File, FileType};
+
+fn file(name: &str, size: i64) -> File {
+    File {
+        id: String::new(),
+        name: name.to_owned(),
+        size,
+        busid: 0,
+    }
+}
+
+#[test]
+fn human_size() {
+    assert_eq!(file("a", 0).human_size(), "0 B");
+    assert_eq!(file("a", 1023).human_size(), "1023 B");
+    assert_eq!(file("a", 1024).human_size(), "1.0 KB");
+    assert_eq!(file("a", 1536 * 1024).human_size(), "1.5 MB");
+}
+
+#[test]
+fn file_type() {
+    assert_eq!(file("photo.JPG", 1).extension(), "jpg");
+    assert_eq!(file("photo.JPG", 1).file_type(), FileType::Image);
+    assert_eq!(file("backup.tar.gz", 1).file_type(), FileType::Archive);
+    assert_eq!(file(".bashrc", 1).file_type(), FileType::Other);
+    assert_eq!(file("README", 1).extension(), "");
+}
@@ -0,0 +1,57 @@
+#![cfg(feature = "testing")]
+
+use std::{sync::Mutex, time::Duration};
+
+use coolq_sdk_rust::{cache, prelude::*, testing::Simulator};
+
+static RESOLVED: Mutex<Vec<(bool, String)>> = Mutex::new(Vec::new());
+
+#[listener]
+fn group_msg(event: GroupMessageEvent) {
+    let mut resolved = RESOLVED.lock().unwrap();
+    resolved.push((
+        event.group.is_resolved(),
+        event.group.get().group_name.clone(),
+    ));
+    resolved.push((
+        event.group.is_resolved(),
+        event.group.get().group_name.clone(),
+    ));
+}
+
+#[listener]
+fn private_msg(event: PrivateMessageEvent) {
+    event.user.send_message(event.user.id()).ok();
+}
+
+fn count(sim: &Simulator, name: &str) -> usize {
+    sim.calls().iter().filter(|call| call.name == name).count()
+}
+
+#[test]
+fn resolve_once() {
+    let sim = Simulator::new();
+    sim.add_group(Group {
+        group_id: 123456,
+        group_name: "测试群".to_owned(),
+        ..Default::default()
+    });
+    // 关闭缓存，第二次get也不会再调用api
+    cache::set_ttl(Duration::from_millis(0));
+
+    sim.group_message(123456, 10001, "hello")
+        .call(on_group_msg_medium);
+    assert_eq!(
+        *RESOLVED.lock().unwrap(),
+        vec![(false, "测试群".to_owned()), (true, "测试群".to_owned()),]
+    );
+    assert_eq!(count(&sim, "get_group_info"), 1);
+
+    // 只用到id时不获取信息
+    sim.private_message(10001, "hello")
+        .call(on_private_msg_medium);
+    assert_eq!(count(&sim, "get_stranger_info"), 0);
+    assert_eq!(sim.sent()[0].msg, "10001");
+
+    cache::set_ttl(Duration::from_secs(60));
+}
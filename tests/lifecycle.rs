@@ -0,0 +1,61 @@
+//! 插件停用后再次启用
+
+use coolq_sdk_rust::{
+    events::{self, Event, EventResult, FriendAddEvent},
+    middleware::{self, Next},
+    module::{self, Module},
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static MIDDLEWARE_CALLS: AtomicUsize = AtomicUsize::new(0);
+static MODULE_ENABLES: AtomicUsize = AtomicUsize::new(0);
+static MODULE_EVENTS: AtomicUsize = AtomicUsize::new(0);
+
+fn counting(event: Event, next: Next<'_>) -> EventResult {
+    MIDDLEWARE_CALLS.fetch_add(1, Ordering::SeqCst);
+    next.run(event)
+}
+
+struct Counting;
+
+impl Module for Counting {
+    fn name(&self) -> &str {
+        "counting"
+    }
+
+    fn on_enable(&self) {
+        MODULE_ENABLES.fetch_add(1, Ordering::SeqCst);
+    }
+
+    fn on_event(&self, _event: &Event) -> EventResult {
+        MODULE_EVENTS.fetch_add(1, Ordering::SeqCst);
+        EventResult::Ignore
+    }
+}
+
+/// 与`main`导出的`on_enable`相同，每次启用都会注册
+fn on_enable() {
+    middleware::register(counting);
+    module::register(Counting);
+    coolq_sdk_rust::enable();
+}
+
+#[test]
+fn reenable() {
+    // 启用时调度器会读取状态文件，不能使用酷q的数据目录
+    #[cfg(feature = "scheduler")]
+    coolq_sdk_rust::scheduler::set_state_file(
+        std::env::temp_dir().join("cqrs_lifecycle_scheduler.json"),
+    );
+    for _ in 0..2 {
+        on_enable();
+        events::dispatch(FriendAddEvent::new(1, 0, 10001), |event| {
+            module::dispatch(event.into())
+        });
+        coolq_sdk_rust::disable();
+    }
+    assert_eq!(MIDDLEWARE_CALLS.load(Ordering::SeqCst), 2);
+    assert_eq!(MODULE_ENABLES.load(Ordering::SeqCst), 2);
+    assert_eq!(MODULE_EVENTS.load(Ordering::SeqCst), 2);
+    assert!(module::names().is_empty());
+}
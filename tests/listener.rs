@@ -0,0 +1,26 @@
+//! `#[listener]`的编译期检查
+
+use std::process::Command;
+
+/// `.stderr`由该版本的rustc生成，报错的格式在不同版本间可能不同，
+/// 其他版本只检查能否编译通过。更新版本后用`TRYBUILD=overwrite cargo test --test listener`重新生成
+const UI_RUSTC: &str = "1.95";
+
+fn rustc_version() -> String {
+    Command::new("rustc")
+        .arg("--version")
+        .output()
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default()
+}
+
+#[test]
+fn listener() {
+    let t = trybuild::TestCases::new();
+    t.pass("tests/ui/listener/pass.rs");
+    if rustc_version().starts_with(&format!("rustc {}.", UI_RUSTC)) {
+        t.compile_fail("tests/ui/listener/fail_*.rs");
+    } else {
+        eprintln!("skip compile_fail tests: the .stderr files are generated by rustc {}", UI_RUSTC);
+    }
+}
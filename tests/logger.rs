@@ -0,0 +1,41 @@
+#![cfg(feature = "logger")]
+
+use coolq_sdk_rust::logger::FileSink;
+use std::{fs, path::PathBuf};
+
+fn temp_dir(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cqrs-logger-{}-{}", name, std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+    dir
+}
+
+fn size(path: PathBuf) -> u64 {
+    fs::metadata(path).unwrap().len()
+}
+
+#[test]
+fn rotate() {
+    let dir = temp_dir("rotate");
+    let sink = FileSink::with_dir(&dir, "plugin").unwrap().max_size(200).keep(2);
+    for i in 0..50 {
+        sink.write("INFO", "test", &format!("message {}", i)).unwrap();
+    }
+    for file in &["plugin.log", "plugin.1.log", "plugin.2.log"] {
+        assert!(size(dir.join(file)) <= 200, "{} is too large", file);
+    }
+    assert!(!dir.join("plugin.3.log").exists());
+    assert!(fs::read_to_string(dir.join("plugin.log")).unwrap().contains("message 49"));
+    fs::remove_dir_all(dir).unwrap();
+}
+
+#[test]
+fn keep_none() {
+    let dir = temp_dir("keep-none");
+    let sink = FileSink::with_dir(&dir, "plugin").unwrap().max_size(200).keep(0);
+    for i in 0..50 {
+        sink.write("INFO", "test", &format!("message {}", i)).unwrap();
+    }
+    assert!(size(dir.join("plugin.log")) <= 200);
+    assert!(!dir.join("plugin.1.log").exists());
+    fs::remove_dir_all(dir).unwrap();
+}
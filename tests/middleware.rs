@@ -0,0 +1,46 @@
+use coolq_sdk_rust::{
+    events::{self, Event, EventResult, FriendAddEvent},
+    middleware::{self, Next},
+};
+use std::sync::Mutex;
+
+static CALLS: Mutex<Vec<&str>> = Mutex::new(Vec::new());
+
+fn call(name: &'static str) {
+    CALLS.lock().unwrap().push(name);
+}
+
+fn take_calls() -> Vec<&'static str> {
+    std::mem::take(&mut *CALLS.lock().unwrap())
+}
+
+fn friend_add(user_id: i64) -> i32 {
+    events::dispatch(FriendAddEvent::new(1, 0, user_id), |_| {
+        call("listener");
+        EventResult::Block
+    })
+}
+
+#[test]
+fn order_and_short_circuit() {
+    middleware::register(|event: Event, next: Next<'_>| {
+        call("first");
+        let result = next.run(event);
+        call("first after");
+        result
+    });
+    middleware::register(|event: Event, next: Next<'_>| {
+        call("blacklist");
+        if event.user_id() == Some(12345) {
+            EventResult::Ignore
+        } else {
+            next.run(event)
+        }
+    });
+
+    assert_eq!(friend_add(10001), 1);
+    assert_eq!(take_calls(), vec!["first", "blacklist", "listener", "first after"]);
+
+    assert_eq!(friend_add(12345), 0);
+    assert_eq!(take_calls(), vec!["first", "blacklist", "first after"]);
+}
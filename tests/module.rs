@@ -0,0 +1,41 @@
+use coolq_sdk_rust::{
+    events::{Event, EventResult, StartEvent},
+    module::{self, Module},
+};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+static CALLED: AtomicUsize = AtomicUsize::new(0);
+
+struct Blocker;
+
+impl Module for Blocker {
+    fn name(&self) -> &str {
+        "blocker"
+    }
+
+    fn on_event(&self, _event: &Event) -> EventResult {
+        CALLED.fetch_add(1, Ordering::SeqCst);
+        EventResult::Block
+    }
+}
+
+struct Unreachable;
+
+impl Module for Unreachable {
+    fn name(&self) -> &str {
+        "unreachable"
+    }
+
+    fn on_event(&self, _event: &Event) -> EventResult {
+        panic!("event should be blocked by the previous module")
+    }
+}
+
+#[test]
+fn dispatch_in_order() {
+    module::register(Blocker);
+    module::register(Unreachable);
+    assert_eq!(module::names(), vec!["blocker", "unreachable"]);
+    assert_eq!(module::dispatch(Event::Start(StartEvent::new())), EventResult::Block);
+    assert_eq!(CALLED.load(Ordering::SeqCst), 1);
+}
@@ -0,0 +1,134 @@
+#![cfg(all(feature = "onebot", feature = "testing"))]
+
+use std::{
+    io::{Read, Write},
+    net::TcpStream,
+    thread,
+    time::Duration,
+};
+
+use coolq_sdk_rust::{
+    events::EventResult,
+    module,
+    onebot::{self, OneBot},
+    testing::{Action, SentMessage, Simulator, Target},
+};
+use serde_json::{json, Value};
+use tungstenite::Message;
+
+const ONEBOT: OneBot = OneBot {
+    http: Some("127.0.0.1:0"),
+    ws: Some("127.0.0.1:0"),
+    access_token: Some("secret"),
+};
+
+fn post(path: &str, body: &str, token: Option<&str>) -> (u16, String) {
+    let mut stream = TcpStream::connect(onebot::http_addr().unwrap()).unwrap();
+    let authorization = token
+        .map(|token| format!("Authorization: Bearer {}\r\n", token))
+        .unwrap_or_default();
+    write!(
+        stream,
+        "POST {} HTTP/1.1\r\nHost: localhost\r\nConnection: close\r\nContent-Type: \
+         application/json\r\nContent-Length: {}\r\n{}\r\n{}",
+        path,
+        body.len(),
+        authorization,
+        body
+    )
+    .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).unwrap();
+    let status = response[9..12].parse().unwrap();
+    let body = response.split("\r\n\r\n").nth(1).unwrap_or_default().to_owned();
+    (status, body)
+}
+
+fn read_json(ws: &mut tungstenite::WebSocket<impl Read + Write>) -> Value {
+    match ws.read_message().unwrap() {
+        Message::Text(text) => serde_json::from_str(&text).unwrap(),
+        msg => panic!("unexpected message: {:?}", msg),
+    }
+}
+
+#[test]
+fn onebot() {
+    let sim = Simulator::new();
+    // 与`#[coolq_sdk_rust::main(modules(ONEBOT))]`相同
+    module::register(ONEBOT);
+    coolq_sdk_rust::enable();
+
+    let (status, _) = post("/send_group_msg", r#"{"group_id": 123456, "message": "hi"}"#, None);
+    assert_eq!(status, 401);
+    let (status, _) = post("/send_group_msg", "{}", Some("wrong"));
+    assert_eq!(status, 403);
+    let (status, body) = post(
+        "/send_group_msg",
+        r#"{"group_id": 123456, "message": [{"type": "text", "data": {"text": "hi"}}]}"#,
+        Some("secret"),
+    );
+    assert_eq!(status, 200);
+    let sent = sim.sent();
+    assert_eq!(sent, vec![SentMessage {
+        target: Target::Group(123456),
+        msg: "hi".to_owned(),
+        msg_id: sent[0].msg_id,
+    }]);
+    let response: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["data"]["message_id"], sent[0].msg_id);
+    let (status, _) = post("/unknown_action", "{}", Some("secret"));
+    assert_eq!(status, 404);
+
+    sim.clear();
+    let (_, body) = post(
+        "/send_private_msg_async",
+        r#"{"user_id": 10001, "message": "hi"}"#,
+        Some("secret"),
+    );
+    let response: Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["status"], "async");
+    for _ in 0..100 {
+        if !sim.sent().is_empty() {
+            break;
+        }
+        thread::sleep(Duration::from_millis(10));
+    }
+    assert_eq!(sim.sent()[0].target, Target::Private(10001));
+
+    let url = format!("ws://{}/?access_token=secret", onebot::ws_addr().unwrap());
+    let (mut ws, _) = tungstenite::connect(url).unwrap();
+    let connect = read_json(&mut ws);
+    assert_eq!(connect["meta_event_type"], "lifecycle");
+    assert_eq!(connect["sub_type"], "connect");
+
+    sim.clear();
+    let action = json!({
+        "action": "set_group_ban",
+        "params": { "group_id": 123456, "user_id": 10001, "duration": 600 },
+        "echo": "ban",
+    });
+    ws.write_message(Message::Text(action.to_string())).unwrap();
+    let response = read_json(&mut ws);
+    assert_eq!(response["retcode"], 0);
+    assert_eq!(response["echo"], "ban");
+    assert_eq!(sim.actions(), vec![Action::Ban {
+        group_id: 123456,
+        user_id: 10001,
+        time: 600
+    }]);
+
+    let result = sim.group_message(123456, 10001, "hello").module();
+    assert_eq!(result, EventResult::Ignore);
+    let event = read_json(&mut ws);
+    assert_eq!(event["post_type"], "message");
+    assert_eq!(event["message_type"], "group");
+    assert_eq!(event["group_id"], 123456);
+    assert_eq!(event["user_id"], 10001);
+    assert_eq!(event["message"], "hello");
+    assert_eq!(event["self_id"], 10000);
+
+    coolq_sdk_rust::disable();
+    assert!(onebot::http_addr().is_none());
+    drop(sim);
+}
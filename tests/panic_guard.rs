@@ -0,0 +1,30 @@
+#![cfg(feature = "testing")]
+
+use coolq_sdk_rust::{
+    events::{self, EventResult, FriendAddEvent},
+    panic_guard::{self, PanicPolicy},
+    testing::Simulator,
+};
+
+fn panicking_listener() -> i32 {
+    events::dispatch(FriendAddEvent::new(1, 0, 10001), |_| -> EventResult {
+        panic!("boom")
+    })
+}
+
+#[test]
+fn fatal_after_n_panics() {
+    let sim = Simulator::new();
+    panic_guard::set_policy(PanicPolicy::After(2));
+    let fatal = || sim.calls().iter().filter(|call| call.name == "set_fatal").count();
+
+    assert_eq!(panicking_listener(), 0);
+    assert_eq!(panic_guard::panic_count(), 1);
+    assert_eq!(fatal(), 0);
+
+    assert_eq!(panicking_listener(), 0);
+    assert_eq!(fatal(), 1);
+    assert_eq!(panicking_listener(), 0);
+    assert_eq!(fatal(), 2);
+    assert_eq!(sim.logs().iter().filter(|log| log.tag == "panic").count(), 3);
+}
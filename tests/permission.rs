@@ -0,0 +1,112 @@
+use coolq_sdk_rust::{
+    permission::{self, Role, GROUP_ADMIN, GROUP_MEMBER},
+    targets::user::{Authority, User},
+};
+#[cfg(feature = "testing")]
+use coolq_sdk_rust::{
+    permission::GROUP_OWNER,
+    targets::{
+        group::{GroupMember, GroupRole},
+        user::UserSex,
+    },
+    testing::Simulator,
+};
+
+fn user(user_id: i64, authority: Authority) -> User {
+    let mut user = User::default();
+    user.user_id = user_id;
+    user.authority = authority;
+    user
+}
+
+#[test]
+fn master_before_super_admin() {
+    User::add_super_admin(1001);
+    User::add_master(1001);
+    assert_eq!(Authority::new(1001), Authority::Master);
+    assert!(permission::check(&user(1001, Authority::User), None, "anything.at.all"));
+}
+
+#[test]
+fn wildcard_and_deny() {
+    permission::add_role(Role::new("test.fun").node("fun.*").node("-fun.dice"));
+    permission::grant(2001, None, "test.fun");
+    let u = user(2001, Authority::User);
+    assert!(permission::check(&u, None, "fun.rps"));
+    assert!(permission::check(&u, None, "fun"));
+    assert!(!permission::check(&u, None, "fun.dice"));
+    assert!(!permission::check(&u, None, "funny"));
+
+    permission::revoke(2001, None, "test.fun");
+    assert!(!permission::check(&u, None, "fun.rps"));
+}
+
+#[cfg(feature = "testing")]
+fn member(group_id: i64, user_id: i64, role: GroupRole) -> GroupMember {
+    GroupMember {
+        group_id,
+        user_id,
+        nickname: String::new(),
+        card: String::new(),
+        sex: UserSex::Unknown,
+        age: 0,
+        area: String::new(),
+        join_time: 0,
+        last_sent_time: 0,
+        level: String::new(),
+        role,
+        unfriendly: false,
+        title: String::new(),
+        title_expire_time: 0,
+        card_changeable: false,
+        authority: Authority::User,
+    }
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn group_scoped_grant() {
+    let _sim = Simulator::new();
+    permission::add_role(Role::new("test.mod").node("moderation.ban"));
+    permission::grant(3001, Some(1), "test.mod");
+    let u = user(3001, Authority::User);
+    assert!(permission::check(&u, Some(1), "moderation.ban"));
+    assert!(!permission::check(&u, Some(2), "moderation.ban"));
+    assert!(!permission::check(&u, None, "moderation.ban"));
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn group_role_inheritance() {
+    let sim = Simulator::new();
+    sim.add_member(member(1, 4001, GroupRole::Owner))
+        .add_member(member(2, 4001, GroupRole::Member))
+        .add_member(member(1, 4002, GroupRole::Member));
+    permission::add_role(Role::new(GROUP_MEMBER).node("test.chat"));
+    permission::add_role(Role::new(GROUP_ADMIN).inherit(GROUP_MEMBER).node("test.kick"));
+    // 从群1的事件中得到的User
+    let owner = user(4001, Authority::GroupOwner);
+    assert!(permission::check(&owner, Some(1), "test.kick"));
+    assert!(permission::check(&owner, Some(1), "test.chat"));
+    assert!(!permission::check(&owner, None, "test.kick"));
+    // 在群2只是群员
+    assert!(!permission::check(&owner, Some(2), "test.kick"));
+    assert!(permission::check(&owner, Some(2), "test.chat"));
+    // 不在群3
+    assert!(!permission::check(&owner, Some(3), "test.chat"));
+
+    let member = user(4002, Authority::User);
+    assert!(permission::check(&member, Some(1), "test.chat"));
+    assert!(!permission::check(&member, Some(1), "test.kick"));
+}
+
+#[cfg(feature = "testing")]
+#[test]
+fn super_admin_group_role() {
+    let _sim = Simulator::new();
+    permission::add_role(Role::new(GROUP_OWNER).inherit(GROUP_ADMIN).node("test.owner"));
+    User::add_super_admin(5001);
+    let u = user(5001, Authority::SuperAdmin);
+    assert!(permission::check(&u, Some(1), "test.owner"));
+    assert!(!permission::check(&u, None, "test.owner"));
+}
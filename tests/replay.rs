@@ -0,0 +1,83 @@
+#![cfg(all(feature = "testing", feature = "recorder"))]
+
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use coolq_sdk_rust::{
+    prelude::*,
+    recorder::{self, Record},
+    testing::{replay::Replayer, PrivateMessage, Simulator},
+};
+
+static POLITE: AtomicBool = AtomicBool::new(true);
+
+#[listener(priority = "high")]
+fn log_msg(event: &PrivateMessageEvent) {
+    api::add_log(CQLogLevel::DEBUG, "msg", event.get_message().raw_msg.clone()).ok();
+}
+
+#[listener]
+fn private_msg(event: PrivateMessageEvent) {
+    if POLITE.load(Ordering::SeqCst) {
+        event.reply("你好").ok();
+    } else {
+        event.reply("hi").ok();
+    }
+}
+
+#[test]
+fn record_and_replay() {
+    let path = std::env::temp_dir().join("coolq-sdk-rust-replay.jsonl");
+    std::fs::remove_file(&path).ok();
+
+    let sim = Simulator::new();
+    recorder::start_with(&path).unwrap();
+    for msg in &["hello", "在吗"] {
+        let event = sim.private_message(10001, msg);
+        event.clone().call(on_private_msg_high);
+        event.call(on_private_msg_medium);
+    }
+    recorder::stop();
+    drop(sim);
+
+    // 同一事件的两个listener只记录一次
+    let records = recorder::read(&path).unwrap();
+    let functions = records.iter().map(Record::function).collect::<Vec<_>>();
+    assert_eq!(functions, vec![
+        "on_private_msg",
+        "add_log",
+        "send_private_msg",
+        "on_private_msg",
+        "add_log",
+        "send_private_msg",
+    ]);
+    assert_eq!(records[0].args()[3], RawValue::Str("hello".to_owned()));
+
+    let mut replayer = Replayer::new(records);
+    replayer
+        .on::<PrivateMessage>(on_private_msg_high)
+        .on::<PrivateMessage>(on_private_msg_medium)
+        .assert_same();
+
+    POLITE.store(false, Ordering::SeqCst);
+    let mismatches = replayer.run();
+    assert_eq!(mismatches.len(), 2);
+    assert_eq!(mismatches[0].index, 0);
+    assert_eq!(mismatches[0].expected[1].args[1].as_str(), "你好");
+    assert_eq!(mismatches[0].actual[1].args[1].as_str(), "hi");
+
+    drop(replayer);
+
+    // 参数完全相同的两次分发都要记录
+    let sim = Simulator::new();
+    recorder::start_with(&path).unwrap();
+    let event = sim.private_message(10001, "hello");
+    for _ in 0..2 {
+        event.clone().call(on_private_msg_high);
+        event.clone().call(on_private_msg_medium);
+    }
+    recorder::stop();
+    let records = recorder::read(&path).unwrap()[6..].to_vec();
+    let events = records.iter().filter(|r| matches!(r, Record::Event(_))).count();
+    assert_eq!(events, 2);
+    assert_eq!(records[0].args(), records[3].args());
+}
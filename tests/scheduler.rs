@@ -0,0 +1,43 @@
+#![cfg(feature = "scheduler")]
+
+use coolq_sdk_rust::scheduler;
+use std::{
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        Arc,
+    },
+    thread::sleep,
+    time::Duration,
+};
+
+#[test]
+fn interval_and_cancel() {
+    let dir = std::env::temp_dir().join("cqrs_scheduler_test");
+    std::fs::create_dir_all(&dir).unwrap();
+    let state = dir.join("scheduler.json");
+    let _ = std::fs::remove_file(&state);
+    scheduler::set_state_file(&state);
+
+    assert!(scheduler::cron("bad", "not a cron", || {}).is_err());
+
+    let count = Arc::new(AtomicUsize::new(0));
+    let c = count.clone();
+    let handle = scheduler::interval("tick", Duration::from_millis(50), move || {
+        c.fetch_add(1, Ordering::SeqCst);
+    })
+    .unwrap();
+    assert!(scheduler::interval("tick", Duration::from_secs(1), || {}).is_err());
+
+    scheduler::start();
+    sleep(Duration::from_millis(300));
+    handle.cancel();
+    sleep(Duration::from_millis(100));
+    let fired = count.load(Ordering::SeqCst);
+    assert!(fired >= 2, "fired {} times", fired);
+    sleep(Duration::from_millis(200));
+    assert_eq!(count.load(Ordering::SeqCst), fired);
+    assert!(std::fs::read_to_string(&state).unwrap().contains("tick"));
+
+    scheduler::stop();
+    assert!(!scheduler::is_running());
+}
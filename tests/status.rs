@@ -0,0 +1,49 @@
+use coolq_sdk_rust::status::{frame, Status, StatusColor};
+
+/// i16长度 + GB18030的data、unit，i32的颜色，整体base64
+#[test]
+#[cfg(windows)]
+#[ignore = "需要酷q目录下的libiconv.dll"]
+fn encode_gb18030() {
+    let status = Status::new(42).unit("秒").color(StatusColor::Red);
+    let encoded = status.encode().unwrap();
+    assert_eq!(encoded, "AAI0MgACw+sAAAAD");
+    assert_eq!(base64::decode(&encoded).unwrap(), vec![
+        0x00, 0x02, b'4', b'2', 0x00, 0x02, 0xc3, 0xeb, 0x00, 0x00, 0x00, 0x03
+    ]);
+}
+
+/// 不经过iconv，只检查长度和颜色的格式
+#[test]
+fn frame_layout() {
+    assert_eq!(base64::decode(&frame(b"42", &[0xc3, 0xeb], StatusColor::Red)).unwrap(), vec![
+        0x00, 0x02, b'4', b'2', 0x00, 0x02, 0xc3, 0xeb, 0x00, 0x00, 0x00, 0x03
+    ]);
+    assert_eq!(frame(b"", b"", StatusColor::Green), "AAAAAAAAAAE=");
+    assert_eq!(
+        base64::decode(&frame(b"", b"", StatusColor::Gray)).unwrap()[4..],
+        [0x00, 0x00, 0x00, 0x06]
+    );
+}
+
+/// 超过i16::MAX字节时截断，长度不会变成负数
+#[test]
+fn frame_clamp() {
+    let data = vec![b'a'; 40000];
+    let b = base64::decode(&frame(&data, b"x", StatusColor::Green)).unwrap();
+    assert_eq!(&b[..2], &[0x7f, 0xff]);
+    assert_eq!(b.len(), 2 + 32767 + 2 + 1 + 4);
+    assert_eq!(&b[2 + 32767..2 + 32767 + 3], &[0x00, 0x01, b'x']);
+}
+
+/// 模拟时字符串不转换编码
+#[test]
+#[cfg(feature = "testing")]
+fn encode_layout() {
+    let _sim = coolq_sdk_rust::testing::Simulator::new();
+    let status = Status::new(42).unit("秒").color(StatusColor::Red);
+    assert_eq!(base64::decode(&status.encode().unwrap()).unwrap(), vec![
+        0x00, 0x02, b'4', b'2', 0x00, 0x03, 0xe7, 0xa7, 0x92, 0x00, 0x00, 0x00, 0x03
+    ]);
+    assert_eq!(Status::new("").encode().unwrap(), "AAAAAAAAAAE=");
+}
@@ -0,0 +1,35 @@
+#![cfg(feature = "storage")]
+
+use coolq_sdk_rust::storage::{FileStorage, Scope, Store};
+use std::{collections::HashMap, fs};
+
+#[test]
+fn file_storage() {
+    let dir = std::env::temp_dir().join(format!("cqrs_storage_test_{}", std::process::id()));
+    let _ = fs::remove_dir_all(&dir);
+
+    let store = Store::new(FileStorage::with_dir(&dir).unwrap().compact_after(2));
+    let mut settings = HashMap::new();
+    settings.insert("welcome".to_owned(), true);
+    store.set(Scope::Group(1), "settings", &settings).unwrap();
+    assert!(dir.join("group_1.journal").exists());
+    assert!(!dir.join("group_1.json").exists());
+    store.set(Scope::Group(1), "settings", &settings).unwrap();
+    assert!(!dir.join("group_1.journal").exists());
+    assert_eq!(store.update(Scope::User(2), "count", |c: &mut i32| *c += 1).unwrap(), 1);
+    assert!(store.remove(Scope::User(2), "count").unwrap());
+    assert!(!store.contains(Scope::User(2), "count").unwrap());
+
+    // 模拟写入journal途中退出
+    fs::write(dir.join("group_1.journal"), "{\"key\":\"name\",\"value\":\"test\"}\n{\"key\":").unwrap();
+
+    let store = Store::new(FileStorage::with_dir(&dir).unwrap());
+    assert_eq!(
+        store.get::<HashMap<String, bool>>(Scope::Group(1), "settings").unwrap(),
+        Some(settings)
+    );
+    assert_eq!(store.get::<String>(Scope::Group(1), "name").unwrap().as_deref(), Some("test"));
+    assert!(!dir.join("group_1.journal").exists());
+    assert_eq!(store.keys(Scope::User(2)).unwrap(), Vec::<String>::new());
+    fs::remove_dir_all(dir).unwrap();
+}
@@ -0,0 +1,17 @@
+use coolq_sdk_rust::events::{GroupBanType, PrivateMessageType};
+
+#[test]
+fn unknown_round_trip() {
+    for i in &[1, 2, 3, 11] {
+        let t = PrivateMessageType::from(*i);
+        assert!(!matches!(t, PrivateMessageType::Unknown(_)));
+        assert_eq!(i32::from(t), *i);
+    }
+    assert_eq!(PrivateMessageType::from(99), PrivateMessageType::Unknown(99));
+    assert_eq!(i32::from(PrivateMessageType::Unknown(99)), 99);
+    assert_eq!(GroupBanType::from(-1), GroupBanType::Unknown(-1));
+    assert_eq!(i32::from(GroupBanType::from(-1)), -1);
+    #[allow(deprecated)]
+    let other = PrivateMessageType::Other;
+    assert_eq!(i32::from(other), 0);
+}
@@ -0,0 +1,96 @@
+#![cfg(feature = "testing")]
+
+use coolq_sdk_rust::{
+    prelude::*,
+    testing::{
+        console::{self, Console},
+        Action, SentMessage, Simulator, Target,
+    },
+};
+
+#[listener]
+fn group_msg(event: GroupMessageEvent) -> EventResult {
+    if event.get_message().raw_msg.contains("广告") {
+        event.get_message().delete();
+        event.group.get().set_ban(event.user.id(), 600).ok();
+        EventResult::Block
+    } else {
+        event.reply(format!("欢迎来到{}", event.group.get().group_name)).ok();
+        EventResult::Ignore
+    }
+}
+
+#[listener]
+fn group_request(event: AddGroupRequestEvent) {
+    event.handle(event.msg == "暗号", "").ok();
+}
+
+#[test]
+fn simulate() {
+    let sim = Simulator::new();
+    sim.add_group(Group {
+        group_id: 123456,
+        group_name: "测试群".to_owned(),
+        ..Default::default()
+    });
+
+    let result = sim.group_message(123456, 10001, "hello").call(on_group_msg_medium);
+    assert_eq!(result, EventResult::Ignore);
+    assert_eq!(sim.sent(), vec![SentMessage {
+        target: Target::Group(123456),
+        msg: "欢迎来到测试群".to_owned(),
+        msg_id: sim.sent()[0].msg_id,
+    }]);
+
+    sim.clear();
+    let spam = sim.group_message(123456, 10001, "广告");
+    let msg_id = spam.msg_id;
+    assert_eq!(spam.call(on_group_msg_medium), EventResult::Block);
+    assert_eq!(sim.actions(), vec![
+        Action::DeleteMessage { msg_id },
+        Action::Ban { group_id: 123456, user_id: 10001, time: 600 },
+    ]);
+
+    sim.clear();
+    let request = sim.add_group_request(123456, 10002, "暗号");
+    let flag = request.flag.clone();
+    request.call(on_add_group_request_medium);
+    assert_eq!(sim.actions(), vec![Action::GroupRequest {
+        flag,
+        request: 1,
+        approve: true,
+        reason: String::new(),
+    }]);
+}
+
+#[test]
+fn console_script() {
+    let mut console = Console::new();
+    console.simulator().add_group(Group {
+        group_id: 123456,
+        group_name: "测试群".to_owned(),
+        ..Default::default()
+    });
+    console.on_group(on_group_msg_medium).assert_script(
+        r#"
+        # 私聊没有注册listener
+        > hello
+        > /group 123456
+        > hello
+        < [group 123456] 欢迎来到测试群
+        > /user 10002
+        > 广告
+        < * DeleteMessage { msg_id: 4 }
+        < * Ban { group_id: 123456, user_id: 10002, time: 600 }
+        "#,
+    );
+    assert!(console.run_script("> hello\n< [group 123456] hi").is_err());
+}
+
+#[test]
+fn render() {
+    assert_eq!(
+        console::render("[CQ:at,qq=10001] &#91;hi&#93; [CQ:face,id=1][CQ:image,file=a.png]"),
+        "@10001 [hi] [表情1][图片 a.png]"
+    );
+}
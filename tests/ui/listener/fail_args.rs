@@ -0,0 +1,12 @@
+use coolq_sdk_rust::prelude::*;
+
+#[listener(prioriy = "high")]
+fn typo(_event: GroupMessageEvent) {}
+
+#[listener(priority = "urgent")]
+fn priority(_event: GroupMessageEvent) {}
+
+#[listener(priority = 1)]
+fn not_string(_event: GroupMessageEvent) {}
+
+fn main() {}
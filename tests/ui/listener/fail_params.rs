@@ -0,0 +1,15 @@
+use coolq_sdk_rust::prelude::*;
+
+#[listener]
+fn no_param() {}
+
+#[listener]
+fn two_params(_event: GroupMessageEvent, _n: i32) {}
+
+#[listener]
+fn not_path(_event: (GroupMessageEvent,)) {}
+
+#[listener]
+fn generic<T>(_event: GroupMessageEvent) {}
+
+fn main() {}
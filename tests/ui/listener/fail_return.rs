@@ -0,0 +1,20 @@
+use coolq_sdk_rust::prelude::*;
+
+#[listener]
+fn string(_event: GroupMessageEvent) -> String {
+    String::new()
+}
+
+struct Result;
+
+#[listener]
+fn not_std_result(_event: GroupMessageEvent) -> Result {
+    Result
+}
+
+#[listener]
+fn result_of_string(_event: PrivateMessageEvent) -> std::result::Result<String, String> {
+    Ok(String::new())
+}
+
+fn main() {}
@@ -0,0 +1,11 @@
+use coolq_sdk_rust::prelude::*;
+
+type Message = GroupMessageEvent;
+
+#[listener]
+fn alias(_event: Message) {}
+
+#[listener(event = "MessageEvent")]
+fn unknown(_event: Message) {}
+
+fn main() {}
@@ -0,0 +1,23 @@
+use coolq_sdk_rust::{events, prelude::*};
+
+type Message = GroupMessageEvent;
+
+#[listener]
+fn full_path(_event: events::PrivateMessageEvent) {}
+
+#[listener(priority = "high")]
+fn reference(_event: &GroupMessageEvent) -> EventResult {
+    EventResult::Ignore
+}
+
+#[listener(event = "GroupMessageEvent", priority = "low")]
+fn alias(_event: &mut Message) -> Result<EventResult, String> {
+    Ok(EventResult::Block)
+}
+
+#[listener]
+fn io_result(_event: FriendAddEvent) -> std::io::Result<()> {
+    Ok(())
+}
+
+fn main() {}